@@ -0,0 +1,73 @@
+/*
+ * C API of riichi-tools-rs, built with `cargo build --release --features ffi`
+ * (target/release/libriichi_tools_rs.so, .dylib or riichi_tools_rs.dll).
+ *
+ * Hands and tables are opaque handles. Strings returned by the library are
+ * UTF-8, owned by the caller and freed with riichi_string_free.
+ *
+ * Functions return RIICHI_OK, a negative RIICHI_ERR_* code when the API is
+ * misused, or the positive code of the library error (for example 131 for a
+ * wrong meld index). riichi_last_error has the details of the last library
+ * error on the calling thread.
+ */
+
+#ifndef RIICHI_H
+#define RIICHI_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define RIICHI_OK 0
+/* a required pointer was null */
+#define RIICHI_ERR_NULL -1
+/* a string was not valid UTF-8 */
+#define RIICHI_ERR_UTF8 -2
+/* the library panicked, which is a bug */
+#define RIICHI_ERR_PANIC -3
+
+typedef struct RiichiHand RiichiHand;
+typedef struct RiichiTable RiichiTable;
+
+/* Parses a hand in the mpsz notation, like "123m456p789s11z(p5z1)". */
+int riichi_hand_from_text(const char *text, RiichiHand **hand);
+void riichi_hand_free(RiichiHand *hand);
+
+/* The hand in the mpsz notation. */
+int riichi_hand_to_text(const RiichiHand *hand, char **text);
+
+/* Shanten of the hand, -1 is a complete hand. */
+int riichi_hand_shanten(RiichiHand *hand, int8_t *shanten);
+
+/* Tiles that lower the shanten for every discard, as JSON:
+ * [{"discard": "5z", "tiles": [{"tile": "1m", "count": 3}], "total": 3}] */
+int riichi_hand_ukeire(RiichiHand *hand, char **json);
+
+/* Waits of a 13 tile tenpai hand as JSON [{"tile": "1z", "count": 2}],
+ * an empty list when the hand is not tenpai. */
+int riichi_hand_waits(RiichiHand *hand, char **json);
+
+/* Creates a table from a JSON object, for example
+ * {"my_hand": "123m456p789s11155z", "my_tsumo": true, "my_riichi": true} */
+int riichi_table_from_json(const char *json, RiichiTable **table);
+void riichi_table_free(RiichiTable *table);
+
+/* Yaku and score of my hand on the table as JSON
+ * {"yaku": ["Riichi"], "han": 1, "fu": 40, "points": 1300},
+ * null when the hand has no yaku. */
+int riichi_table_score(RiichiTable *table, char **json);
+
+/* The last library error on this thread as JSON
+ * {"code": 131, "message": "...", "offset": 14, "token": "(p1z4)"},
+ * NULL when the last call did not end with a library error. */
+char *riichi_last_error(void);
+
+void riichi_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RIICHI_H */
@@ -0,0 +1,385 @@
+//! Command-line hand analyzer, built with `cargo build --features cli`.
+//!
+//! riichi shanten 123m456p789s1135z
+//! riichi score 123m456p789s11155z --tsumo --riichi --dora 4z --json
+
+use clap::{Parser, Subcommand};
+use riichi_tools_rs::riichi::analysis::{self, TileCount};
+use riichi_tools_rs::riichi::hand::Hand;
+use riichi_tools_rs::riichi::riichi_error::{IllegalAction, ParseError, RiichiError};
+use riichi_tools_rs::riichi::round::{Round, RoundEvent, RoundLog};
+use riichi_tools_rs::riichi::table::Table;
+use riichi_tools_rs::riichi::tile::Tile;
+use serde_json::{json, Map, Value};
+use std::error::Error;
+use std::fs;
+use std::process;
+
+#[derive(Parser)]
+#[command(name = "riichi", about = "Riichi mahjong hand analysis")]
+struct Cli {
+    /// Print the result as JSON
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Shanten of a hand, -1 is a complete hand
+    Shanten { hand: String },
+    /// Tiles that lower the shanten, for every discard of a 14 tile hand
+    Ukeire { hand: String },
+    /// Yaku and points of a complete hand, the last tile is the winning tile
+    Score {
+        hand: String,
+        /// Won by ron (the default)
+        #[arg(long, conflicts_with = "tsumo")]
+        ron: bool,
+        /// Won by tsumo
+        #[arg(long)]
+        tsumo: bool,
+        #[arg(long)]
+        riichi: bool,
+        /// Dora indicators, for example 4z9m
+        #[arg(long)]
+        dora: Option<String>,
+        /// 1 = east (dealer) - 4 = north
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=4))]
+        seat_wind: Option<u8>,
+        /// 1 = east - 4 = north
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=4))]
+        prevalent_wind: Option<u8>,
+    },
+    /// Waits of a 13 tile tenpai hand
+    Waits { hand: String },
+    /// Replays a round log (JSON with the starting state, rules and events) and prints what happened
+    Replay { file: String },
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    match run(&cli) {
+        Ok(output) => println!("{}", output),
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    }
+}
+
+fn run(cli: &Cli) -> Result<String, Box<dyn Error>> {
+    let (value, text) = match &cli.command {
+        Command::Shanten { hand } => shanten(hand)?,
+        Command::Ukeire { hand } => ukeire(hand)?,
+        Command::Score {
+            hand,
+            ron,
+            tsumo,
+            riichi,
+            dora,
+            seat_wind,
+            prevalent_wind,
+        } => {
+            let mut table = Table::from_map(&Map::new())?;
+            table.set_my_hand(Hand::from_text(hand, false)?);
+            // ron is the default, the two flags can't be given together
+            table.set_my_tsumo(*tsumo && !*ron);
+            table.set_my_riichi(*riichi);
+            if let Some(dora) = dora {
+                table.set_dora_indicators(Tile::list_from_text(dora)?);
+            }
+            if let Some(wind) = seat_wind {
+                table.set_my_seat_wind(*wind);
+            }
+            if let Some(wind) = prevalent_wind {
+                table.set_prevalent_wind(*wind);
+            }
+
+            score(&mut table)?
+        }
+        Command::Waits { hand } => waits(hand)?,
+        Command::Replay { file } => replay(&fs::read_to_string(file)?)?,
+    };
+
+    if cli.json {
+        return Ok(value.to_string());
+    }
+
+    Ok(text)
+}
+
+fn shanten(hand: &str) -> Result<(Value, String), RiichiError> {
+    let shanten = Hand::from_text(hand, false)?.shanten();
+
+    let text = match shanten {
+        -1 => "complete".to_string(),
+        0 => "tenpai".to_string(),
+        _ => format!("{}-shanten", shanten),
+    };
+
+    Ok((json!(shanten), text))
+}
+
+fn ukeire(hand: &str) -> Result<(Value, String), RiichiError> {
+    let mut hand = Hand::from_text(hand, false)?;
+    let options = analysis::ukeire(&mut hand);
+
+    let lines: Vec<String> = options
+        .iter()
+        .map(|option| {
+            let tiles = format!("{} - {} tiles", tile_counts(&option.tiles), option.total);
+            match option.discard {
+                None => tiles,
+                Some(discard) => format!("discard {}: {}", discard, tiles),
+            }
+        })
+        .collect();
+
+    Ok((json!(options), lines.join("\n")))
+}
+
+fn score(table: &mut Table) -> Result<(Value, String), RiichiError> {
+    if table.get_my_hand().clone().shanten() != -1 {
+        return Err(IllegalAction::HandNotComplete.into());
+    }
+
+    let summary = match analysis::score(table) {
+        Some(summary) => summary,
+        None => return Ok((Value::Null, "no yaku".to_string())),
+    };
+
+    let mut lines: Vec<String> = summary
+        .yaku
+        .iter()
+        .map(|yaku| format!("{} {} han", yaku.get_name(), yaku.get_han(table)))
+        .collect();
+
+    let dora = table.count_my_dora();
+    if dora > 0 && !summary.yaku.iter().any(|yaku| yaku.is_yakuman()) {
+        lines.push(format!("Dora {} han", dora));
+    }
+    lines.push(format!(
+        "{} han {} fu: {}",
+        summary.han, summary.fu, summary.points
+    ));
+
+    Ok((json!(summary), lines.join("\n")))
+}
+
+fn waits(hand: &str) -> Result<(Value, String), RiichiError> {
+    let mut hand = Hand::from_text(hand, false)?;
+    let waits = analysis::waits(&mut hand);
+
+    let text = if waits.is_empty() {
+        "not tenpai".to_string()
+    } else {
+        tile_counts(&waits)
+    };
+
+    Ok((json!(waits), text))
+}
+
+fn replay(content: &str) -> Result<(Value, String), RiichiError> {
+    let log: RoundLog = serde_json::from_str(content)
+        .map_err(|error| RiichiError::from(ParseError::Json(error.to_string())))?;
+
+    let round = Round::from_log(&log)?;
+    let state = round.get_state();
+
+    let mut lines: Vec<String> = log.events.iter().map(describe_event).collect();
+    for win in state.get_wins() {
+        let yakus: Vec<&str> = win.yaku.iter().map(|yaku| yaku.get_name()).collect();
+        lines.push(format!(
+            "player {} won: {} ({} han {} fu)",
+            win.player,
+            yakus.join(", "),
+            win.score.han,
+            win.score.fu
+        ));
+    }
+    lines.push(format!("points: {:?}", state.get_points()));
+
+    let value = json!({
+        "wins": state.get_wins(),
+        "draw": state.get_draw(),
+        "points": state.get_points(),
+    });
+
+    Ok((value, lines.join("\n")))
+}
+
+fn describe_event(event: &RoundEvent) -> String {
+    match event {
+        RoundEvent::Deal { dora_indicator, .. } => {
+            format!("deal, dora indicator {}", dora_indicator)
+        }
+        RoundEvent::Draw { player, tile } => format!("player {} draws {}", player, tile),
+        RoundEvent::Discard { player, tile } => format!("player {} discards {}", player, tile),
+        RoundEvent::Riichi { player } => format!("player {} declares riichi", player),
+        RoundEvent::Call {
+            player,
+            call_type,
+            tiles,
+        } => format!(
+            "player {} calls {:?} with {}",
+            player,
+            call_type,
+            tile_list(tiles)
+        ),
+        RoundEvent::Kan {
+            player,
+            kan_type,
+            tile,
+        } => format!("player {} declares {:?} of {}", player, kan_type, tile),
+        RoundEvent::Rinshan { player, tile } => {
+            format!("player {} draws {} from the dead wall", player, tile)
+        }
+        RoundEvent::NewDora { indicator } => format!("new dora indicator {}", indicator),
+        RoundEvent::Win { player, from } if player == from => format!("player {} tsumo", player),
+        RoundEvent::Win { player, from } => format!("player {} ron from player {}", player, from),
+        RoundEvent::ExhaustiveDraw => "exhaustive draw".to_string(),
+        RoundEvent::AbortiveDraw { reason, .. } => format!("abortive draw: {:?}", reason),
+    }
+}
+
+fn tile_counts(tiles: &[TileCount]) -> String {
+    tiles
+        .iter()
+        .map(|count| format!("{}({})", count.tile, count.count))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn tile_list(tiles: &[Tile]) -> String {
+    tiles.iter().map(|tile| tile.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use riichi_tools_rs::riichi::round::RoundLog;
+    use riichi_tools_rs::riichi::rules::Rules;
+
+    fn tiles(text: &str) -> Vec<Tile> {
+        Tile::list_from_text(text).unwrap()
+    }
+
+    fn tile(text: &str) -> Tile {
+        Tile::from_text(text).unwrap()
+    }
+
+    #[test]
+    fn replay_json() {
+        let log = RoundLog {
+            prevalent_wind: 1,
+            dealer: 0,
+            honba: 0,
+            riichi_sticks: 0,
+            renchan: 0,
+            points: [25000; 4],
+            rules: Rules::default(),
+            events: vec![
+                RoundEvent::Deal {
+                    hands: [
+                        tiles("123m456p789s1122z"),
+                        tiles("123m456p789s3344z"),
+                        tiles("456m123p123s5566z"),
+                        tiles("789m789p456s1357z"),
+                    ],
+                    dora_indicator: tile("9m"),
+                },
+                RoundEvent::Draw {
+                    player: 0,
+                    tile: tile("7z"),
+                },
+                RoundEvent::Riichi { player: 0 },
+                RoundEvent::Discard {
+                    player: 0,
+                    tile: tile("7z"),
+                },
+                RoundEvent::Draw {
+                    player: 1,
+                    tile: tile("2z"),
+                },
+                RoundEvent::Discard {
+                    player: 1,
+                    tile: tile("2z"),
+                },
+                RoundEvent::Win { player: 0, from: 1 },
+            ],
+        };
+
+        let (value, text) = replay(&serde_json::to_string(&log).unwrap()).unwrap();
+
+        assert_eq!(value["points"], json!([32700, 17300, 25000, 25000]));
+        assert_eq!(value["draw"], Value::Null);
+        assert_eq!(value["wins"][0]["player"], 0);
+        assert_eq!(value["wins"][0]["from"], 1);
+        // double riichi, 7700 and the riichi stick back
+        assert_eq!(value["wins"][0]["payments"], json!([8700, -7700, 0, 0]));
+        assert!(text.contains("player 0 ron from player 1"));
+
+        assert_eq!(replay("{}").unwrap_err().code(), 135);
+    }
+
+    #[test]
+    fn score_arguments() {
+        let parse = |args: &[&str]| Cli::try_parse_from(args).map(|_| ());
+        let hand = "123m456p789s11155z";
+
+        assert!(parse(&["riichi", "score", hand, "--ron"]).is_ok());
+        assert!(parse(&["riichi", "score", hand, "--ron", "--tsumo"]).is_err());
+        assert!(parse(&["riichi", "score", hand, "--seat-wind", "4"]).is_ok());
+        assert!(parse(&["riichi", "score", hand, "--seat-wind", "9"]).is_err());
+        assert!(parse(&["riichi", "score", hand, "--prevalent-wind", "0"]).is_err());
+    }
+
+    #[test]
+    fn score_without_yaku() {
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("123m456p789s11155z", false).unwrap());
+
+        assert_eq!(
+            score(&mut table).unwrap(),
+            (Value::Null, "no yaku".to_string())
+        );
+    }
+
+    #[test]
+    fn score_like_the_library() {
+        let cli = Cli::try_parse_from([
+            "riichi",
+            "score",
+            "123m456p789s11155z",
+            "--tsumo",
+            "--riichi",
+            "--dora",
+            "4z",
+            "--json",
+        ])
+        .unwrap();
+        let value: Value = serde_json::from_str(&run(&cli).unwrap()).unwrap();
+
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("123m456p789s11155z", false).unwrap());
+        table.set_my_tsumo(true);
+        table.set_my_riichi(true);
+        table.set_dora_indicators(tiles("4z"));
+        assert_eq!(value, json!(analysis::score(&mut table)));
+    }
+
+    #[test]
+    fn score_of_incomplete_hand() {
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("123m456p789s1115z", false).unwrap());
+
+        assert_eq!(
+            score(&mut table).unwrap_err(),
+            IllegalAction::HandNotComplete.into()
+        );
+    }
+}
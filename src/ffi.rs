@@ -0,0 +1,345 @@
+//! C ABI enabled by the `ffi` feature, declared in include/riichi.h.
+//! Hands and tables are opaque handles, results are JSON strings owned by the caller.
+//! Every function returns 0 on success, a negative RIICHI_ERR_* code for misuse of the API
+//! or the RiichiError code - riichi_last_error has the details of the last error.
+
+use crate::riichi::analysis;
+use crate::riichi::hand::Hand;
+use crate::riichi::riichi_error::{RiichiError, ValidationError};
+use crate::riichi::table::Table;
+use serde_json::json;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+pub const RIICHI_OK: c_int = 0;
+/// A required pointer was null
+pub const RIICHI_ERR_NULL: c_int = -1;
+/// A string was not valid UTF-8
+pub const RIICHI_ERR_UTF8: c_int = -2;
+/// The library panicked, which is a bug
+pub const RIICHI_ERR_PANIC: c_int = -3;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<RiichiError>> = const { RefCell::new(None) };
+}
+
+/// Parses a hand in the mpsz notation. Free it with riichi_hand_free.
+///
+/// # Safety
+/// `text` is a nul-terminated string, `hand` points to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn riichi_hand_from_text(text: *const c_char, hand: *mut *mut Hand) -> c_int {
+    guard(|| {
+        if hand.is_null() {
+            return Err(FfiError::Null);
+        }
+
+        let parsed = Hand::from_text(read_str(text)?, false)?;
+        *hand = Box::into_raw(Box::new(parsed));
+
+        Ok(())
+    })
+}
+
+/// # Safety
+/// `hand` comes from riichi_hand_from_text and was not freed yet, or is null.
+#[no_mangle]
+pub unsafe extern "C" fn riichi_hand_free(hand: *mut Hand) {
+    if !hand.is_null() {
+        drop(Box::from_raw(hand));
+    }
+}
+
+/// The hand in the mpsz notation
+///
+/// # Safety
+/// `hand` is a live handle, `text` points to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn riichi_hand_to_text(hand: *const Hand, text: *mut *mut c_char) -> c_int {
+    write_string(text, || {
+        Ok(hand.as_ref().ok_or(FfiError::Null)?.to_string())
+    })
+}
+
+/// Shanten of the hand, -1 is a complete hand
+///
+/// # Safety
+/// `hand` is a live handle, `shanten` points to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn riichi_hand_shanten(hand: *mut Hand, shanten: *mut i8) -> c_int {
+    guard(|| {
+        if shanten.is_null() {
+            return Err(FfiError::Null);
+        }
+
+        *shanten = hand.as_mut().ok_or(FfiError::Null)?.shanten();
+
+        Ok(())
+    })
+}
+
+/// Ukeire of every discard as JSON, see analysis::ukeire
+///
+/// # Safety
+/// `hand` is a live handle, `json` points to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn riichi_hand_ukeire(hand: *mut Hand, json: *mut *mut c_char) -> c_int {
+    write_string(json, || {
+        Ok(json!(analysis::ukeire(hand.as_mut().ok_or(FfiError::Null)?)).to_string())
+    })
+}
+
+/// Waits of a tenpai hand as JSON, an empty list when the hand is not tenpai
+///
+/// # Safety
+/// `hand` is a live handle, `json` points to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn riichi_hand_waits(hand: *mut Hand, json: *mut *mut c_char) -> c_int {
+    write_string(json, || {
+        Ok(json!(analysis::waits(hand.as_mut().ok_or(FfiError::Null)?)).to_string())
+    })
+}
+
+/// Creates a table from a JSON object with the keys of Table::from_map. Free it with riichi_table_free.
+///
+/// # Safety
+/// `json` is a nul-terminated string, `table` points to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn riichi_table_from_json(
+    json: *const c_char,
+    table: *mut *mut Table,
+) -> c_int {
+    guard(|| {
+        if table.is_null() {
+            return Err(FfiError::Null);
+        }
+
+        *table = Box::into_raw(Box::new(Table::from_json(read_str(json)?)?));
+
+        Ok(())
+    })
+}
+
+/// # Safety
+/// `table` comes from riichi_table_from_json and was not freed yet, or is null.
+#[no_mangle]
+pub unsafe extern "C" fn riichi_table_free(table: *mut Table) {
+    if !table.is_null() {
+        drop(Box::from_raw(table));
+    }
+}
+
+/// Yaku and score of my hand on the table as JSON {yaku, han, fu, points}, null when there is no yaku
+///
+/// # Safety
+/// `table` is a live handle, `json` points to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn riichi_table_score(table: *mut Table, json: *mut *mut c_char) -> c_int {
+    write_string(json, || {
+        let table = table.as_mut().ok_or(FfiError::Null)?;
+        if table.get_my_hand_option().is_none() {
+            return Err(RiichiError::from(ValidationError::UnknownHand(0)).into());
+        }
+
+        Ok(json!(analysis::score(table)).to_string())
+    })
+}
+
+/// The last error on this thread as JSON {code, message, offset, token}, or null if there was none.
+/// Free it with riichi_string_free.
+#[no_mangle]
+pub extern "C" fn riichi_last_error() -> *mut c_char {
+    LAST_ERROR.with(|last| match &*last.borrow() {
+        None => ptr::null_mut(),
+        Some(error) => to_c_string(json!(error).to_string()),
+    })
+}
+
+/// # Safety
+/// `string` was returned by this library and was not freed yet, or is null.
+#[no_mangle]
+pub unsafe extern "C" fn riichi_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Runs the call, turning errors into return codes and keeping them for riichi_last_error
+fn guard<F>(call: F) -> c_int
+where
+    F: FnOnce() -> Result<(), FfiError>,
+{
+    let result = match panic::catch_unwind(AssertUnwindSafe(call)) {
+        Ok(result) => result,
+        Err(_) => Err(FfiError::Panic),
+    };
+
+    let (code, error) = match result {
+        Ok(()) => (RIICHI_OK, None),
+        Err(FfiError::Null) => (RIICHI_ERR_NULL, None),
+        Err(FfiError::Utf8) => (RIICHI_ERR_UTF8, None),
+        Err(FfiError::Panic) => (RIICHI_ERR_PANIC, None),
+        Err(FfiError::Riichi(error)) => (error.code() as c_int, Some(error)),
+    };
+
+    LAST_ERROR.with(|last| *last.borrow_mut() = error);
+
+    code
+}
+
+enum FfiError {
+    Null,
+    Utf8,
+    Panic,
+    Riichi(RiichiError),
+}
+
+impl From<RiichiError> for FfiError {
+    fn from(error: RiichiError) -> Self {
+        FfiError::Riichi(error)
+    }
+}
+
+unsafe fn read_str<'a>(text: *const c_char) -> Result<&'a str, FfiError> {
+    if text.is_null() {
+        return Err(FfiError::Null);
+    }
+
+    CStr::from_ptr(text).to_str().map_err(|_| FfiError::Utf8)
+}
+
+unsafe fn write_string<F>(out: *mut *mut c_char, string: F) -> c_int
+where
+    F: FnOnce() -> Result<String, FfiError>,
+{
+    guard(|| {
+        if out.is_null() {
+            return Err(FfiError::Null);
+        }
+
+        *out = to_c_string(string()?);
+
+        Ok(())
+    })
+}
+
+/// Our strings never contain a nul byte, hands and JSON escape everything
+fn to_c_string(string: String) -> *mut c_char {
+    CString::new(string).unwrap().into_raw()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    unsafe fn take_string(string: *mut c_char) -> String {
+        let result = CStr::from_ptr(string).to_str().unwrap().to_string();
+        riichi_string_free(string);
+
+        result
+    }
+
+    #[test]
+    fn hand_handle() {
+        let text = CString::new("123m456p789s23m115z").unwrap();
+        let mut hand = ptr::null_mut();
+        let mut shanten = 0i8;
+        let mut json = ptr::null_mut();
+
+        unsafe {
+            assert_eq!(riichi_hand_from_text(text.as_ptr(), &mut hand), RIICHI_OK);
+            assert_eq!(riichi_hand_shanten(hand, &mut shanten), RIICHI_OK);
+            assert_eq!(riichi_hand_to_text(hand, &mut json), RIICHI_OK);
+            assert_eq!(take_string(json), "12233m456p789s115z");
+            assert_eq!(riichi_hand_ukeire(hand, &mut json), RIICHI_OK);
+            let ukeire: Value = serde_json::from_str(&take_string(json)).unwrap();
+            riichi_hand_free(hand);
+
+            assert_eq!(shanten, 0);
+            assert_eq!(ukeire[0]["discard"], "5z");
+            assert_eq!(ukeire[0]["total"], 7);
+        }
+    }
+
+    #[test]
+    fn table_score() {
+        let params = CString::new(
+            r#"{"my_hand": "123m456p789s11155z", "my_tsumo": true, "my_riichi": true}"#,
+        )
+        .unwrap();
+        let mut table = ptr::null_mut();
+        let mut json = ptr::null_mut();
+
+        unsafe {
+            assert_eq!(
+                riichi_table_from_json(params.as_ptr(), &mut table),
+                RIICHI_OK
+            );
+            assert_eq!(riichi_table_score(table, &mut json), RIICHI_OK);
+            let score: Value = serde_json::from_str(&take_string(json)).unwrap();
+            riichi_table_free(table);
+
+            assert_eq!(score["yaku"], json!(["MenzenTsumo", "Riichi"]));
+            assert_eq!(score["han"], 2);
+        }
+    }
+
+    #[test]
+    fn errors_as_return_codes() {
+        let text = CString::new("123m456p789s1z(p1z4)").unwrap();
+        let mut hand = ptr::null_mut();
+
+        unsafe {
+            assert_eq!(riichi_hand_from_text(text.as_ptr(), &mut hand), 131);
+            assert!(hand.is_null());
+
+            let error: Value = serde_json::from_str(&take_string(riichi_last_error())).unwrap();
+            assert_eq!(error["offset"], 14);
+            assert_eq!(error["token"], "(p1z4)");
+
+            assert_eq!(
+                riichi_hand_from_text(ptr::null(), &mut hand),
+                RIICHI_ERR_NULL
+            );
+            assert!(riichi_last_error().is_null());
+
+            // a null handle clears the last error too
+            assert_eq!(riichi_hand_from_text(text.as_ptr(), &mut hand), 131);
+            let mut shanten = 0i8;
+            assert_eq!(
+                riichi_hand_shanten(ptr::null_mut(), &mut shanten),
+                RIICHI_ERR_NULL
+            );
+            assert!(riichi_last_error().is_null());
+        }
+    }
+
+    #[test]
+    fn table_without_hand() {
+        let params = CString::new(r#"{"my_tsumo": true}"#).unwrap();
+        let mut table = ptr::null_mut();
+        let mut json = ptr::null_mut();
+
+        unsafe {
+            assert_eq!(
+                riichi_table_from_json(params.as_ptr(), &mut table),
+                RIICHI_OK
+            );
+            let code = riichi_table_score(table, &mut json);
+            riichi_table_free(table);
+
+            assert_eq!(
+                code,
+                RiichiError::from(ValidationError::UnknownHand(0)).code() as c_int
+            );
+            assert!(json.is_null());
+
+            let error: Value = serde_json::from_str(&take_string(riichi_last_error())).unwrap();
+            assert_eq!(error["message"], "Hand of player 0 is not known");
+        }
+    }
+}
@@ -3,3 +3,9 @@
 extern crate lazy_static;
 
 pub mod riichi;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,258 @@
+use crate::riichi::fu::Fu;
+use crate::riichi::generator::{self, WaitShape};
+use crate::riichi::hand::Hand;
+use crate::riichi::scores::Score;
+use crate::riichi::shape_finder::ShapeFinder;
+use crate::riichi::shapes::Shape;
+use crate::riichi::table::Table;
+use crate::riichi::tile::Tile;
+use crate::riichi::yaku::{Yaku, YakuFinder};
+use serde::{Deserialize, Serialize};
+
+/// A tile and how many copies of it are left
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TileCount {
+    pub tile: Tile,
+    pub count: u8,
+}
+
+/// Tiles that lower the shanten after a discard (no discard for 13 tile hands)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UkeireOption {
+    pub discard: Option<Tile>,
+    pub tiles: Vec<TileCount>,
+    pub total: u8,
+}
+
+/// Yaku and score of a winning hand
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreSummary {
+    pub yaku: Vec<Yaku>,
+    pub han: u8,
+    pub fu: u8,
+    pub points: u32,
+}
+
+/// One way to read a winning hand and what it is worth that way
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Decomposition {
+    /// sets and the pair (or 7 pairs, or kokushi), calls included
+    pub shapes: Vec<Shape>,
+    pub winning_tile: Tile,
+    /// how the winning tile completes the hand, more than one when it is in more shapes
+    pub waits: Vec<WaitShape>,
+    /// empty when there is no yaku
+    pub yaku: Vec<Yaku>,
+    pub score: Score,
+    /// where the fu come from, empty when they are not counted (mangan and above)
+    pub fu: Vec<Fu>,
+}
+
+/// Ukeire of every discard that keeps the shanten, see Hand::find_shanten_improving_tiles
+pub fn ukeire(hand: &mut Hand) -> Vec<UkeireOption> {
+    hand.find_shanten_improving_tiles(None)
+        .iter()
+        .map(|(discard, tiles, total)| UkeireOption {
+            discard: discard.as_ref().map(plain),
+            tiles: tile_counts(tiles),
+            total: *total,
+        })
+        .collect()
+}
+
+/// Waits of a 13 tile tenpai hand, empty when the hand is not tenpai
+pub fn waits(hand: &mut Hand) -> Vec<TileCount> {
+    if hand.count_tiles() != 13 || hand.shanten() != 0 {
+        return vec![];
+    }
+
+    match hand.find_shanten_improving_tiles(None).first() {
+        None => vec![],
+        Some((_, tiles, _)) => tile_counts(tiles),
+    }
+}
+
+/// Yaku and score of my hand on this table, None when there is no yaku
+pub fn score(table: &mut Table) -> Option<ScoreSummary> {
+    let (yaku, score) = match table.yaku()? {
+        (yaku, _) if yaku.is_empty() => return None,
+        result => result,
+    };
+
+    Some(ScoreSummary {
+        yaku,
+        han: score.han,
+        fu: score.fu,
+        points: score.total_points(),
+    })
+}
+
+/// Every decomposition of a complete hand, or of a tenpai hand completed by each of its waits.
+/// Waits with no tiles left (all in the hand or visible on the table) are skipped.
+/// They are scored on the table with this hand instead of mine, the most valuable one first.
+pub fn decompositions(hand: &Hand, table: &Table) -> Vec<Decomposition> {
+    let mut hand = hand.clone();
+    let mut wins = vec![];
+
+    match hand.count_tiles() {
+        13 => {
+            let visible = table.get_visible_tiles();
+            for wait in waits(&mut hand) {
+                if wait.count <= visible[(wait.tile.to_id() - 1) as usize] {
+                    continue;
+                }
+
+                let mut win = hand.clone();
+                let mut tile = wait.tile;
+                tile.is_draw = true;
+
+                win.reset_drawn_tiles();
+                win.add_tile(tile);
+                wins.push(win);
+            }
+        }
+        _ => {
+            if hand.shanten() == -1 && hand.get_drawn_tile().is_some() {
+                wins.push(hand);
+            }
+        }
+    }
+
+    let mut table = table.clone();
+    let mut decompositions = vec![];
+
+    for mut win in wins {
+        let winning_tile = *win.get_drawn_tile().unwrap();
+        let variants = ShapeFinder::new().find(&mut win);
+        table.set_my_hand(win);
+
+        for shapes in variants {
+            let (yaku, score, fu) = YakuFinder::new().score_variant(&mut table, &shapes);
+            let mut waits = vec![];
+            for wait in shapes
+                .iter()
+                .filter_map(|shape| generator::wait_shape(shape, &winning_tile))
+            {
+                if !waits.contains(&wait) {
+                    waits.push(wait);
+                }
+            }
+
+            decompositions.push(Decomposition {
+                shapes,
+                winning_tile: plain(&winning_tile),
+                waits,
+                yaku,
+                score,
+                fu,
+            });
+        }
+    }
+
+    decompositions.sort_by_key(|d| std::cmp::Reverse(d.score.total_points()));
+
+    decompositions
+}
+
+fn tile_counts(tiles: &[(Tile, u8)]) -> Vec<TileCount> {
+    tiles
+        .iter()
+        .map(|(tile, count)| TileCount {
+            tile: plain(tile),
+            count: *count,
+        })
+        .collect()
+}
+
+/// Only the tile itself without flags like is_draw, so it's written in the mpsz notation
+pub(crate) fn plain(tile: &Tile) -> Tile {
+    let mut plain = Tile::new(tile.tile_type);
+    plain.is_red = tile.is_red;
+
+    plain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ukeire_after_discard() {
+        let mut hand = Hand::from_text("123m456p789s23m115z", false).unwrap();
+        let options = ukeire(&mut hand);
+
+        assert_eq!(options.len(), 1);
+        assert_eq!(options[0].discard.unwrap().to_string(), "5z");
+        assert_eq!(options[0].total, 7);
+    }
+
+    #[test]
+    fn waits_of_shanpon() {
+        let mut hand = Hand::from_text("123m456p789s1155z", false).unwrap();
+        let waits: Vec<String> = waits(&mut hand)
+            .iter()
+            .map(|w| w.tile.to_string())
+            .collect();
+
+        assert_eq!(waits, vec!["1z", "5z"]);
+        assert!(super::waits(&mut Hand::from_text("123m456p789s1357z", false).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn score_without_yaku() {
+        let mut table = Table::from_map(&serde_json::Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("123m456p789s11155z", false).unwrap());
+        assert!(score(&mut table).is_none());
+
+        table.set_my_tsumo(true);
+        let summary = score(&mut table).unwrap();
+        assert_eq!(summary.yaku, vec![Yaku::MenzenTsumo]);
+        assert_eq!(summary.han, 1);
+    }
+
+    fn table() -> Table {
+        let mut map = serde_json::Map::new();
+        map.insert("my_seat_wind".to_string(), serde_json::Value::from(2));
+        map.insert("prevalent_wind".to_string(), serde_json::Value::from(1));
+
+        Table::from_map(&map).unwrap()
+    }
+
+    #[test]
+    fn decompositions_of_complete_hand() {
+        let hand = Hand::from_text("223344m223344p55s", false).unwrap();
+        let decompositions = hand.decompositions(&table());
+
+        assert_eq!(decompositions.len(), 2);
+        assert!(decompositions[0].yaku.contains(&Yaku::Ryanpeikou));
+        assert_eq!(decompositions[0].waits, vec![WaitShape::Tanki]);
+        assert_eq!(decompositions[1].yaku, vec![Yaku::Tanyao, Yaku::Chiitoitsu]);
+        assert_eq!(decompositions[1].score.fu, 25);
+        assert!(decompositions[0].score.total_points() > decompositions[1].score.total_points());
+    }
+
+    #[test]
+    fn decompositions_of_tenpai_hand() {
+        let hand = Hand::from_text("123m456p789s22z33z", false).unwrap();
+        let decompositions = hand.decompositions(&table());
+
+        assert_eq!(decompositions.len(), 2);
+        assert_eq!(decompositions[0].winning_tile.to_string(), "2z");
+        assert_eq!(decompositions[0].yaku, vec![Yaku::SouthSeat]);
+        assert_eq!(decompositions[0].waits, vec![WaitShape::Shanpon]);
+        assert_eq!(decompositions[1].winning_tile.to_string(), "3z");
+        assert!(decompositions[1].yaku.is_empty());
+
+        // both other 3z are gone
+        let mut seen = table();
+        seen.add_tile_to_visible_tiles(Tile::from_text("3z").unwrap());
+        seen.add_tile_to_visible_tiles(Tile::from_text("3z").unwrap());
+        let decompositions = hand.decompositions(&seen);
+        assert_eq!(decompositions.len(), 1);
+        assert_eq!(decompositions[0].winning_tile.to_string(), "2z");
+
+        let mut noten = Hand::from_text("123m456p789s2z3z56z", false).unwrap();
+        assert!(noten.decompositions(&table()).is_empty());
+        assert_eq!(noten.shanten(), 2);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::riichi::rules::{Rules, TieBreak};
+
+/// Final result of one player
+#[derive(Debug, Clone)]
+pub struct FinalScore {
+    pub seat: u8,
+    /// points including riichi sticks left on the table
+    pub points: i32,
+    /// 1 - 4, tied players share the better placing when uma is split
+    pub placement: u8,
+    /// points after return points, uma and oka, in thousands
+    pub score: f32,
+}
+
+/// Final scores by seat (seat 0 is the first dealer) from the points at the end of a game.
+/// Oka is (return points - starting points) * 4 and goes to the first place together with
+/// riichi sticks left on the table. With TieBreak::Split, tied players share their uma, oka and sticks,
+/// otherwise the player closer to the first dealer places higher.
+pub fn final_scores(points: &[i32; 4], riichi_sticks: u8, rules: &Rules) -> Vec<FinalScore> {
+    let oka = (rules.return_points - rules.starting_points) * 4;
+
+    let mut seats: Vec<usize> = (0..4).collect();
+    seats.sort_by(|a, b| points[*b].cmp(&points[*a]).then(a.cmp(b)));
+
+    // what each placing gets on top of the points, uma in points
+    let mut bonuses = [0i32; 4];
+    let mut sticks = [0i32; 4];
+    for (placing, bonus) in bonuses.iter_mut().enumerate() {
+        *bonus = rules.uma[placing] * 1000;
+    }
+    bonuses[0] += oka;
+    sticks[0] = 1000 * riichi_sticks as i32;
+
+    let mut results: Vec<FinalScore> = vec![];
+    let mut first = 0;
+
+    while first < 4 {
+        let mut last = first + 1;
+        if rules.tie_break == TieBreak::Split {
+            while last < 4 && points[seats[last]] == points[seats[first]] {
+                last += 1;
+            }
+        }
+
+        let tied = (last - first) as f32;
+        let bonus = bonuses[first..last].iter().sum::<i32>() as f32 / tied;
+        let stick_share = sticks[first..last].iter().sum::<i32>() as f32 / tied;
+
+        for seat in seats[first..last].iter() {
+            let difference = points[*seat] as f32 + stick_share - rules.return_points as f32;
+
+            results.push(FinalScore {
+                seat: *seat as u8,
+                points: points[*seat] + stick_share as i32,
+                placement: first as u8 + 1,
+                score: (difference + bonus) / 1000.0,
+            });
+        }
+
+        first = last;
+    }
+
+    results.sort_by_key(|result| result.seat);
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scores(results: &[FinalScore]) -> Vec<f32> {
+        results.iter().map(|r| r.score).collect()
+    }
+
+    #[test]
+    fn tenhou_scores() {
+        let results = final_scores(&[40000, 30000, 20000, 9000], 1, &Rules::tenhou());
+
+        assert_eq!(results[0].points, 41000);
+        // 11 + 20 oka + 20 uma
+        assert_eq!(scores(&results), vec![51.0, 10.0, -20.0, -41.0]);
+    }
+
+    #[test]
+    fn m_league_split_ties() {
+        let results = final_scores(&[35000, 35000, 20000, 10000], 0, &Rules::m_league());
+
+        assert_eq!(results[0].placement, 1);
+        assert_eq!(results[1].placement, 1);
+        // 5 + (30 + 10 + 20 oka) / 2
+        assert_eq!(scores(&results), vec![35.0, 35.0, -20.0, -50.0]);
+
+        let results = final_scores(&[35000, 35000, 20000, 10000], 0, &Rules::tenhou());
+        assert_eq!(results[1].placement, 2);
+    }
+
+    #[test]
+    fn ema_scores() {
+        let results = final_scores(&[45000, 32000, 25000, 16000], 2, &Rules::ema());
+
+        // no oka, the leftover sticks go to the first place
+        assert_eq!(results[0].points, 47000);
+        assert_eq!(scores(&results), vec![32.0, 7.0, -10.0, -29.0]);
+    }
+}
@@ -0,0 +1,312 @@
+use crate::riichi::shapes::{ClosedShape, CompleteShape, OpenKan, OpenShape, Shape, ShapeType};
+use crate::riichi::table::Table;
+use crate::riichi::tile::{Tile, TileType};
+use crate::riichi::yaku::Yaku;
+use serde::{Deserialize, Serialize};
+
+/// Where the fu of a winning hand come from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FuSource {
+    /// 20 fu every hand starts with (futei)
+    Base,
+    /// chiitoitsu is always 25 fu
+    Chiitoitsu,
+    /// ron with a closed hand (menzen kafu)
+    ClosedRon,
+    /// tsumo, except for pinfu
+    Tsumo,
+    /// triplet or quad. A triplet completed by ron counts as open.
+    Meld { tile: Tile, open: bool, kan: bool },
+    /// pair of dragons, the prevalent wind or the seat wind
+    Pair(Tile),
+    /// kanchan, penchan or tanki wait
+    Wait,
+    /// an open hand without any fu is counted as 30 fu
+    OpenPinfu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Fu {
+    pub source: FuSource,
+    pub fu: u8,
+}
+
+impl Fu {
+    fn new(source: FuSource, fu: u8) -> Fu {
+        Fu { source, fu }
+    }
+}
+
+/// Fu of one decomposition of my winning hand with these yaku, before rounding up.
+/// When the winning tile can complete more shapes, the wait with the most fu is used.
+pub fn count_fu(table: &Table, variant: &[Shape], yakus: &[Yaku]) -> Vec<Fu> {
+    if yakus.iter().any(|y| matches!(y, Yaku::Chiitoitsu)) {
+        return vec![Fu::new(FuSource::Chiitoitsu, 25)];
+    }
+
+    let tsumo = table.did_i_tsumo();
+
+    if yakus.iter().any(|y| matches!(y, Yaku::Pinfu)) {
+        if tsumo {
+            return vec![Fu::new(FuSource::Base, 20)];
+        }
+
+        return vec![
+            Fu::new(FuSource::Base, 20),
+            Fu::new(FuSource::ClosedRon, 10),
+        ];
+    }
+
+    let winning_tile = table.get_my_winning_tile();
+    let mut waits: Vec<Option<usize>> = variant
+        .iter()
+        .enumerate()
+        .filter(|(_, shape)| match shape.get_shape_type() {
+            ShapeType::Complete(CompleteShape::Closed(closed)) => match closed {
+                ClosedShape::Shuntsu(_) | ClosedShape::Koutsu(_) | ClosedShape::Toitsu(_) => {
+                    closed.get_tiles().contains(&winning_tile)
+                }
+                _ => false,
+            },
+            _ => false,
+        })
+        .map(|(i, _)| Some(i))
+        .collect();
+
+    if waits.is_empty() {
+        waits.push(None);
+    }
+
+    waits
+        .iter()
+        .map(|wait| count_fu_with_wait(table, variant, *wait))
+        .max_by_key(|fu| total_fu(fu))
+        .unwrap()
+}
+
+/// Sum of the fu, before rounding up
+pub fn total_fu(fu: &[Fu]) -> u8 {
+    fu.iter().map(|f| f.fu).sum()
+}
+
+fn count_fu_with_wait(table: &Table, variant: &[Shape], wait: Option<usize>) -> Vec<Fu> {
+    let tsumo = table.did_i_tsumo();
+    let closed = variant.iter().all(|shape| {
+        !matches!(
+            shape.get_shape_type(),
+            ShapeType::Complete(CompleteShape::Open(_))
+        )
+    });
+    let winning_tile = table.get_my_winning_tile();
+
+    let mut fu = vec![Fu::new(FuSource::Base, 20)];
+
+    if closed && !tsumo {
+        fu.push(Fu::new(FuSource::ClosedRon, 10));
+    }
+
+    if tsumo {
+        fu.push(Fu::new(FuSource::Tsumo, 2));
+    }
+
+    for (i, shape) in variant.iter().enumerate() {
+        let is_wait = wait == Some(i);
+
+        let complete = match shape.get_shape_type() {
+            ShapeType::Complete(complete) => complete,
+            ShapeType::Incomplete(..) => continue,
+        };
+
+        match complete {
+            CompleteShape::Closed(ClosedShape::Shuntsu(tiles)) => {
+                // kanchan or penchan
+                if is_wait
+                    && (tiles[1] == winning_tile
+                        || tiles[0].get_value() == 1 && tiles[2] == winning_tile
+                        || tiles[2].get_value() == 9 && tiles[0] == winning_tile)
+                {
+                    fu.push(Fu::new(FuSource::Wait, 2));
+                }
+            }
+            CompleteShape::Closed(ClosedShape::Koutsu(tiles)) => {
+                // a triplet completed by ron is open
+                fu.push(meld_fu(tiles[0], is_wait && !tsumo, false));
+            }
+            CompleteShape::Closed(ClosedShape::Kantsu(tiles)) => {
+                fu.push(meld_fu(tiles[0], false, true));
+            }
+            CompleteShape::Closed(ClosedShape::Toitsu(tiles)) => {
+                let pair = pair_fu(table, &tiles[0]);
+                if pair > 0 {
+                    fu.push(Fu::new(FuSource::Pair(tiles[0]), pair));
+                }
+
+                // tanki
+                if is_wait {
+                    fu.push(Fu::new(FuSource::Wait, 2));
+                }
+            }
+            CompleteShape::Closed(ClosedShape::Single(_)) => {}
+            CompleteShape::Open(OpenShape::Chi(_)) => {}
+            CompleteShape::Open(OpenShape::Pon(tiles)) => {
+                fu.push(meld_fu(tiles[0], true, false));
+            }
+            CompleteShape::Open(OpenShape::Kan(OpenKan::Daiminkan(tiles)))
+            | CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(tiles))) => {
+                fu.push(meld_fu(tiles[0], true, true));
+            }
+        }
+    }
+
+    if !closed && total_fu(&fu) == 20 {
+        fu.push(Fu::new(FuSource::OpenPinfu, 10));
+    }
+
+    fu
+}
+
+/// Open triplet of simples is 2 fu, terminals and honors double it, closed doubles it and kan is 4 times more
+fn meld_fu(tile: Tile, open: bool, kan: bool) -> Fu {
+    let mut fu = 2;
+
+    if tile.is_terminal_or_honor() {
+        fu *= 2;
+    }
+
+    if !open {
+        fu *= 2;
+    }
+
+    if kan {
+        fu *= 4;
+    }
+
+    Fu::new(FuSource::Meld { tile, open, kan }, fu)
+}
+
+/// Dragons are 2 fu, the prevalent and seat wind are 2 fu each
+fn pair_fu(table: &Table, tile: &Tile) -> u8 {
+    match tile.tile_type {
+        TileType::Number(_, _) => 0,
+        TileType::Dragon(_) => 2,
+        TileType::Wind(value) => {
+            let mut fu = 0;
+
+            if table.get_prevalent_wind() == Some(value) {
+                fu += 2;
+            }
+
+            if table.get_my_seat_wind() == Some(value) {
+                fu += 2;
+            }
+
+            fu
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riichi::shape_finder::ShapeFinder;
+    use serde_json::{Map, Value};
+
+    fn table(hand: &str, tsumo: bool) -> Table {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from(hand));
+        map.insert("my_tsumo".to_string(), Value::from(tsumo));
+        map.insert("my_seat_wind".to_string(), Value::from(2));
+        map.insert("prevalent_wind".to_string(), Value::from(1));
+
+        Table::from_map(&map).unwrap()
+    }
+
+    /// Fu of the first decomposition and the rounded fu of the score
+    fn fu(hand: &str, tsumo: bool) -> (Vec<Fu>, u8) {
+        let mut table = table(hand, tsumo);
+        let variant = ShapeFinder::new().find(&mut table.get_my_hand().clone())[0].clone();
+        let (yakus, score) = table.yaku().unwrap();
+
+        (count_fu(&table, &variant, &yakus), score.fu)
+    }
+
+    fn tile(text: &str) -> Tile {
+        Tile::from_text(text).unwrap()
+    }
+
+    #[test]
+    fn fu_of_closed_ron_and_tsumo() {
+        // 20 + 10 ron + 8 (999s) + 8 (777z) + 2 kanchan
+        let (breakdown, rounded) = fu("13m456p999s777z22m2m", false);
+        assert_eq!(total_fu(&breakdown), 48);
+        assert_eq!(rounded, 50);
+        assert!(breakdown.contains(&Fu::new(FuSource::ClosedRon, 10)));
+        assert!(breakdown.contains(&Fu::new(FuSource::Wait, 2)));
+
+        // 20 + 2 tsumo + 8 + 8 + 2
+        let (breakdown, rounded) = fu("13m456p999s777z22m2m", true);
+        assert_eq!(total_fu(&breakdown), 40);
+        assert_eq!(rounded, 40);
+    }
+
+    #[test]
+    fn fu_of_triplet_completed_by_ron() {
+        // 20 + 10 ron + 8 (111p) + 8 (999s) + 4 (777z counts as open)
+        let (breakdown, _) = fu("234m11155p999s77z7z", false);
+        assert_eq!(total_fu(&breakdown), 50);
+        assert!(breakdown.contains(&Fu::new(
+            FuSource::Meld {
+                tile: tile("7z"),
+                open: true,
+                kan: false
+            },
+            4
+        )));
+
+        // 20 + 2 tsumo + 8 + 8 + 8
+        let (breakdown, _) = fu("234m11155p999s77z7z", true);
+        assert_eq!(total_fu(&breakdown), 46);
+    }
+
+    #[test]
+    fn fu_of_open_hand_without_fu() {
+        let (breakdown, rounded) = fu("234m456p55m678p(345s0)", false);
+
+        assert_eq!(
+            breakdown,
+            vec![
+                Fu::new(FuSource::Base, 20),
+                Fu::new(FuSource::OpenPinfu, 10)
+            ]
+        );
+        assert_eq!(rounded, 30);
+    }
+
+    #[test]
+    fn fu_of_kans_and_honor_pair() {
+        // 20 + 2 tsumo + 32 (closed kan of 1z) + 4 (pon of 7z) + 2 (seat wind pair) + 2 tanki
+        let (breakdown, rounded) = fu("234m678s22z(k1z)(p7z1)", true);
+        assert!(breakdown.contains(&Fu::new(
+            FuSource::Meld {
+                tile: tile("1z"),
+                open: false,
+                kan: true
+            },
+            32
+        )));
+        assert!(breakdown.contains(&Fu::new(FuSource::Pair(tile("2z")), 2)));
+        assert_eq!(total_fu(&breakdown), 62);
+        assert_eq!(rounded, 70);
+
+        // no closed ron fu for an open hand
+        let (breakdown, _) = fu("234m678s22z(k1z)(p7z1)", false);
+        assert_eq!(total_fu(&breakdown), 60);
+    }
+
+    #[test]
+    fn fu_of_pinfu_and_chiitoitsu() {
+        assert_eq!(fu("234m456p678s55m67p8p", false).1, 30);
+        assert_eq!(fu("234m456p678s55m67p8p", true).1, 20);
+        assert_eq!(fu("1133m5577p99s11z2z2z", false).1, 25);
+    }
+}
@@ -0,0 +1,851 @@
+use crate::riichi::final_score::{final_scores, FinalScore};
+use crate::riichi::riichi_error::{IllegalAction, RiichiError, RulesError};
+use crate::riichi::round::{AbortiveDraw, CallType, KanType, Phase, Round, RoundEvent, RoundState};
+use crate::riichi::rules::{GameLength, Rules};
+use crate::riichi::shapes::{CompleteShape, OpenShape};
+use crate::riichi::table::Table;
+use crate::riichi::tile::Tile;
+use crate::riichi::wall::Wall;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// What a player does on their turn, when they have 14 tiles
+#[derive(Debug, Clone)]
+pub enum TurnAction {
+    Discard(Tile),
+    /// riichi declaration with this discard
+    Riichi(Tile),
+    Tsumo,
+    Kan(KanType, Tile),
+    /// kyuushu kyuuhai
+    AbortiveDraw,
+}
+
+/// Reaction to another player's discard
+#[derive(Debug, Clone)]
+pub enum CallAction {
+    Pass,
+    Ron,
+    /// tiles from the hand that are used for the call
+    Call(CallType, Vec<Tile>),
+}
+
+/// Legal actions on the player's turn
+#[derive(Debug, Clone, Default)]
+pub struct TurnOptions {
+    pub tsumo: bool,
+    pub riichi: bool,
+    pub kans: Vec<(KanType, Tile)>,
+    pub kyuushu_kyuuhai: bool,
+}
+
+/// Legal reactions to a discard
+#[derive(Debug, Clone, Default)]
+pub struct CallOptions {
+    pub ron: bool,
+    pub calls: Vec<(CallType, Vec<Tile>)>,
+}
+
+/// Decision maker for one seat.
+/// Agents see the table from their own point of view and choose from the legal options.
+/// An illegal choice (like discarding a tile that's not in hand) stops the game with an error.
+pub trait Agent {
+    fn turn(&mut self, table: &Table, options: &TurnOptions) -> TurnAction;
+
+    /// `from` is the relative position of the discarder, 1 = shimocha, 2 = toimen, 3 = kamicha.
+    /// Only called when there is something to choose from.
+    fn call(&mut self, table: &Table, tile: &Tile, from: u8, options: &CallOptions) -> CallAction;
+}
+
+/// Goes for the lowest shanten, declares riichi when it can, wins whenever it can and never calls.
+pub struct SimpleAgent;
+
+impl Agent for SimpleAgent {
+    fn turn(&mut self, table: &Table, options: &TurnOptions) -> TurnAction {
+        if options.tsumo {
+            return TurnAction::Tsumo;
+        }
+
+        if options.kyuushu_kyuuhai {
+            return TurnAction::AbortiveDraw;
+        }
+
+        let hand = table.get_my_hand();
+
+        if table.did_i_riichi() {
+            return TurnAction::Discard(*hand.get_drawn_tile().unwrap());
+        }
+
+        // (shanten, how much we want to keep the tile, tile)
+        let mut best: Option<(i8, u8, Tile)> = None;
+        for tile in hand.get_tiles().iter().flatten() {
+            if tile.is_open || tile.is_kan {
+                continue;
+            }
+
+            let mut after = hand.clone();
+            after.remove_tile(tile);
+            let shanten = after.shanten();
+
+            let keep = if tile.is_honor() {
+                0
+            } else if tile.is_terminal() {
+                1
+            } else if tile.is_red {
+                3
+            } else {
+                2
+            };
+
+            let better = match best {
+                None => true,
+                Some((best_shanten, best_keep, _)) => (shanten, keep) < (best_shanten, best_keep),
+            };
+
+            if better {
+                best = Some((shanten, keep, *tile));
+            }
+        }
+
+        let (shanten, _, tile) = best.unwrap();
+        if options.riichi && shanten == 0 {
+            TurnAction::Riichi(tile)
+        } else {
+            TurnAction::Discard(tile)
+        }
+    }
+
+    fn call(
+        &mut self,
+        _table: &Table,
+        _tile: &Tile,
+        _from: u8,
+        options: &CallOptions,
+    ) -> CallAction {
+        if options.ron {
+            CallAction::Ron
+        } else {
+            CallAction::Pass
+        }
+    }
+}
+
+/// A full game of 4 players, played round by round with a new wall every round.
+/// Seat 0 is the first dealer.
+pub struct Game {
+    rules: Rules,
+    agents: [Box<dyn Agent>; 4],
+    rng: StdRng,
+    // 1 = east, 2 = south...
+    prevalent_wind: u8,
+    dealer: u8,
+    honba: u8,
+    /// consecutive wins of the current dealer
+    renchan: u8,
+    riichi_sticks: u8,
+    points: [i32; 4],
+    rounds: Vec<Round>,
+    ended: bool,
+}
+
+impl Game {
+    /// The same seed and agents always play the same game.
+    /// Only games of 4 players can be played, sanma rules are an error.
+    pub fn new(rules: Rules, agents: [Box<dyn Agent>; 4], seed: u64) -> Result<Game, RiichiError> {
+        if rules.sanma {
+            return Err(RulesError::SanmaGame.into());
+        }
+
+        let points = [rules.starting_points; 4];
+
+        Ok(Game {
+            rules,
+            agents,
+            rng: StdRng::seed_from_u64(seed),
+            prevalent_wind: 1,
+            dealer: 0,
+            honba: 0,
+            renchan: 0,
+            riichi_sticks: 0,
+            points,
+            rounds: vec![],
+            ended: false,
+        })
+    }
+
+    /// Plays rounds until the game ends
+    pub fn play(&mut self) -> Result<(), RiichiError> {
+        while !self.ended {
+            self.play_round()?;
+        }
+
+        Ok(())
+    }
+
+    /// Plays one round and moves the game to the next one
+    pub fn play_round(&mut self) -> Result<(), RiichiError> {
+        if self.ended {
+            return Err(IllegalAction::GameEnded.into());
+        }
+
+        let mut wall = Wall::from_rules(self.rng.gen(), &self.rules)?;
+        let mut round = Round::new(
+            self.prevalent_wind,
+            self.dealer,
+            self.honba,
+            self.riichi_sticks,
+            self.points,
+        );
+        round.set_rules(self.rules.clone());
+        round.set_renchan(self.renchan);
+
+        let hands = wall.deal(self.dealer)?;
+        round.apply(RoundEvent::Deal {
+            hands,
+            dora_indicator: wall.dora_indicators()[0],
+        })?;
+
+        while !round.is_ended() {
+            match round.get_state().get_phase() {
+                Phase::Draw(player) => Game::draw(&mut round, &mut wall, player)?,
+                Phase::Discard(player) => self.turn(&mut round, &mut wall, player)?,
+                Phase::Calls(player) => self.calls(&mut round, &mut wall, player)?,
+                Phase::Rinshan(player) => match wall.draw_rinshan() {
+                    Some(tile) => round.apply(RoundEvent::Rinshan { player, tile })?,
+                    None => return Err(IllegalAction::NoRinshanTiles.into()),
+                },
+                _ => break,
+            }
+        }
+
+        self.finish_round(round.get_state());
+        self.rounds.push(round);
+
+        Ok(())
+    }
+
+    /// Final scores by seat. Riichi sticks left on the table go to the first place.
+    pub fn final_scores(&self) -> Vec<FinalScore> {
+        final_scores(&self.points, self.riichi_sticks, &self.rules)
+    }
+
+    /// Placements by seat, ties are broken based on the rules.
+    pub fn placements(&self) -> [u8; 4] {
+        let mut placements = [0u8; 4];
+        for result in self.final_scores() {
+            placements[result.seat as usize] = result.placement;
+        }
+
+        placements
+    }
+
+    pub fn is_ended(&self) -> bool {
+        self.ended
+    }
+
+    pub fn get_rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn get_prevalent_wind(&self) -> u8 {
+        self.prevalent_wind
+    }
+
+    pub fn get_dealer(&self) -> u8 {
+        self.dealer
+    }
+
+    pub fn get_honba(&self) -> u8 {
+        self.honba
+    }
+
+    pub fn get_riichi_sticks(&self) -> u8 {
+        self.riichi_sticks
+    }
+
+    pub fn get_points(&self) -> [i32; 4] {
+        self.points
+    }
+
+    /// Played rounds with all their events
+    pub fn get_rounds(&self) -> &Vec<Round> {
+        &self.rounds
+    }
+
+    fn draw(round: &mut Round, wall: &mut Wall, player: u8) -> Result<(), RiichiError> {
+        match wall.draw() {
+            Some(tile) => round.apply(RoundEvent::Draw { player, tile }),
+            None => round.apply(RoundEvent::ExhaustiveDraw),
+        }
+    }
+
+    fn turn(&mut self, round: &mut Round, wall: &mut Wall, player: u8) -> Result<(), RiichiError> {
+        let options = self.turn_options(round.get_state(), player);
+        let table = round.get_state().table_for(player);
+
+        match self.agents[player as usize].turn(&table, &options) {
+            TurnAction::Tsumo => round.apply(RoundEvent::Win {
+                player,
+                from: player,
+            }),
+            TurnAction::AbortiveDraw => {
+                if !options.kyuushu_kyuuhai {
+                    return Err(IllegalAction::KyuushuKyuuhaiNotPossible.into());
+                }
+
+                round.apply(RoundEvent::AbortiveDraw {
+                    reason: AbortiveDraw::KyuushuKyuuhai,
+                    rons: vec![],
+                })
+            }
+            TurnAction::Discard(tile) => Game::discard(round, wall, player, tile),
+            TurnAction::Riichi(tile) => {
+                round.apply(RoundEvent::Riichi { player })?;
+                Game::discard(round, wall, player, tile)
+            }
+            TurnAction::Kan(kan_type, tile) => {
+                round.apply(RoundEvent::Kan {
+                    player,
+                    kan_type,
+                    tile,
+                })?;
+
+                // the added tile can be robbed
+                if kan_type == KanType::Shouminkan {
+                    let rons = self.ask_for_calls(round.get_state(), player, &tile, false);
+                    if !rons.0.is_empty() {
+                        return self.ron(round, player, &rons.0);
+                    }
+                }
+
+                for indicator in wall.kan(kan_type == KanType::Ankan) {
+                    round.apply(RoundEvent::NewDora { indicator })?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn discard(
+        round: &mut Round,
+        wall: &mut Wall,
+        player: u8,
+        tile: Tile,
+    ) -> Result<(), RiichiError> {
+        round.apply(RoundEvent::Discard { player, tile })?;
+
+        for indicator in wall.discard() {
+            round.apply(RoundEvent::NewDora { indicator })?;
+        }
+
+        Ok(())
+    }
+
+    fn calls(
+        &mut self,
+        round: &mut Round,
+        wall: &mut Wall,
+        discarder: u8,
+    ) -> Result<(), RiichiError> {
+        let tile = round.get_state().get_last_discard().unwrap();
+        // no calls on the last discard (houtei)
+        let calls_allowed = wall.tiles_remaining() > 0;
+        let (rons, call) = self.ask_for_calls(round.get_state(), discarder, &tile, calls_allowed);
+
+        if !rons.is_empty() {
+            return self.ron(round, discarder, &rons);
+        }
+
+        if let Some(reason) = Game::abortive_draw_after_discard(round.get_state()) {
+            return round.apply(RoundEvent::AbortiveDraw {
+                reason,
+                rons: vec![],
+            });
+        }
+
+        match call {
+            Some((player, call_type, tiles)) => {
+                round.apply(RoundEvent::Call {
+                    player,
+                    call_type,
+                    tiles,
+                })?;
+
+                if call_type == CallType::Daiminkan {
+                    for indicator in wall.kan(false) {
+                        round.apply(RoundEvent::NewDora { indicator })?;
+                    }
+                }
+
+                Ok(())
+            }
+            None => Game::draw(round, wall, (discarder + 1) % 4),
+        }
+    }
+
+    /// Asks the other players what they want to do with this tile.
+    /// Returns the players who ron, in turn order, and the call with the highest priority.
+    #[allow(clippy::type_complexity)]
+    fn ask_for_calls(
+        &mut self,
+        state: &RoundState,
+        discarder: u8,
+        tile: &Tile,
+        calls_allowed: bool,
+    ) -> (Vec<u8>, Option<(u8, CallType, Vec<Tile>)>) {
+        let mut rons = vec![];
+        let mut call: Option<(u8, CallType, Vec<Tile>)> = None;
+
+        for i in 1..4 {
+            let player = (discarder + i) % 4;
+            let options = Game::call_options(state, player, discarder, tile, calls_allowed);
+
+            if !options.ron && options.calls.is_empty() {
+                continue;
+            }
+
+            let table = state.table_for(player);
+            let from = (discarder + 4 - player) % 4;
+
+            match self.agents[player as usize].call(&table, tile, from, &options) {
+                CallAction::Pass => {}
+                CallAction::Ron => rons.push(player),
+                CallAction::Call(call_type, tiles) => {
+                    // pon and kan go before chi
+                    let replace = match &call {
+                        None => true,
+                        Some((_, CallType::Chi, _)) => call_type != CallType::Chi,
+                        _ => false,
+                    };
+
+                    if replace {
+                        call = Some((player, call_type, tiles));
+                    }
+                }
+            }
+        }
+
+        (rons, call)
+    }
+
+    fn ron(&self, round: &mut Round, from: u8, rons: &[u8]) -> Result<(), RiichiError> {
+        if round
+            .get_state()
+            .table_for(from)
+            .is_sanchahou(rons.len() as u8)
+        {
+            return round.apply(RoundEvent::AbortiveDraw {
+                reason: AbortiveDraw::Sanchahou,
+                rons: rons.to_vec(),
+            });
+        }
+
+        for player in rons.iter() {
+            round.apply(RoundEvent::Win {
+                player: *player,
+                from,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn turn_options(&self, state: &RoundState, player: u8) -> TurnOptions {
+        let mut options = TurnOptions::default();
+        let mut hand = state.get_player(player).get_hand().clone();
+
+        if hand.shanten() == -1 {
+            options.tsumo = Game::is_legal(
+                state,
+                RoundEvent::Win {
+                    player,
+                    from: player,
+                },
+            );
+        }
+
+        options.riichi = Game::is_legal(state, RoundEvent::Riichi { player });
+
+        let closed = hand.get_34_array(true);
+        let mut candidates = vec![];
+        for tile in hand.get_tiles().iter().flatten() {
+            if tile.is_open || tile.is_kan {
+                continue;
+            }
+
+            if closed[(tile.to_id() - 1) as usize] == 4
+                && !candidates.contains(&(KanType::Ankan, *tile))
+            {
+                candidates.push((KanType::Ankan, *tile));
+            }
+
+            let has_pon = hand.get_shapes().iter().any(|shape| match shape {
+                CompleteShape::Open(OpenShape::Pon(tiles)) => tiles[0].eq(tile),
+                _ => false,
+            });
+
+            if has_pon {
+                candidates.push((KanType::Shouminkan, *tile));
+            }
+        }
+
+        options.kans = candidates
+            .into_iter()
+            .filter(|(kan_type, tile)| {
+                Game::is_legal(
+                    state,
+                    RoundEvent::Kan {
+                        player,
+                        kan_type: *kan_type,
+                        tile: *tile,
+                    },
+                )
+            })
+            .collect();
+
+        options.kyuushu_kyuuhai = state.table_for(player).can_kyuushu_kyuuhai();
+
+        options
+    }
+
+    fn call_options(
+        state: &RoundState,
+        player: u8,
+        discarder: u8,
+        tile: &Tile,
+        calls_allowed: bool,
+    ) -> CallOptions {
+        let mut options = CallOptions::default();
+        let me = state.get_player(player);
+
+        // a quick check before the full legality check
+        let mut hand = me.get_hand().clone();
+        let mut winning_tile = *tile;
+        winning_tile.is_open = false;
+        hand.add_tile(winning_tile);
+        hand.reset_shanten();
+        if hand.shanten() == -1 {
+            options.ron = Game::is_legal(
+                state,
+                RoundEvent::Win {
+                    player,
+                    from: discarder,
+                },
+            );
+        }
+
+        if !calls_allowed || me.is_riichi() {
+            return options;
+        }
+
+        let closed: Vec<Tile> = me
+            .get_hand()
+            .get_tiles()
+            .iter()
+            .flatten()
+            .filter(|t| !t.is_open && !t.is_kan)
+            .copied()
+            .collect();
+        let find = |id: u8| closed.iter().find(|t| t.to_id() == id).copied();
+
+        let mut candidates = vec![];
+        let same: Vec<Tile> = closed.iter().filter(|t| t.eq(&tile)).copied().collect();
+        if same.len() >= 2 {
+            candidates.push((CallType::Pon, same[..2].to_vec()));
+        }
+        if same.len() >= 3 {
+            candidates.push((CallType::Daiminkan, same[..3].to_vec()));
+        }
+
+        if player == (discarder + 1) % 4 && !tile.is_honor() {
+            let prev_2 = tile.prev_id(false, 2);
+            let prev_1 = tile.prev_id(false, 1);
+            let next_1 = tile.next_id(false, 1);
+            let next_2 = tile.next_id(false, 2);
+
+            for (a, b) in [(prev_2, prev_1), (prev_1, next_1), (next_1, next_2)].iter() {
+                if *a == 0 || *b == 0 {
+                    continue;
+                }
+
+                if let (Some(tile_a), Some(tile_b)) = (find(*a), find(*b)) {
+                    candidates.push((CallType::Chi, vec![tile_a, tile_b]));
+                }
+            }
+        }
+
+        options.calls = candidates
+            .into_iter()
+            .filter(|(call_type, tiles)| {
+                Game::is_legal(
+                    state,
+                    RoundEvent::Call {
+                        player,
+                        call_type: *call_type,
+                        tiles: tiles.clone(),
+                    },
+                )
+            })
+            .collect();
+
+        options
+    }
+
+    fn is_legal(state: &RoundState, event: RoundEvent) -> bool {
+        Round::from_snapshot(state.clone()).apply(event).is_ok()
+    }
+
+    /// Abortive draws that happen when the discard is not ronned
+    fn abortive_draw_after_discard(state: &RoundState) -> Option<AbortiveDraw> {
+        let table = state.table_for(0);
+
+        if table.is_suufon_renda() {
+            Some(AbortiveDraw::SuufonRenda)
+        } else if table.is_suucha_riichi() {
+            Some(AbortiveDraw::SuuchaRiichi)
+        } else if table.is_suukaikan() {
+            Some(AbortiveDraw::Suukaikan)
+        } else {
+            None
+        }
+    }
+
+    fn finish_round(&mut self, state: &RoundState) {
+        self.points = state.get_points();
+        self.riichi_sticks = state.get_riichi_sticks();
+
+        // an abortive draw repeats the round, but only a dealer win or tenpai can end the game
+        let (dealer_repeats, dealer_keeps) = if !state.get_wins().is_empty() {
+            let dealer_won = state.get_wins().iter().any(|w| w.player == self.dealer);
+            self.honba = if dealer_won { self.honba + 1 } else { 0 };
+            self.renchan = if dealer_won { self.renchan + 1 } else { 0 };
+            (dealer_won, dealer_won)
+        } else {
+            self.honba += 1;
+            self.renchan = 0;
+            match state.get_draw() {
+                Some(draw) => {
+                    let dealer_tenpai =
+                        draw.abortive.is_none() && draw.tenpai[self.dealer as usize];
+                    (draw.abortive.is_some() || dealer_tenpai, dealer_tenpai)
+                }
+                None => (false, false),
+            }
+        };
+
+        let last_wind = match self.rules.game_length {
+            GameLength::Tonpuusen => 1,
+            GameLength::Hanchan => 2,
+        };
+        let all_last = self.prevalent_wind == last_wind && self.dealer == 3;
+
+        let tobi = self.rules.tobi && self.points.iter().any(|p| *p < 0);
+        let agariyame = all_last
+            && dealer_keeps
+            && self.rules.agariyame
+            && self.placements()[self.dealer as usize] == 1;
+
+        if tobi || agariyame {
+            self.ended = true;
+        } else if !dealer_repeats {
+            self.dealer += 1;
+            if self.dealer == 4 {
+                self.dealer = 0;
+                self.prevalent_wind += 1;
+            }
+
+            if self.prevalent_wind > last_wind {
+                self.ended = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riichi::hand::Hand;
+
+    fn simple_agents() -> [Box<dyn Agent>; 4] {
+        [
+            Box::new(SimpleAgent),
+            Box::new(SimpleAgent),
+            Box::new(SimpleAgent),
+            Box::new(SimpleAgent),
+        ]
+    }
+
+    fn tiles(representation: &str) -> Vec<Tile> {
+        Hand::from_text(representation, false)
+            .unwrap()
+            .get_tiles()
+            .iter()
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    fn tile(representation: &str) -> Tile {
+        Tile::from_text(representation).unwrap()
+    }
+
+    fn played_game(rules: Rules, seed: u64) -> Game {
+        let mut game = Game::new(rules, simple_agents(), seed).unwrap();
+        game.play().unwrap();
+
+        game
+    }
+
+    #[test]
+    fn full_hanchan() {
+        let mut game = played_game(Rules::default(), 1);
+
+        assert!(game.is_ended());
+        assert!(game.get_rounds().len() >= 4);
+        assert!(game.play_round().is_err());
+
+        let total: i32 = game.get_points().iter().sum();
+        assert_eq!(total + 1000 * game.get_riichi_sticks() as i32, 100000);
+
+        let final_scores = game.final_scores();
+        let total_score: f32 = final_scores.iter().map(|s| s.score).sum();
+        assert!(total_score.abs() < 0.01);
+
+        let mut placements: Vec<u8> = final_scores.iter().map(|s| s.placement).collect();
+        placements.sort_unstable();
+        assert_eq!(placements, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn same_seed_same_game() {
+        let game_1 = played_game(Rules::default(), 7);
+        let game_2 = played_game(Rules::default(), 7);
+
+        assert_eq!(game_1.get_points(), game_2.get_points());
+        assert_eq!(game_1.get_rounds().len(), game_2.get_rounds().len());
+    }
+
+    #[test]
+    fn tonpuusen_is_east_only() {
+        let rules = Rules {
+            game_length: GameLength::Tonpuusen,
+            ..Rules::default()
+        };
+        let game = played_game(rules, 3);
+
+        for round in game.get_rounds().iter() {
+            assert_eq!(round.get_state().get_prevalent_wind(), 1);
+        }
+    }
+
+    #[test]
+    fn dealer_rotation_and_honba() {
+        let game = played_game(Rules::default(), 5);
+        let rounds = game.get_rounds();
+
+        for i in 1..rounds.len() {
+            let previous = rounds[i - 1].get_state();
+            let current = rounds[i].get_initial_state();
+            let dealer = previous.get_dealer();
+
+            let dealer_repeats = match previous.get_draw() {
+                Some(draw) => draw.abortive.is_some() || draw.tenpai[dealer as usize],
+                None => previous.get_wins().iter().any(|w| w.player == dealer),
+            };
+
+            if dealer_repeats {
+                assert_eq!(current.get_dealer(), dealer);
+                assert_eq!(current.get_honba(), previous.get_honba() + 1);
+            } else {
+                assert_eq!(current.get_dealer(), (dealer + 1) % 4);
+            }
+
+            if previous.get_wins().is_empty() {
+                assert_eq!(current.get_honba(), previous.get_honba() + 1);
+            } else if !dealer_repeats {
+                assert_eq!(current.get_honba(), 0);
+            }
+
+            // riichi sticks stay on the table until somebody wins
+            assert_eq!(current.get_riichi_sticks(), previous.get_riichi_sticks());
+            assert_eq!(current.get_points(), previous.get_points());
+        }
+    }
+
+    #[test]
+    fn final_scores_uma_oka() {
+        let mut game = Game::new(Rules::default(), simple_agents(), 0).unwrap();
+        game.points = [40000, 30000, 20000, 9000];
+        game.riichi_sticks = 1;
+
+        let scores = game.final_scores();
+        assert_eq!(scores[0].placement, 1);
+        assert_eq!(scores[0].points, 41000);
+        // 11 + 20 oka + 20 uma
+        assert!((scores[0].score - 51.0).abs() < 0.01);
+        assert!((scores[1].score - 10.0).abs() < 0.01);
+        assert!((scores[2].score + 20.0).abs() < 0.01);
+        assert!((scores[3].score + 41.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn sanma_games_are_rejected() {
+        let rules = Rules {
+            sanma: true,
+            ..Rules::default()
+        };
+
+        match Game::new(rules, simple_agents(), 0) {
+            Ok(_) => panic!("sanma can't be dealt to 4 seats"),
+            Err(error) => assert_eq!(error.code(), 251),
+        }
+    }
+
+    #[test]
+    fn no_agariyame_after_abortive_draw() {
+        let mut game = Game::new(Rules::default(), simple_agents(), 0).unwrap();
+        game.prevalent_wind = 2;
+        game.dealer = 3;
+        game.points = [20000, 20000, 20000, 40000];
+
+        let mut round = Round::new(2, 3, 0, 0, game.points);
+        round
+            .apply(RoundEvent::Deal {
+                hands: [
+                    tiles("234m456p678s6677z"),
+                    tiles("567m567p567s5556z"),
+                    tiles("888m888p888s2223z"),
+                    tiles("19m19p19s12345z23m"),
+                ],
+                dora_indicator: tile("9m"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Draw {
+                player: 3,
+                tile: tile("4m"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::AbortiveDraw {
+                reason: AbortiveDraw::KyuushuKyuuhai,
+                rons: vec![],
+            })
+            .unwrap();
+
+        // the leading dealer repeats All Last instead of ending the game
+        game.finish_round(round.get_state());
+        assert!(!game.is_ended());
+        assert_eq!(game.dealer, 3);
+        assert_eq!(game.honba, 1);
+    }
+
+    #[test]
+    fn ties_go_to_seat_order() {
+        let mut game = Game::new(Rules::default(), simple_agents(), 0).unwrap();
+        game.points = [20000, 30000, 30000, 20000];
+
+        assert_eq!(game.placements(), [3, 1, 2, 4]);
+    }
+}
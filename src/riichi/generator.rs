@@ -0,0 +1,480 @@
+use crate::riichi::analysis;
+use crate::riichi::hand::Hand;
+use crate::riichi::shape_finder::ShapeFinder;
+use crate::riichi::shapes::{ClosedShape, CompleteShape, Shape, ShapeType};
+use crate::riichi::table::Table;
+use crate::riichi::tile::Tile;
+use crate::riichi::yaku::Yaku;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Random hands that don't meet the constraints are thrown away, this many times at most
+const MAX_ATTEMPTS: usize = 20000;
+
+const TERMINALS_AND_HONORS: [u8; 13] = [1, 9, 10, 18, 19, 27, 28, 29, 30, 31, 32, 33, 34];
+
+/// How the winning tile completes the hand
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WaitShape {
+    Ryanmen,
+    Kanchan,
+    Penchan,
+    Shanpon,
+    Tanki,
+}
+
+/// What random_hand has to generate. The default is any complete hand, even without a yaku.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HandConstraints {
+    /// 13 tiles waiting on a tile that meets the constraints, instead of a complete hand
+    pub tenpai: bool,
+    pub required_yaku: Vec<Yaku>,
+    pub forbidden_yaku: Vec<Yaku>,
+    /// m, p, s or z (honors) that have to be in the hand
+    pub required_suits: Vec<char>,
+    /// m, p, s or z (honors) that can't be in the hand
+    pub forbidden_suits: Vec<char>,
+    pub wait: Option<WaitShape>,
+    /// exact number of calls (chi, pon and open kans), None for any
+    pub calls: Option<u8>,
+    /// dora and red fives, counted with the dora indicators of the table
+    pub min_dora: u8,
+    pub min_han: u8,
+}
+
+/// A random hand that meets the constraints, judged on this table (winds, tsumo, riichi, dora indicators and rules).
+/// Tenpai hands meet them with at least one of their waits.
+/// The same seed always gives the same hand, None when no such hand was found.
+pub fn random_hand(constraints: &HandConstraints, table: &Table, seed: u64) -> Option<Hand> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut table = table.clone();
+    let aka = table.rules_or_default().aka_ari;
+
+    for _ in 0..MAX_ATTEMPTS {
+        let (mut closed, melds) = match random_sets(constraints, aka, &mut rng) {
+            None => continue,
+            Some(sets) => sets,
+        };
+
+        if !constraints.tenpai {
+            match hand_from(&closed, &melds) {
+                Some(hand) if is_wanted_win(&mut table, hand.clone(), constraints) => {
+                    return Some(hand)
+                }
+                _ => continue,
+            }
+        }
+
+        closed.pop();
+        let mut hand = match hand_from(&closed, &melds) {
+            None => continue,
+            Some(hand) => hand,
+        };
+
+        for wait in analysis::waits(&mut hand) {
+            if wait.count == 0 {
+                continue;
+            }
+
+            closed.push(wait.tile);
+            let win = hand_from(&closed, &melds);
+            closed.pop();
+
+            if let Some(win) = win {
+                if is_wanted_win(&mut table, win, constraints) && has_suits(&hand, constraints) {
+                    return Some(hand);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// How the drawn tile completes this hand, in any of its decompositions
+pub fn wait_shapes(hand: &Hand) -> Vec<WaitShape> {
+    let winning_tile = match hand.get_drawn_tile() {
+        None => return vec![],
+        Some(tile) => *tile,
+    };
+
+    let mut waits = vec![];
+    for variant in ShapeFinder::new().find(&mut hand.clone()).iter() {
+        for wait in variant.iter().filter_map(|s| wait_shape(s, &winning_tile)) {
+            if !waits.contains(&wait) {
+                waits.push(wait);
+            }
+        }
+    }
+
+    waits
+}
+
+pub(crate) fn wait_shape(shape: &Shape, winning_tile: &Tile) -> Option<WaitShape> {
+    let closed = match shape.get_shape_type() {
+        ShapeType::Complete(CompleteShape::Closed(closed)) => closed,
+        _ => return None,
+    };
+
+    if !closed.get_tiles().contains(winning_tile) {
+        return None;
+    }
+
+    match closed {
+        ClosedShape::Shuntsu(tiles) => {
+            if tiles[1] == *winning_tile {
+                Some(WaitShape::Kanchan)
+            } else if tiles[0].get_value() == 1 && tiles[2] == *winning_tile
+                || tiles[2].get_value() == 9 && tiles[0] == *winning_tile
+            {
+                Some(WaitShape::Penchan)
+            } else {
+                Some(WaitShape::Ryanmen)
+            }
+        }
+        ClosedShape::Koutsu(_) => Some(WaitShape::Shanpon),
+        ClosedShape::Toitsu(_) => Some(WaitShape::Tanki),
+        _ => None,
+    }
+}
+
+fn is_wanted_win(table: &mut Table, hand: Hand, constraints: &HandConstraints) -> bool {
+    let calls = hand.get_open_shapes().len() as u8;
+    if constraints.calls.is_some_and(|wanted| wanted != calls) || !has_suits(&hand, constraints) {
+        return false;
+    }
+
+    if let Some(wait) = constraints.wait {
+        if !wait_shapes(&hand).contains(&wait) {
+            return false;
+        }
+    }
+
+    table.set_my_hand(hand);
+    let (yakus, score) = match table.yaku() {
+        None => return false,
+        Some(result) => result,
+    };
+
+    constraints.required_yaku.iter().all(|y| yakus.contains(y))
+        && !constraints.forbidden_yaku.iter().any(|y| yakus.contains(y))
+        && score.han >= constraints.min_han
+        && table.count_my_dora() >= constraints.min_dora
+}
+
+fn has_suits(hand: &Hand, constraints: &HandConstraints) -> bool {
+    let suits: Vec<char> = hand
+        .get_tiles()
+        .iter()
+        .flatten()
+        .map(|tile| tile.tile_type.to_char())
+        .collect();
+
+    constraints.required_suits.iter().all(|s| suits.contains(s))
+        && !constraints
+            .forbidden_suits
+            .iter()
+            .any(|s| suits.contains(s))
+}
+
+/// Random closed tiles and called melds (in the mpsz notation) of a complete hand, the last closed tile wins.
+/// Mostly 4 sets and a pair, sometimes chiitoitsu or kokushi. Required yaku and suits narrow down the tiles.
+pub(crate) fn random_sets<R: Rng>(
+    constraints: &HandConstraints,
+    aka: bool,
+    rng: &mut R,
+) -> Option<(Vec<Tile>, Vec<String>)> {
+    let requires = |yaku: Yaku| constraints.required_yaku.contains(&yaku);
+
+    let mut allowed: Vec<u8> = (1..=34)
+        .filter(|id| !constraints.forbidden_suits.contains(&suit(*id)))
+        .collect();
+
+    if requires(Yaku::Tanyao) {
+        allowed.retain(|id| !TERMINALS_AND_HONORS.contains(id));
+    }
+
+    if requires(Yaku::Honitsu) || requires(Yaku::Chinitsu) {
+        let suits: Vec<char> = ['m', 'p', 's']
+            .iter()
+            .filter(|s| !constraints.forbidden_suits.contains(s))
+            .copied()
+            .collect();
+        let chosen = *suits.choose(rng)?;
+        let honors = !requires(Yaku::Chinitsu);
+        allowed.retain(|id| suit(*id) == chosen || honors && suit(*id) == 'z');
+    }
+
+    let mut counts = [0u8; 34];
+    let mut closed: Vec<u8> = vec![];
+    let mut melds: Vec<String> = vec![];
+
+    let calls = constraints.calls.unwrap_or(0);
+    let special = calls == 0 && constraints.required_yaku.is_empty();
+    let kind = rng.gen_range(0, 100);
+
+    if requires(Yaku::Kokushi) || special && kind < 4 {
+        if calls > 0 || !TERMINALS_AND_HONORS.iter().all(|id| allowed.contains(id)) {
+            return None;
+        }
+
+        closed.extend_from_slice(&TERMINALS_AND_HONORS);
+        closed.push(*TERMINALS_AND_HONORS.choose(rng).unwrap());
+    } else if requires(Yaku::Chiitoitsu) || special && kind < 16 {
+        if calls > 0 {
+            return None;
+        }
+
+        while closed.len() < 14 {
+            let id = random_tile_id(&counts, 0, &allowed, rng)?;
+            counts[(id - 1) as usize] += 2;
+            closed.extend_from_slice(&[id, id]);
+        }
+    } else {
+        let open: Vec<bool> = match constraints.calls {
+            Some(calls) if calls > 4 => return None,
+            Some(calls) => {
+                let mut open: Vec<bool> = (0..4).map(|i| i < calls).collect();
+                open.shuffle(rng);
+                open
+            }
+            None => {
+                let open_hand = rng.gen_bool(0.4);
+                (0..4).map(|_| open_hand && rng.gen_bool(0.6)).collect()
+            }
+        };
+
+        let triplets_only = requires(Yaku::Toitoi);
+
+        for open in open {
+            let from = rng.gen_range(1, 4);
+            let set = if triplets_only {
+                rng.gen_range(11, 20)
+            } else {
+                rng.gen_range(0, 20)
+            };
+
+            match set {
+                // shuntsu
+                0..=10 => {
+                    let starts: Vec<u8> = allowed
+                        .iter()
+                        .filter(|id| {
+                            **id <= 27
+                                && (**id - 1) % 9 < 7
+                                && (**id..**id + 3)
+                                    .all(|i| allowed.contains(&i) && counts[(i - 1) as usize] < 4)
+                        })
+                        .copied()
+                        .collect();
+                    let start = *starts.choose(rng)?;
+                    let ids: Vec<u8> = (start..start + 3).collect();
+
+                    for id in ids.iter() {
+                        counts[(id - 1) as usize] += 1;
+                    }
+
+                    if open {
+                        let numbers: String = ids.iter().map(|id| number(*id)).collect();
+                        let called = rng.gen_range(0, 3);
+                        melds.push(format!("({}{}{})", numbers, suit(start), called));
+                    } else {
+                        closed.extend(ids);
+                    }
+                }
+                // koutsu
+                11..=17 => {
+                    let id = random_tile_id(&counts, 1, &allowed, rng)?;
+                    counts[(id - 1) as usize] += 3;
+
+                    if open {
+                        melds.push(format!("(p{}{}{})", number(id), suit(id), from));
+                    } else {
+                        closed.extend_from_slice(&[id, id, id]);
+                    }
+                }
+                // kantsu
+                _ => {
+                    let id = random_tile_id(&counts, 0, &allowed, rng)?;
+                    counts[(id - 1) as usize] += 4;
+
+                    melds.push(match (open, rng.gen_bool(0.5)) {
+                        (false, _) => format!("(k{}{})", number(id), suit(id)),
+                        (true, true) => format!("(k{}{}{})", number(id), suit(id), from),
+                        (true, false) => format!("(s{}{}{})", number(id), suit(id), from),
+                    });
+                }
+            }
+        }
+
+        let pair = random_tile_id(&counts, 2, &allowed, rng)?;
+        closed.extend_from_slice(&[pair, pair]);
+    }
+
+    closed.shuffle(rng);
+
+    let mut tiles: Vec<Tile> = closed
+        .iter()
+        .map(|id| Tile::from_id(*id).unwrap())
+        .collect();
+
+    if aka {
+        // one red five of each suit at most
+        for color in ['m', 'p', 's'].iter() {
+            let five = tiles
+                .iter_mut()
+                .find(|t| t.get_value() == 5 && t.tile_type.to_char() == *color);
+
+            if let Some(five) = five {
+                five.is_red = rng.gen_bool(0.3);
+            }
+        }
+    }
+
+    Some((tiles, melds))
+}
+
+/// A random complete hand, with or without a yaku, for the quizzes
+pub(crate) fn random_complete_hand<R: Rng>(rng: &mut R) -> Option<Hand> {
+    let (closed, melds) = random_sets(&HandConstraints::default(), false, rng)?;
+
+    hand_from(&closed, &melds)
+}
+
+/// Hand from closed tiles and melds in the mpsz notation, the last closed tile is the drawn one
+pub(crate) fn hand_from(closed: &[Tile], melds: &[String]) -> Option<Hand> {
+    let closed: String = closed.iter().map(|tile| tile.to_string()).collect();
+
+    Hand::from_text(&format!("{}{}", closed, melds.concat()), false).ok()
+}
+
+/// A random tile id out of the allowed ones with at most `max` copies already used
+pub(crate) fn random_tile_id<R: Rng>(
+    counts: &[u8; 34],
+    max: u8,
+    allowed: &[u8],
+    rng: &mut R,
+) -> Option<u8> {
+    let ids: Vec<u8> = allowed
+        .iter()
+        .filter(|id| counts[(**id - 1) as usize] <= max)
+        .copied()
+        .collect();
+
+    ids.choose(rng).copied()
+}
+
+fn number(id: u8) -> char {
+    Tile::from_id(id)
+        .unwrap()
+        .to_string()
+        .chars()
+        .next()
+        .unwrap()
+}
+
+fn suit(id: u8) -> char {
+    Tile::from_id(id).unwrap().tile_type.to_char()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Map;
+
+    fn table() -> Table {
+        Table::from_map(&Map::new()).unwrap()
+    }
+
+    #[test]
+    fn open_honitsu_tenpai_on_shanpon() {
+        let constraints = HandConstraints {
+            tenpai: true,
+            required_yaku: vec![Yaku::Honitsu],
+            wait: Some(WaitShape::Shanpon),
+            calls: Some(1),
+            ..Default::default()
+        };
+
+        for seed in 0..5 {
+            let mut hand = random_hand(&constraints, &table(), seed).unwrap();
+
+            assert_eq!(hand.count_tiles(), 13);
+            assert_eq!(hand.shanten(), 0);
+            assert_eq!(hand.get_open_shapes().len(), 1);
+        }
+    }
+
+    #[test]
+    fn complete_hand_with_yaku_and_han() {
+        let constraints = HandConstraints {
+            required_yaku: vec![Yaku::Tanyao],
+            forbidden_yaku: vec![Yaku::Pinfu],
+            forbidden_suits: vec!['m'],
+            min_han: 2,
+            ..Default::default()
+        };
+
+        let mut table = table();
+        let mut hand = random_hand(&constraints, &table, 1).unwrap();
+        assert_eq!(hand.shanten(), -1);
+        assert!(!hand.to_string().contains('m'));
+
+        table.set_my_hand(hand);
+        let (yakus, score) = table.yaku().unwrap();
+        assert!(yakus.contains(&Yaku::Tanyao));
+        assert!(!yakus.contains(&Yaku::Pinfu));
+        assert!(score.han >= 2);
+    }
+
+    #[test]
+    fn dora_and_kokushi() {
+        let mut table = table();
+        table.set_dora_indicators(vec![Tile::from_text("4p").unwrap()]);
+
+        let dora = HandConstraints {
+            min_dora: 3,
+            ..Default::default()
+        };
+        let hand = random_hand(&dora, &table, 3).unwrap();
+        table.set_my_hand(hand);
+        assert!(table.count_my_dora() >= 3);
+
+        let kokushi = HandConstraints {
+            required_yaku: vec![Yaku::Kokushi],
+            ..Default::default()
+        };
+        assert!(random_hand(&kokushi, &table, 3).is_some());
+
+        let impossible = HandConstraints {
+            required_yaku: vec![Yaku::Kokushi],
+            forbidden_suits: vec!['z'],
+            ..Default::default()
+        };
+        assert!(random_hand(&impossible, &table, 3).is_none());
+    }
+
+    #[test]
+    fn wait_shapes_of_hand() {
+        let hand = Hand::from_text("13m456p789s11z555z2m", false).unwrap();
+        assert_eq!(wait_shapes(&hand), vec![WaitShape::Kanchan]);
+
+        let hand = Hand::from_text("123m456p789s11z55z5z", false).unwrap();
+        assert_eq!(wait_shapes(&hand), vec![WaitShape::Shanpon]);
+    }
+
+    #[test]
+    fn same_seed_same_hand() {
+        let constraints = HandConstraints {
+            tenpai: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            random_hand(&constraints, &table(), 9).unwrap().to_string(),
+            random_hand(&constraints, &table(), 9).unwrap().to_string()
+        );
+    }
+}
@@ -4,11 +4,19 @@ use std::fmt;
 
 use super::shanten::ShantenFinder;
 use super::tile::Tile;
-use crate::riichi::riichi_error::RiichiError;
+use crate::riichi::analysis::{self, Decomposition};
+use crate::riichi::riichi_error::{
+    IllegalAction, ParseError, RiichiError, RulesError, ValidationError,
+};
 use crate::riichi::shapes::{ClosedShape, CompleteShape, OpenKan, OpenShape, Shape, ShapeType};
+use crate::riichi::table::Table;
 use rand::seq::SliceRandom;
 use rand::Rng;
 use regex::Regex;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A parsed tile with the byte offset and the text it was parsed from
+type TileSource = (Tile, usize, String);
 
 #[derive(Clone)]
 pub struct Hand {
@@ -18,7 +26,13 @@ pub struct Hand {
     tiles: Vec<Option<Tile>>,
     array_34: Option<[u8; 34]>,
     shapes: Vec<CompleteShape>,
+    /// indices of shapes in the order they were called, a shouminkan adds its pon again
+    call_order: Vec<usize>,
     shanten: i8,
+    /// three-player mahjong, 2m - 8m are not used
+    sanma: bool,
+    /// kita (4z) tiles set aside as nukidora in sanma
+    kita: u8,
 }
 
 impl Hand {
@@ -36,11 +50,16 @@ impl Hand {
         let mut tile_count = 0;
         let array34 = self.get_34_array(false);
 
-        for count in array34.iter() {
+        for (i, count) in array34.iter().enumerate() {
             tile_count += *count;
             if *count > 4 {
                 return false;
             }
+
+            // 2m - 8m
+            if self.sanma && *count > 0 && (1..=7).contains(&i) {
+                return false;
+            }
         }
 
         // 13 tiles + 5 optional from kans & draw
@@ -63,6 +82,41 @@ impl Hand {
         &self.shapes
     }
 
+    /// Shapes in the order they were called, the last call last. A shouminkan keeps the place of its pon.
+    /// Hands from text are called in the order of the text.
+    pub fn get_shapes_in_call_order(&self) -> Vec<CompleteShape> {
+        self.shapes_in_order(self.call_order.iter())
+    }
+
+    /// Shapes in the order they were called or became kans, a shouminkan is moved to the end
+    pub fn get_shapes_in_kan_order(&self) -> Vec<CompleteShape> {
+        let mut order: Vec<usize> = vec![];
+        for index in self.call_order.iter().rev() {
+            if !order.contains(index) {
+                order.insert(0, *index);
+            }
+        }
+
+        self.shapes_in_order(order.iter())
+    }
+
+    /// Shapes in this order of indices, the ones missing from it (and duplicates) are put first
+    fn shapes_in_order<'a, I>(&self, order: I) -> Vec<CompleteShape>
+    where
+        I: Iterator<Item = &'a usize>,
+    {
+        let mut indices: Vec<usize> = (0..self.shapes.len())
+            .filter(|index| !self.call_order.contains(index))
+            .collect();
+        for index in order {
+            if *index < self.shapes.len() && !indices.contains(index) {
+                indices.push(*index);
+            }
+        }
+
+        indices.iter().map(|index| self.shapes[*index]).collect()
+    }
+
     /// Returns a vector of OpenShape from shapes that we have identified in the hand.
     pub fn get_open_shapes(&self) -> Vec<OpenShape> {
         let mut open_shapes = vec![];
@@ -97,10 +151,14 @@ impl Hand {
     /// TODO fix kan generation
     /// TODO add open hand generation
     pub fn random_complete_hand(closed: bool, kans: bool) -> Hand {
+        Hand::random_complete_hand_with_rng(closed, kans, &mut rand::thread_rng())
+    }
+
+    /// Same as random_complete_hand, a seeded rng always gives the same hand
+    pub fn random_complete_hand_with_rng<R: Rng>(closed: bool, kans: bool, rng: &mut R) -> Hand {
         // we are looking to generate 4 shapes + 1 pair, so 5 shapes
         // ignoring kokushi and chiitoitsu for now
 
-        let mut rng = rand::thread_rng();
         let mut pair_found = false;
         let mut used_tiles: [u8; 34] = [0; 34];
         let mut tiles: Vec<Tile> = vec![];
@@ -108,7 +166,7 @@ impl Hand {
         for i in 0..5 {
             if i == 4 && !pair_found {
                 // last shape must be a pair now
-                Hand::generate_toitsu(&mut used_tiles, &mut tiles);
+                Hand::generate_toitsu(&mut used_tiles, &mut tiles, rng);
                 break;
             }
 
@@ -276,7 +334,7 @@ impl Hand {
                     }
                     // Toitsu
                     3 => {
-                        Hand::generate_toitsu(&mut used_tiles, &mut tiles);
+                        Hand::generate_toitsu(&mut used_tiles, &mut tiles, rng);
                         pair_found = true;
                     }
                     _ => {}
@@ -287,7 +345,7 @@ impl Hand {
         let mut final_tiles = vec![];
         let mut found_draw = false;
 
-        tiles.shuffle(&mut rng);
+        tiles.shuffle(rng);
 
         for tile in tiles.iter_mut() {
             if !found_draw && !tile.is_open && !tile.is_kan {
@@ -300,8 +358,7 @@ impl Hand {
         Hand::new(final_tiles)
     }
 
-    fn generate_toitsu(used_tiles: &mut [u8; 34], tiles: &mut Vec<Tile>) {
-        let mut rng = rand::thread_rng();
+    fn generate_toitsu<R: Rng>(used_tiles: &mut [u8; 34], tiles: &mut Vec<Tile>, rng: &mut R) {
         let mut tile_id: u8;
         loop {
             tile_id = rng.gen_range(0, 34);
@@ -316,68 +373,71 @@ impl Hand {
     }
 
     /// Parses a hand from its text representation.
-    /// force_return: will return even a partial/invalid hand
-    /// TODO: red 5
+    /// force_return: will return even a partial/invalid hand (wrong number of tiles).
+    /// Malformed text, impossible red fives and more than 4 copies of a tile are always an error,
+    /// the error then has the byte offset and the token where parsing failed.
     pub fn from_text(representation: &str, force_return: bool) -> Result<Hand, RiichiError> {
-        lazy_static! {
-            static ref RE: Regex = Regex::new(r"(?P<closed>[0-9mspz]+)|\((?P<chi>[0-9]{3}[msp][0-2])\)|\((?P<pon>p[0-9][mspz][1-3]r?)\)|\((?P<kan>k[0-9][mspz][1-3]?r?)\)|\((?P<shouminkan>s[0-9][mspz][1-3]?r?)\)").unwrap();
-        }
+        Hand::parse(representation, force_return, false)
+    }
 
-        let mut closed = vec![];
+    /// from_text, with sanma the tiles not used in sanma are an error too
+    fn parse(representation: &str, force_return: bool, sanma: bool) -> Result<Hand, RiichiError> {
+        let mut closed: Vec<(usize, &str)> = vec![];
         let mut shapes: Vec<CompleteShape> = vec![];
         let mut called_tiles = vec![];
+        let mut sources: Vec<TileSource> = vec![];
 
-        for cap in RE.captures_iter(representation) {
-            match cap.name("closed") {
-                None => {}
-                Some(value) => closed.push(value.as_str()),
-            }
-            match cap.name("chi") {
-                None => {}
-                Some(chi) => {
-                    if let Ok(mut result) = Hand::parse_chi(chi.as_str()) {
-                        called_tiles.append(&mut result.0);
-                        shapes.push(result.1);
-                    }
-                }
-            }
-            match cap.name("pon") {
-                None => {}
-                Some(pon) => {
-                    if let Ok(mut result) = Hand::parse_pon(pon.as_str()) {
-                        called_tiles.append(&mut result.0);
-                        shapes.push(result.1);
-                    }
-                }
-            }
-            match cap.name("kan") {
-                None => {}
-                Some(value) => {
-                    if let Ok(mut result) = Hand::parse_kan(value.as_str()) {
-                        called_tiles.append(&mut result.0);
-                        shapes.push(result.1);
-                    }
-                }
-            }
-            match cap.name("shouminkan") {
-                None => {}
-                Some(value) => {
-                    if let Ok(mut result) = Hand::parse_kan(value.as_str()) {
-                        called_tiles.append(&mut result.0);
-                        shapes.push(result.1);
+        let mut offset = 0;
+        while offset < representation.len() {
+            let rest = &representation[offset..];
+
+            if rest.starts_with('(') {
+                let token = match rest.find(')') {
+                    Some(end) => &rest[..=end],
+                    None => {
+                        return Err(RiichiError::from(ParseError::UnclosedMeld).at(offset, rest));
                     }
+                };
+
+                let mut result = Hand::parse_meld(&token[1..token.len() - 1])
+                    .map_err(|error| error.at(offset, token))?;
+
+                for tile in result.0.iter().flatten() {
+                    sources.push((*tile, offset, token.to_string()));
                 }
+                called_tiles.append(&mut result.0);
+                shapes.push(result.1);
+
+                offset += token.len();
+            } else {
+                let end = rest.find('(').unwrap_or(rest.len());
+                closed.push((offset, &rest[..end]));
+
+                offset += end;
             }
         }
 
         if closed.len() != 1 {
-            return Err(RiichiError::new(333, "Closed hand not defined correctly"));
+            let error = RiichiError::from(ParseError::ClosedHand);
+            return match closed.get(1) {
+                Some((second_offset, second)) => Err(error.at(*second_offset, second)),
+                None => Err(error),
+            };
         }
 
-        let mut tiles = match Hand::parse_closed_hand(closed.get(0).unwrap()) {
-            Ok(t) => t,
-            Err(e) => return Err(e),
-        };
+        let mut closed_sources = Hand::parse_closed_hand(closed[0].1, closed[0].0)?;
+        let mut tiles: Vec<Option<Tile>> = closed_sources.iter().map(|s| Some(s.0)).collect();
+        sources.append(&mut closed_sources);
+
+        Hand::check_tile_counts(&mut sources)?;
+
+        if sanma {
+            if let Some((tile, offset, token)) =
+                sources.iter().find(|source| source.0.is_removed_in_sanma())
+            {
+                return Err(RiichiError::from(RulesError::SanmaTile(*tile)).at(*offset, token));
+            }
+        }
 
         tiles.append(&mut called_tiles);
 
@@ -398,7 +458,151 @@ impl Hand {
             return Result::Ok(hand);
         }
 
-        Err(RiichiError::new(100, "Couldn't parse hand representation."))
+        Err(ValidationError::InvalidHand.into())
+    }
+
+    /// No tile can be in the hand more than 4 times, there is only one red 5m and 5s and two red 5p.
+    /// Reports the first tile (by position in the text) over the limit.
+    fn check_tile_counts(sources: &mut [TileSource]) -> Result<(), RiichiError> {
+        sources.sort_by_key(|source| source.1);
+
+        let mut counts = [0u8; 34];
+        let mut red_counts = [0u8; 3];
+
+        for (tile, offset, token) in sources.iter() {
+            let id = tile.to_id() as usize - 1;
+            counts[id] += 1;
+            if counts[id] > 4 {
+                return Err(RiichiError::from(ParseError::TooManyCopies(*tile)).at(*offset, token));
+            }
+
+            if tile.is_red {
+                let color = id / 9;
+                red_counts[color] += 1;
+                let limit = if color == 1 { 2 } else { 1 };
+                if red_counts[color] > limit {
+                    return Err(RiichiError::from(ParseError::TooManyRedFives {
+                        tile: *tile,
+                        limit,
+                    })
+                    .at(*offset, token));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The checks of from_text for a hand that wasn't parsed from text: tile counts, red fives,
+    /// tiles not used in sanma, kita and the call order. The offset of an error is the index of the tile.
+    fn check_fields(&self) -> Result<(), RiichiError> {
+        let mut sources: Vec<TileSource> = self
+            .tiles
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(index, tile)| (*tile, index, tile.to_string()))
+            .collect();
+        Hand::check_tile_counts(&mut sources)?;
+
+        if self.sanma {
+            if let Some((tile, offset, token)) =
+                sources.iter().find(|source| source.0.is_removed_in_sanma())
+            {
+                return Err(RiichiError::from(RulesError::SanmaTile(*tile)).at(*offset, token));
+            }
+        }
+
+        if self.kita > 0 {
+            if !self.sanma {
+                return Err(RulesError::KitaNotSanma.into());
+            }
+
+            let kita = Tile::from_text("4z")?;
+            let in_hand = sources.iter().filter(|source| source.0.eq(&kita)).count();
+            if in_hand + self.kita as usize > 4 {
+                return Err(ParseError::TooManyCopies(kita).into());
+            }
+        }
+
+        // every meld is called once, a shouminkan can also be called as a pon before
+        for (index, shape) in self.shapes.iter().enumerate() {
+            let calls = self.call_order.iter().filter(|i| **i == index).count();
+            let max = match shape {
+                CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(_))) => 2,
+                _ => 1,
+            };
+
+            if calls == 0 || calls > max {
+                return Err(ValidationError::CallOrder.into());
+            }
+        }
+
+        if self
+            .call_order
+            .iter()
+            .any(|index| *index >= self.shapes.len())
+        {
+            return Err(ValidationError::CallOrder.into());
+        }
+
+        Ok(())
+    }
+
+    /// Checks the syntax of a meld (without brackets) and parses it
+    fn parse_meld(meld: &str) -> Result<(Vec<Option<Tile>>, CompleteShape), RiichiError> {
+        lazy_static! {
+            static ref CHI: Regex = Regex::new(r"^[0-9]{3}[msp][0-9]$").unwrap();
+            // pon or shouminkan, both need the player who was called from
+            static ref CALLED: Regex = Regex::new(r"^[ps][0-9][mspz][0-9]r?$").unwrap();
+            static ref CLOSED_KAN: Regex = Regex::new(r"^k[0-9][mspz]([0-9]r?)?$").unwrap();
+        }
+
+        let (index, min, max) = if CHI.is_match(meld) {
+            (4, 0, 2)
+        } else if CALLED.is_match(meld) || CLOSED_KAN.is_match(meld) {
+            (3, 1, 3)
+        } else {
+            return Err(ParseError::MeldSyntax.into());
+        };
+
+        if let Some(player) = meld.chars().nth(index).and_then(|c| c.to_digit(10)) {
+            if player < min || player > max {
+                return Err(ParseError::MeldIndex {
+                    index: player,
+                    min,
+                    max,
+                }
+                .into());
+            }
+        }
+
+        if index == 4 {
+            let mut numbers: Vec<u32> = meld[..3]
+                .chars()
+                .map(|c| match c.to_digit(10).unwrap() {
+                    0 => 5,
+                    number => number,
+                })
+                .collect();
+            numbers.sort_unstable();
+            if numbers[0] + 1 != numbers[1] || numbers[1] + 1 != numbers[2] {
+                return Err(ParseError::ChiNotConsecutive.into());
+            }
+
+            return Hand::parse_chi(meld);
+        }
+
+        if &meld[1..3] == "0z" {
+            return Err(ParseError::HonorRedFive.into());
+        }
+        Tile::from_text(&meld[1..3])?;
+
+        if meld.starts_with('p') {
+            Hand::parse_pon(meld)
+        } else {
+            Hand::parse_kan(meld)
+        }
     }
 
     /// A chi looks like this:
@@ -435,7 +639,7 @@ impl Hand {
         }
 
         if !got_called {
-            return Err(RiichiError::new(780, "Called tile was not specified"));
+            return Err(ParseError::CalledTileMissing.into());
         }
 
         tiles.push(Some(tile_1));
@@ -471,7 +675,7 @@ impl Hand {
         };
 
         if n != '0' && r {
-            return Err(RiichiError::new(589, "Only 0 can have r"));
+            return Err(ParseError::RedMarker.into());
         }
 
         let mut first_tile = Tile::from_text(&format!("{}{}", n, c)[..]).unwrap();
@@ -530,7 +734,7 @@ impl Hand {
         };
 
         if n != '0' && r {
-            return Err(RiichiError::new(589, "Only 0 can have r"));
+            return Err(ParseError::RedMarker.into());
         }
 
         let mut first_tile = Tile::from_text(&format!("{}{}", n, c)[..]).unwrap();
@@ -575,61 +779,113 @@ impl Hand {
         Ok((tiles, shape))
     }
 
-    /// Closed part of the hand (or the whole hand, if we have no open tiles / kans)
-    fn parse_closed_hand(closed: &str) -> Result<Vec<Option<Tile>>, RiichiError> {
-        // let's read the hand from the back, because colors are written after the numbers
-        let iter = closed.chars().rev();
-        let mut tiles: Vec<Option<Tile>> = Vec::new();
+    /// Closed part of the hand (or the whole hand, if we have no open tiles / kans).
+    /// offset: where the closed part starts in the whole representation
+    fn parse_closed_hand(closed: &str, offset: usize) -> Result<Vec<TileSource>, RiichiError> {
+        let mut tiles: Vec<TileSource> = Vec::new();
 
-        let mut color: char = 'x';
-        let mut rep: String;
+        let mut color: Option<char> = None;
         let mut found_draw: bool = false;
 
-        for ch in iter {
+        // let's read the hand from the back, because colors are written after the numbers
+        for (i, ch) in closed.char_indices().rev() {
             if ch.is_alphabetic() {
                 // type
-                color = ch;
-            }
-
-            if color != 'x' && ch.is_numeric() {
-                // tile value
-                rep = String::from("");
-                rep.push(ch);
-                rep.push(color);
-                match Tile::from_text(&rep[..]) {
-                    Ok(mut tile) => {
-                        if !found_draw && !tile.is_open && !tile.is_kan {
-                            // the last tile you write in your hand representation is your drawn tile
-                            tile.is_draw = true;
-                            found_draw = true;
-                        }
-                        tiles.push(Option::Some(tile));
-                    }
-                    Err(error) => {
-                        return Err(error);
-                    }
+                color = Some(ch);
+                continue;
+            }
+
+            let position = offset + i;
+            if !ch.is_ascii_digit() {
+                return Err(RiichiError::from(ParseError::UnexpectedCharacter(ch))
+                    .at(position, &ch.to_string()[..]));
+            }
+
+            let rep = match color {
+                Some(c) => format!("{}{}", ch, c),
+                None => {
+                    return Err(RiichiError::from(ParseError::MissingColor)
+                        .at(position, &ch.to_string()[..]));
                 }
+            };
+
+            if rep == "0z" {
+                return Err(RiichiError::from(ParseError::HonorRedFive).at(position, &rep));
+            }
+
+            let mut tile = Tile::from_text(&rep[..]).map_err(|error| error.at(position, &rep))?;
+            if !found_draw && !tile.is_open && !tile.is_kan {
+                // the last tile you write in your hand representation is your drawn tile
+                tile.is_draw = true;
+                found_draw = true;
             }
+            tiles.push((tile, position, rep));
         }
 
         Ok(tiles)
     }
 
+    /// Parses a three-player (sanma) hand. 2m - 8m are not used in sanma, so they are rejected.
+    pub fn from_text_sanma(representation: &str, force_return: bool) -> Result<Hand, RiichiError> {
+        let mut hand = Hand::parse(representation, force_return, true)?;
+        hand.sanma = true;
+
+        Ok(hand)
+    }
+
+    pub fn set_sanma(&mut self, sanma: bool) {
+        self.sanma = sanma;
+        self.reset_shanten();
+    }
+
+    pub fn is_sanma(&self) -> bool {
+        self.sanma
+    }
+
+    /// Sets a kita (4z) from the closed part of the hand aside as nukidora. Only possible in sanma.
+    pub fn nuki_kita(&mut self) -> Result<(), RiichiError> {
+        if !self.sanma {
+            return Err(RulesError::KitaNotSanma.into());
+        }
+
+        let kita = Tile::from_text("4z")?;
+        if self.get_34_array(true)[(kita.to_id() - 1) as usize] == 0 {
+            return Err(IllegalAction::NoKita.into());
+        }
+
+        self.remove_tile(&kita);
+        self.kita += 1;
+
+        Ok(())
+    }
+
+    /// How many kita were set aside as nukidora
+    pub fn get_kita_count(&self) -> u8 {
+        self.kita
+    }
+
     /// Adds a tile to this hand
     pub fn add_tile(&mut self, tile: Tile) {
         self.tiles.push(Some(tile));
         self.tiles.sort();
     }
 
-    /// Removes a tile from this hand
+    /// Removes a tile from this hand.
+    /// A copy with the same red 5 flag is preferred, so discarding 0m from 50m removes the red one.
     pub fn remove_tile(&mut self, tile: &Tile) {
         let mut found: usize = 999;
         for (i, hand_tile) in self.tiles.iter().enumerate() {
             match hand_tile {
                 Some(t) => {
                     if !t.is_open && !t.is_kan && t.to_id() == tile.to_id() {
-                        found = i;
-                        break;
+                        if t.is_red == tile.is_red {
+                            found = i;
+                            break;
+                        }
+
+                        if found == 999 {
+                            found = i;
+                        }
                     }
                 }
                 None => (),
@@ -754,6 +1010,59 @@ impl Hand {
         }
 
         self.shapes.push(CompleteShape::Open(*shape));
+        self.call_order.push(self.shapes.len() - 1);
+    }
+
+    /// Upgrades an existing pon to a shouminkan (added kan) with the 4th tile from the closed part of the hand.
+    pub fn add_shouminkan(&mut self, tile: &Tile) -> Result<(), RiichiError> {
+        let pon_index = self.shapes.iter().position(|shape| match shape {
+            CompleteShape::Open(OpenShape::Pon(tiles)) => tiles[0].eq(tile),
+            _ => false,
+        });
+
+        let pon_index = match pon_index {
+            None => return Err(IllegalAction::NoPonToAddTo.into()),
+            Some(index) => index,
+        };
+
+        let added_index = self.tiles.iter().position(|t| match t {
+            None => false,
+            Some(hand_tile) => hand_tile.eq(tile) && !hand_tile.is_open && !hand_tile.is_kan,
+        });
+
+        let added_index = match added_index {
+            None => return Err(IllegalAction::AddedTileNotInHand.into()),
+            Some(index) => index,
+        };
+
+        let pon_tiles = match self.shapes[pon_index] {
+            CompleteShape::Open(OpenShape::Pon(tiles)) => tiles,
+            _ => unreachable!(),
+        };
+
+        let mut added_tile = self.tiles[added_index].unwrap();
+        added_tile.is_open = true;
+        added_tile.is_kan = true;
+        added_tile.is_draw = false;
+        self.tiles[added_index] = Some(added_tile);
+
+        // the ponned tiles are now kanned tiles
+        for hand_tile in self.tiles.iter_mut().flatten() {
+            if (*hand_tile).eq(tile) && hand_tile.is_pon {
+                hand_tile.is_kan = true;
+            }
+        }
+
+        self.shapes[pon_index] = CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan([
+            pon_tiles[0],
+            pon_tiles[1],
+            pon_tiles[2],
+            added_tile,
+        ])));
+        self.call_order.push(pon_index);
+        self.reset_shanten();
+
+        Ok(())
     }
 
     pub fn add_closed_kan(&mut self, kan: ClosedShape) {
@@ -781,7 +1090,8 @@ impl Hand {
                     }
                 }
 
-                self.shapes.push(CompleteShape::Closed(kan))
+                self.shapes.push(CompleteShape::Closed(kan));
+                self.call_order.push(self.shapes.len() - 1);
             }
             _ => panic!("This is not a kan"),
         }
@@ -850,27 +1160,7 @@ impl Hand {
         let mut color = 'x';
         let mut last_tile: Option<&Tile> = Option::None;
 
-        let mut tiles = self.tiles.clone();
-
-        for complete_shape in self.shapes.iter() {
-            match complete_shape {
-                CompleteShape::Closed(closed_shape) => {
-                    if let ClosedShape::Kantsu(closed_kan) = closed_shape {
-                        self.remove_meld_from_tiles(&closed_kan.to_vec(), &mut tiles);
-                    }
-                }
-                CompleteShape::Open(open_shape) => match open_shape {
-                    OpenShape::Chi(tls) | OpenShape::Pon(tls) => {
-                        self.remove_meld_from_tiles(&tls.to_vec(), &mut tiles);
-                    }
-                    OpenShape::Kan(open_kan) => match open_kan {
-                        OpenKan::Daiminkan(tls) | OpenKan::Shouminkan(tls) => {
-                            self.remove_meld_from_tiles(&tls.to_vec(), &mut tiles)
-                        }
-                    },
-                },
-            }
-        }
+        let tiles = self.tiles_without_melds();
 
         for tile in tiles.iter() {
             match &tile {
@@ -957,6 +1247,37 @@ impl Hand {
         out
     }
 
+    /// Tiles in the closed part of the hand, without melds and closed kans
+    pub fn get_closed_tiles(&self) -> Vec<Tile> {
+        self.tiles_without_melds().into_iter().flatten().collect()
+    }
+
+    fn tiles_without_melds(&self) -> Vec<Option<Tile>> {
+        let mut tiles = self.tiles.clone();
+
+        for complete_shape in self.shapes.iter() {
+            match complete_shape {
+                CompleteShape::Closed(closed_shape) => {
+                    if let ClosedShape::Kantsu(closed_kan) = closed_shape {
+                        self.remove_meld_from_tiles(&closed_kan.to_vec(), &mut tiles);
+                    }
+                }
+                CompleteShape::Open(open_shape) => match open_shape {
+                    OpenShape::Chi(tls) | OpenShape::Pon(tls) => {
+                        self.remove_meld_from_tiles(&tls.to_vec(), &mut tiles);
+                    }
+                    OpenShape::Kan(open_kan) => match open_kan {
+                        OpenKan::Daiminkan(tls) | OpenKan::Shouminkan(tls) => {
+                            self.remove_meld_from_tiles(&tls.to_vec(), &mut tiles)
+                        }
+                    },
+                },
+            }
+        }
+
+        tiles
+    }
+
     fn remove_meld_from_tiles(&self, meld_tiles: &[Tile], tiles: &mut Vec<Option<Tile>>) {
         for meld_tile in meld_tiles.iter() {
             let mut index = 0;
@@ -1180,6 +1501,11 @@ impl Hand {
             if hand.get_tile_count_by_id(*i) == 4 {
                 continue;
             }
+
+            // 2m - 8m are not in the sanma wall
+            if self.sanma && *i >= 2 && *i <= 8 {
+                continue;
+            }
             let drawn_tile = Tile::from_id(*i).unwrap();
             // let tile_str = drawn_tile.to_string();
             hand.add_tile(drawn_tile);
@@ -1221,6 +1547,11 @@ impl Hand {
         self.tiles = new_tiles;
     }
 
+    /// Every way to read this complete or tenpai hand, scored on the table, see analysis::decompositions
+    pub fn decompositions(&self, table: &Table) -> Vec<Decomposition> {
+        analysis::decompositions(self, table)
+    }
+
     pub fn get_drawn_tile(&self) -> Option<&Tile> {
         for p_tile in self.tiles.iter() {
             match p_tile {
@@ -1243,7 +1574,10 @@ impl Default for Hand {
             tiles: vec![],
             array_34: None,
             shapes: vec![],
+            call_order: vec![],
             shanten: 99,
+            sanma: false,
+            kita: 0,
         }
     }
 }
@@ -1254,6 +1588,181 @@ impl fmt::Display for Hand {
     }
 }
 
+/// A tile of the structured hand form, with all its flags
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(transparent)]
+struct FlaggedTile(#[serde(with = "crate::riichi::tile::fields")] Tile);
+
+/// A meld (or closed kan) of the structured hand form, the tiles keep all their flags
+#[derive(Serialize, Deserialize)]
+enum MeldFields {
+    Chi([FlaggedTile; 3]),
+    Pon([FlaggedTile; 3]),
+    Daiminkan([FlaggedTile; 4]),
+    Shouminkan([FlaggedTile; 4]),
+    ClosedKan([FlaggedTile; 4]),
+}
+
+impl MeldFields {
+    fn from_shape(shape: &CompleteShape) -> MeldFields {
+        match shape {
+            CompleteShape::Open(OpenShape::Chi(tiles)) => MeldFields::Chi(tiles.map(FlaggedTile)),
+            CompleteShape::Open(OpenShape::Pon(tiles)) => MeldFields::Pon(tiles.map(FlaggedTile)),
+            CompleteShape::Open(OpenShape::Kan(OpenKan::Daiminkan(tiles))) => {
+                MeldFields::Daiminkan(tiles.map(FlaggedTile))
+            }
+            CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(tiles))) => {
+                MeldFields::Shouminkan(tiles.map(FlaggedTile))
+            }
+            CompleteShape::Closed(ClosedShape::Kantsu(tiles)) => {
+                MeldFields::ClosedKan(tiles.map(FlaggedTile))
+            }
+            // hands only keep melds and closed kans as shapes
+            CompleteShape::Closed(_) => unreachable!(),
+        }
+    }
+
+    fn to_shape(&self) -> CompleteShape {
+        match self {
+            MeldFields::Chi(tiles) => CompleteShape::Open(OpenShape::Chi(tiles.map(|t| t.0))),
+            MeldFields::Pon(tiles) => CompleteShape::Open(OpenShape::Pon(tiles.map(|t| t.0))),
+            MeldFields::Daiminkan(tiles) => {
+                CompleteShape::Open(OpenShape::Kan(OpenKan::Daiminkan(tiles.map(|t| t.0))))
+            }
+            MeldFields::Shouminkan(tiles) => {
+                CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(tiles.map(|t| t.0))))
+            }
+            MeldFields::ClosedKan(tiles) => {
+                CompleteShape::Closed(ClosedShape::Kantsu(tiles.map(|t| t.0)))
+            }
+        }
+    }
+}
+
+/// Structured serde form of a hand, the tiles keep all their flags
+#[derive(Serialize, Deserialize)]
+struct HandFields {
+    tiles: Vec<FlaggedTile>,
+    #[serde(default)]
+    shapes: Vec<MeldFields>,
+    /// empty means the order of shapes
+    #[serde(default)]
+    call_order: Vec<usize>,
+    #[serde(default)]
+    sanma: bool,
+    #[serde(default)]
+    kita: u8,
+}
+
+/// Hands are serialized in the structured form, see the mpsz module for the compact one.
+impl Serialize for Hand {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        HandFields {
+            tiles: self
+                .tiles
+                .iter()
+                .flatten()
+                .copied()
+                .map(FlaggedTile)
+                .collect(),
+            shapes: self.shapes.iter().map(MeldFields::from_shape).collect(),
+            call_order: self.call_order.clone(),
+            sanma: self.sanma,
+            kita: self.kita,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Compact serde form of a sanma hand, the mpsz notation can't tell it from a 4-player one
+#[derive(Serialize, Deserialize)]
+struct SanmaText {
+    hand: String,
+    sanma: bool,
+    #[serde(default)]
+    kita: u8,
+}
+
+/// Reads both the structured form and the mpsz notation, with the same checks as from_text
+impl<'de> Deserialize<'de> for Hand {
+    fn deserialize<D>(deserializer: D) -> Result<Hand, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum HandForm {
+            Compact(String),
+            CompactSanma(SanmaText),
+            Structured(HandFields),
+        }
+
+        match HandForm::deserialize(deserializer)? {
+            HandForm::Compact(text) => Hand::from_text(&text[..], true).map_err(de::Error::custom),
+            HandForm::CompactSanma(text) => {
+                let mut hand = if text.sanma {
+                    Hand::from_text_sanma(&text.hand[..], true)
+                } else {
+                    Hand::from_text(&text.hand[..], true)
+                }
+                .map_err(de::Error::custom)?;
+                hand.kita = text.kita;
+                hand.check_fields().map_err(de::Error::custom)?;
+
+                Ok(hand)
+            }
+            HandForm::Structured(fields) => {
+                let mut hand = Hand::new(fields.tiles.into_iter().map(|t| Some(t.0)).collect());
+                hand.call_order = if fields.call_order.is_empty() {
+                    (0..fields.shapes.len()).collect()
+                } else {
+                    fields.call_order
+                };
+                hand.shapes = fields.shapes.iter().map(MeldFields::to_shape).collect();
+                hand.sanma = fields.sanma;
+                hand.kita = fields.kita;
+                hand.check_fields().map_err(de::Error::custom)?;
+
+                Ok(hand)
+            }
+        }
+    }
+}
+
+/// Compact serde form of a hand in the mpsz notation, for `#[serde(with = "...::hand::mpsz")]` fields.
+/// Sanma hands are `{"hand": "...", "sanma": true, "kita": 0}`.
+/// Flags that the notation can't hold (like id_136) are lost.
+pub mod mpsz {
+    use super::{Hand, SanmaText};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S>(hand: &Hand, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if hand.sanma {
+            return SanmaText {
+                hand: hand.to_string(),
+                sanma: true,
+                kita: hand.kita,
+            }
+            .serialize(serializer);
+        }
+
+        serializer.serialize_str(&hand.to_string()[..])
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Hand, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Hand::deserialize(deserializer)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1337,12 +1846,70 @@ mod tests {
         assert!(hand.validate());
     }
 
+    fn parse_error(representation: &str) -> RiichiError {
+        match Hand::from_text(representation, true) {
+            Ok(_) => panic!("{} should not parse", representation),
+            Err(error) => error,
+        }
+    }
+
+    fn assert_parse_error(representation: &str, code: u16, offset: usize, token: &str) {
+        let error = parse_error(representation);
+
+        assert_eq!(error.code(), code);
+        assert_eq!(error.offset(), Some(offset));
+        assert_eq!(error.token(), Some(token));
+    }
+
     #[test]
     fn validation_bad_5_same_tiles() {
-        let rep = "123m123p11111s22z";
-        let mut hand = Hand::from_text(rep, true).unwrap();
+        // even with force_return
+        assert_parse_error("123m123p11111s22z", 132, 12, "1s");
+        // the 5th copy is in a meld
+        assert_parse_error("123m456p789s1z(k1z)", 132, 14, "(k1z)");
+    }
 
-        assert!(!hand.validate());
+    #[test]
+    fn parse_error_bad_meld_syntax() {
+        assert_parse_error("123m456p789s11z(p1z)", 130, 15, "(p1z)");
+        assert_parse_error("123m456p789s11z(135m0)", 130, 15, "(135m0)");
+        assert_parse_error("123m456p789s(p1z2", 130, 12, "(p1z2");
+        assert_parse_error("123m(p1z2)456p(123s0)", 333, 10, "456p");
+    }
+
+    #[test]
+    fn parse_error_wrong_meld_index() {
+        assert_parse_error("123m456p789s1z(p1z4)", 131, 14, "(p1z4)");
+        assert_parse_error("123m456p789s1z(345p3)", 131, 14, "(345p3)");
+        assert_parse_error("123m456p789s1z(k1z0)", 131, 14, "(k1z0)");
+    }
+
+    #[test]
+    fn parse_error_impossible_red_five() {
+        assert_parse_error("123m0z", 133, 4, "0z");
+        assert_parse_error("123m00p0p", 133, 7, "0p");
+        assert_parse_error("123m0s(p0s1)", 133, 6, "(p0s1)");
+        assert_parse_error("123m456p789s1z(p0z1)", 133, 14, "(p0z1)");
+
+        // two red 5p are fine
+        assert!(Hand::from_text("123m00p456s22z(p1z2)", true).is_ok());
+    }
+
+    #[test]
+    fn parse_error_in_closed_tiles() {
+        assert_parse_error("123m456", 134, 6, "6");
+        assert_parse_error("123m 456p", 134, 4, " ");
+        assert_parse_error("123m9z", 103, 4, "9z");
+    }
+
+    #[test]
+    fn parse_error_display() {
+        let error = parse_error("123m456p789s1z(p1z4)");
+
+        assert_eq!(
+            error.to_string(),
+            "131 : Wrong meld index 4, must be 1 - 3 (at 14: \"(p1z4)\")"
+        );
     }
 
     #[test]
@@ -1760,4 +2327,166 @@ mod tests {
 
         assert_eq!(reds, 1);
     }
+
+    #[test]
+    fn parse_sanma_hand() {
+        assert!(Hand::from_text_sanma("19m123456p789s115z", false).is_ok());
+
+        match Hand::from_text_sanma("234m123456p789s11z", false) {
+            Ok(_) => panic!("2m - 4m are not used in sanma"),
+            Err(error) => {
+                assert_eq!(error.code(), 127);
+                assert_eq!(error.offset(), Some(0));
+                assert_eq!(error.token(), Some("2m"));
+            }
+        }
+
+        // the first tile in the text, even in a meld
+        match Hand::from_text_sanma("19m123456p78s11z(p5m1)", false) {
+            Ok(_) => panic!("5m is not used in sanma"),
+            Err(error) => {
+                assert_eq!(error.offset(), Some(16));
+                assert_eq!(error.token(), Some("(p5m1)"));
+            }
+        }
+    }
+
+    #[test]
+    fn sanma_ukeire_ignores_removed_manzu() {
+        let has_2m = |hand: &mut Hand| {
+            hand.find_shanten_improving_tiles(None)[0]
+                .1
+                .iter()
+                .any(|(tile, _)| tile.to_string() == "2m")
+        };
+
+        let mut hand = Hand::from_text("1m123456p789s115z", false).unwrap();
+        assert!(has_2m(&mut hand));
+
+        let mut hand = Hand::from_text_sanma("1m123456p789s115z", false).unwrap();
+        assert!(!has_2m(&mut hand));
+    }
+
+    #[test]
+    fn nuki_kita() {
+        let mut hand = Hand::from_text("1m123456p789s1144z", false).unwrap();
+        assert_eq!(hand.nuki_kita().unwrap_err().code(), 128);
+
+        hand.set_sanma(true);
+        hand.nuki_kita().unwrap();
+        hand.nuki_kita().unwrap();
+
+        assert_eq!(hand.get_kita_count(), 2);
+        assert_eq!(hand.to_string(), "1m123456p789s11z");
+        assert_eq!(hand.nuki_kita().unwrap_err().code(), 129);
+    }
+
+    #[test]
+    fn serde_structured_hand() {
+        let hand = Hand::from_text("123m456p789s1z0s(p7z3)", false).unwrap();
+
+        let json = serde_json::to_string(&hand).unwrap();
+        let back: Hand = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(back.to_string(), "123m456p789s1z0s(p7z3)");
+        assert_eq!(back.get_open_shapes().len(), 1);
+        let drawn = back.get_tiles().iter().flatten().find(|t| t.is_draw);
+        assert_eq!(drawn.unwrap().to_string(), "0s");
+        let called = back
+            .get_tiles()
+            .iter()
+            .flatten()
+            .find(|t| t.called_from > 0);
+        assert_eq!(called.unwrap().called_from, 3);
+
+        // the melds keep who they were called from
+        match back.get_open_shapes()[0] {
+            OpenShape::Pon(tiles) => assert!(tiles.iter().any(|t| t.called_from == 3)),
+            _ => panic!("the meld is a pon"),
+        }
+    }
+
+    #[test]
+    fn serde_compact_hand() {
+        #[derive(Serialize, Deserialize)]
+        struct Problem {
+            #[serde(with = "mpsz")]
+            hand: Hand,
+        }
+
+        let problem = Problem {
+            hand: Hand::from_text("123m456p789s1z(k2z)", false).unwrap(),
+        };
+
+        let json = serde_json::to_string(&problem).unwrap();
+        assert_eq!(json, "{\"hand\":\"123m456p789s1z(k2z)\"}");
+
+        let back: Problem = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.hand.to_string(), "123m456p789s1z(k2z)");
+
+        assert!(serde_json::from_str::<Hand>("\"123m(p1z4)\"").is_err());
+    }
+
+    #[test]
+    fn serde_compact_sanma_hand() {
+        #[derive(Serialize, Deserialize)]
+        struct Problem {
+            #[serde(with = "mpsz")]
+            hand: Hand,
+        }
+
+        let mut hand = Hand::from_text_sanma("1m123456p789s1144z", false).unwrap();
+        hand.nuki_kita().unwrap();
+        let json = serde_json::to_string(&Problem { hand }).unwrap();
+        assert_eq!(
+            json,
+            "{\"hand\":{\"hand\":\"1m123456p789s114z\",\"sanma\":true,\"kita\":1}}"
+        );
+
+        let back: Problem = serde_json::from_str(&json).unwrap();
+        assert!(back.hand.is_sanma());
+        assert_eq!(back.hand.get_kita_count(), 1);
+
+        // 2m - 8m are not used in sanma, and there are only 4 kita
+        assert!(serde_json::from_str::<Hand>(r#"{"hand": "123m", "sanma": true}"#).is_err());
+        assert!(
+            serde_json::from_str::<Hand>(r#"{"hand": "1m444z", "sanma": true, "kita": 2}"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn serde_structured_hand_is_checked() {
+        let hand = Hand::from_text("123m456p789s1z0s(p7z3)", false).unwrap();
+        let json = serde_json::to_value(&hand).unwrap();
+        let invalid = |change: &dyn Fn(&mut serde_json::Value)| {
+            let mut json = json.clone();
+            change(&mut json);
+            serde_json::from_value::<Hand>(json).is_err()
+        };
+
+        assert!(!invalid(&|_| ()));
+        // a fifth 1m
+        assert!(invalid(&|json| {
+            let tile = json["tiles"][0].clone();
+            for _ in 0..4 {
+                json["tiles"].as_array_mut().unwrap().push(tile.clone());
+            }
+        }));
+        // a second red 5s
+        assert!(invalid(&|json| {
+            let tiles = json["tiles"].as_array_mut().unwrap();
+            let red_five = tiles.iter().find(|t| t["is_red"] == true).unwrap().clone();
+            tiles.push(red_five);
+        }));
+        // 2m and 3m in sanma
+        assert!(invalid(
+            &|json| json["sanma"] = serde_json::Value::from(true)
+        ));
+        // the call order has to name every meld once
+        assert!(invalid(
+            &|json| json["call_order"] = serde_json::json!([0, 0])
+        ));
+        assert!(invalid(&|json| json["call_order"] = serde_json::json!([1])));
+    }
 }
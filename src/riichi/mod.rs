@@ -1,5 +1,14 @@
+pub mod analysis;
+pub mod final_score;
+pub mod fu;
+pub mod game;
+pub mod generator;
 pub mod hand;
+pub mod progress;
+pub mod quiz;
+pub mod render;
 pub mod riichi_error;
+pub mod round;
 pub mod rules;
 pub mod scores;
 mod shanten;
@@ -7,4 +16,5 @@ mod shape_finder;
 pub mod shapes;
 pub mod table;
 pub mod tile;
+pub mod wall;
 pub mod yaku;
@@ -0,0 +1,345 @@
+use crate::riichi::analysis;
+use crate::riichi::hand::Hand;
+use crate::riichi::shapes::OpenShape;
+use crate::riichi::table::Table;
+use crate::riichi::tile::Tile;
+use crate::riichi::yaku::Yaku;
+use serde::{Deserialize, Serialize};
+
+/// How far my hand is from a yaku
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YakuProgress {
+    pub yaku: Yaku,
+    /// Tiles missing from the pattern of the yaku, 0 when the pattern is already there.
+    /// The rest of the hand is not checked, that's what shanten is for.
+    /// For riichi it's the shanten, 0 in tenpai.
+    pub tiles_away: u8,
+}
+
+/// A tile of value ukeire, weighted by the yaku it keeps in the hand
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValueTile {
+    pub tile: Tile,
+    pub count: u8,
+    /// yaku (except riichi) with their whole pattern in the hand after drawing this tile
+    pub yaku: Vec<Yaku>,
+    /// 1 + han of these yaku
+    pub weight: u8,
+}
+
+/// Ukeire of a discard with every tile weighted by value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValueUkeireOption {
+    pub discard: Option<Tile>,
+    pub tiles: Vec<ValueTile>,
+    pub total: u8,
+    /// sum of count * weight
+    pub value: u16,
+}
+
+/// Yaku my hand (of any shanten) can still get, the closest first.
+/// Only yaku with a pattern are checked: tanyao, yakuhai, iipeikou, sanshoku, ittsu,
+/// toitoi, chiitoitsu, honitsu, chinitsu and riichi.
+pub fn yaku_progress(table: &Table) -> Vec<YakuProgress> {
+    let mut hand = table.get_my_hand().clone();
+    let mut progress = pattern_progress(table, &hand);
+
+    if hand.is_closed() {
+        progress.push(YakuProgress {
+            yaku: Yaku::Riichi,
+            tiles_away: hand.shanten().max(0) as u8,
+        });
+    }
+
+    progress.sort_by_key(|p| p.tiles_away);
+
+    progress
+}
+
+/// Ukeire of every discard of my hand, the tiles weighted by the yaku they keep. The most valuable first.
+pub fn value_ukeire(table: &Table) -> Vec<ValueUkeireOption> {
+    let mut table = table.clone();
+    let hand = table.get_my_hand().clone();
+    let mut options = vec![];
+
+    for (discard, tiles, total) in hand.clone().find_shanten_improving_tiles(None) {
+        let mut value_tiles = vec![];
+
+        for (tile, count) in tiles {
+            let mut next = hand.clone();
+            if let Some(discard) = discard {
+                next.remove_tile(&discard);
+            }
+            next.add_tile(tile);
+
+            let yaku: Vec<Yaku> = pattern_progress(&table, &next)
+                .into_iter()
+                .filter(|p| p.tiles_away == 0)
+                .map(|p| p.yaku)
+                .collect();
+
+            table.set_my_hand(next);
+            let han: u8 = yaku.iter().map(|y| y.get_han(&mut table)).sum();
+
+            value_tiles.push(ValueTile {
+                tile: analysis::plain(&tile),
+                count,
+                yaku,
+                weight: 1 + han,
+            });
+        }
+
+        options.push(ValueUkeireOption {
+            discard: discard.as_ref().map(analysis::plain),
+            value: value_tiles
+                .iter()
+                .map(|t| t.count as u16 * t.weight as u16)
+                .sum(),
+            tiles: value_tiles,
+            total,
+        });
+    }
+
+    options.sort_by_key(|o| std::cmp::Reverse(o.value));
+
+    options
+}
+
+/// Progress of the yaku with a pattern, impossible ones (because of calls) are left out
+fn pattern_progress(table: &Table, hand: &Hand) -> Vec<YakuProgress> {
+    let counts = hand.get_34_array(false);
+    let melds: Vec<Vec<u8>> = hand
+        .get_open_shapes()
+        .iter()
+        .map(|shape| shape.get_tiles().iter().map(|t| t.to_id()).collect())
+        .collect();
+    let closed = hand.is_closed();
+    let has_chi = hand
+        .get_open_shapes()
+        .iter()
+        .any(|shape| matches!(shape, OpenShape::Chi(_)));
+
+    let mut progress = vec![];
+    let mut add = |yaku: Yaku, tiles_away: Option<u8>| {
+        if let Some(tiles_away) = tiles_away {
+            progress.push(YakuProgress { yaku, tiles_away });
+        }
+    };
+
+    add(
+        Yaku::Tanyao,
+        if melds.iter().flatten().any(|id| is_terminal_or_honor(*id)) {
+            None
+        } else {
+            Some(count_ids(&counts, is_terminal_or_honor))
+        },
+    );
+
+    for (yaku, id) in yakuhai(table) {
+        add(yaku, Some(3 - counts[(id - 1) as usize].min(3)));
+    }
+
+    if closed {
+        add(Yaku::Iipeikou, Some(min_missing(&counts, &sequences(), 2)));
+        add(
+            Yaku::Chiitoitsu,
+            Some(7 - counts.iter().filter(|c| **c >= 2).count().min(7) as u8),
+        );
+    }
+
+    let sanshoku: Vec<Vec<u8>> = (1..=7)
+        .map(|n| {
+            (0..3)
+                .flat_map(|suit| (n..n + 3).map(move |i| suit * 9 + i))
+                .collect()
+        })
+        .collect();
+    add(
+        Yaku::SanshokuDoujun,
+        Some(min_missing(&counts, &sanshoku, 1)),
+    );
+
+    let ittsu: Vec<Vec<u8>> = (0..3)
+        .map(|suit| (1..=9).map(|i| suit * 9 + i).collect())
+        .collect();
+    add(Yaku::Ittsu, Some(min_missing(&counts, &ittsu, 1)));
+
+    if !has_chi {
+        add(Yaku::Toitoi, Some(toitoi_missing(&counts)));
+    }
+
+    for (yaku, honors) in [(Yaku::Honitsu, true), (Yaku::Chinitsu, false)].iter() {
+        let tiles_away = (0..3)
+            .filter(|suit| {
+                melds
+                    .iter()
+                    .flatten()
+                    .all(|id| in_flush(*id, *suit, *honors))
+            })
+            .map(|suit| count_ids(&counts, |id| !in_flush(id, suit, *honors)))
+            .min();
+
+        add(yaku.clone(), tiles_away);
+    }
+
+    progress
+}
+
+/// Yakuhai of this table and their tiles
+fn yakuhai(table: &Table) -> Vec<(Yaku, u8)> {
+    let mut yakuhai = vec![
+        (Yaku::WhiteDragons, 32),
+        (Yaku::GreenDragons, 33),
+        (Yaku::RedDragons, 34),
+    ];
+
+    match table.get_prevalent_wind() {
+        Some(1) => yakuhai.push((Yaku::EastRound, 28)),
+        Some(2) => yakuhai.push((Yaku::SouthRound, 29)),
+        Some(3) => yakuhai.push((Yaku::WestRound, 30)),
+        _ => {}
+    }
+
+    match table.get_my_seat_wind() {
+        Some(1) => yakuhai.push((Yaku::EastSeat, 28)),
+        Some(2) => yakuhai.push((Yaku::SouthSeat, 29)),
+        Some(3) => yakuhai.push((Yaku::WestSeat, 30)),
+        Some(4) => yakuhai.push((Yaku::NorthSeat, 31)),
+        _ => {}
+    }
+
+    yakuhai
+}
+
+/// Ids of the sequences 123 - 789 of every suit
+fn sequences() -> Vec<Vec<u8>> {
+    (0..3)
+        .flat_map(|suit| (1..=7).map(move |n| (n..n + 3).map(|i| suit * 9 + i).collect()))
+        .collect()
+}
+
+/// The fewest tiles missing to have `copies` of every tile of one of the patterns
+fn min_missing(counts: &[u8; 34], patterns: &[Vec<u8>], copies: u8) -> u8 {
+    patterns
+        .iter()
+        .map(|ids| {
+            ids.iter()
+                .map(|id| copies - counts[(id - 1) as usize].min(copies))
+                .sum::<u8>()
+        })
+        .min()
+        .unwrap_or(0)
+}
+
+/// Tiles missing for 4 triplets and a pair
+fn toitoi_missing(counts: &[u8; 34]) -> u8 {
+    (0..34)
+        .map(|pair| {
+            let mut triplets: Vec<u8> = (0..34)
+                .filter(|i| *i != pair)
+                .map(|i| 3 - counts[i].min(3))
+                .collect();
+            triplets.sort_unstable();
+
+            2 - counts[pair].min(2) + triplets.iter().take(4).sum::<u8>()
+        })
+        .min()
+        .unwrap()
+}
+
+fn count_ids<F>(counts: &[u8; 34], filter: F) -> u8
+where
+    F: Fn(u8) -> bool,
+{
+    (1..=34)
+        .filter(|id| filter(*id))
+        .map(|id| counts[(id - 1) as usize])
+        .sum()
+}
+
+fn is_terminal_or_honor(id: u8) -> bool {
+    id > 27 || id % 9 == 1 || id.is_multiple_of(9)
+}
+
+/// Is the tile of this suit (0 - 2), or an honor with honors allowed
+fn in_flush(id: u8, suit: u8, honors: bool) -> bool {
+    if id > 27 {
+        return honors;
+    }
+
+    (id - 1) / 9 == suit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Map, Value};
+
+    fn table(hand: &str) -> Table {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from(hand));
+        map.insert("my_seat_wind".to_string(), Value::from(2));
+        map.insert("prevalent_wind".to_string(), Value::from(1));
+
+        Table::from_map(&map).unwrap()
+    }
+
+    fn tiles_away(progress: &[YakuProgress], yaku: Yaku) -> Option<u8> {
+        progress
+            .iter()
+            .find(|p| p.yaku == yaku)
+            .map(|p| p.tiles_away)
+    }
+
+    #[test]
+    fn progress_of_tenpai_hand() {
+        let progress = yaku_progress(&table("234m234p23s678s55z"));
+
+        assert_eq!(tiles_away(&progress, Yaku::Riichi), Some(0));
+        assert_eq!(tiles_away(&progress, Yaku::SanshokuDoujun), Some(1));
+        assert_eq!(tiles_away(&progress, Yaku::WhiteDragons), Some(1));
+        assert_eq!(tiles_away(&progress, Yaku::Tanyao), Some(2));
+        assert_eq!(tiles_away(&progress, Yaku::SouthSeat), Some(3));
+        assert_eq!(tiles_away(&progress, Yaku::EastRound), Some(3));
+        assert_eq!(tiles_away(&progress, Yaku::Ittsu), Some(4));
+        assert_eq!(tiles_away(&progress, Yaku::Honitsu), Some(6));
+        assert_eq!(tiles_away(&progress, Yaku::Chiitoitsu), Some(6));
+        assert_eq!(tiles_away(&progress, Yaku::Toitoi), Some(8));
+        assert!(tiles_away(&progress, Yaku::EastSeat).is_none());
+        assert!(progress
+            .windows(2)
+            .all(|p| p[0].tiles_away <= p[1].tiles_away));
+    }
+
+    #[test]
+    fn progress_with_calls() {
+        let progress = yaku_progress(&table("234m678m345p5z(p1z2)"));
+
+        assert!(tiles_away(&progress, Yaku::Tanyao).is_none());
+        assert!(tiles_away(&progress, Yaku::Riichi).is_none());
+        assert!(tiles_away(&progress, Yaku::Chiitoitsu).is_none());
+        assert!(tiles_away(&progress, Yaku::Chinitsu).is_none());
+        assert_eq!(tiles_away(&progress, Yaku::EastRound), Some(0));
+        assert_eq!(tiles_away(&progress, Yaku::Honitsu), Some(3));
+    }
+
+    #[test]
+    fn value_ukeire_weights_yaku() {
+        // discarding 1m waits on 6m and 9m, only 6m keeps tanyao
+        let options = value_ukeire(&table("78m456p678s22p567s1m"));
+        let best = &options[0];
+        let weight = |tile: &str| {
+            best.tiles
+                .iter()
+                .find(|t| t.tile.to_string() == tile)
+                .unwrap()
+                .weight
+        };
+
+        assert_eq!(best.discard.unwrap().to_string(), "1m");
+        assert_eq!(best.total, 8);
+        assert_eq!(weight("6m"), 2);
+        assert_eq!(weight("9m"), 1);
+        assert_eq!(best.value, 12);
+    }
+}
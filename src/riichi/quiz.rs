@@ -0,0 +1,635 @@
+use crate::riichi::analysis::{self, UkeireOption};
+use crate::riichi::fu::Fu;
+use crate::riichi::generator;
+use crate::riichi::hand::Hand;
+use crate::riichi::scores::Score;
+use crate::riichi::shapes::{ClosedShape, CompleteShape, OpenShape, Shape};
+use crate::riichi::table::Table;
+use crate::riichi::tile::Tile;
+use crate::riichi::yaku::{Yaku, YakuFinder};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+
+/// Hands further from tenpai are too rare to generate from complete hands
+pub const MAX_NANIKIRU_SHANTEN: i8 = 4;
+
+/// Generated hands that don't have the wanted shanten are thrown away, this many times at most
+const MAX_ATTEMPTS: usize = 10000;
+
+/// What to discard? A closed 14 tile hand with the efficiency-based answer key.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NanikiruProblem {
+    pub hand: Hand,
+    pub shanten: i8,
+    /// Every discard that keeps the shanten, the best ukeire first
+    pub answers: Vec<UkeireOption>,
+    /// How close the ukeire of the second best discard is to the best one:
+    /// 0.0 for a single good discard, 1.0 when the best discards are tied
+    pub difficulty: f32,
+}
+
+impl NanikiruProblem {
+    /// A random problem at this shanten (0 - MAX_NANIKIRU_SHANTEN). The same seed always gives the same problem.
+    pub fn generate(seed: u64, shanten: i8) -> Option<NanikiruProblem> {
+        if !(0..=MAX_NANIKIRU_SHANTEN).contains(&shanten) {
+            return None;
+        }
+
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        for _ in 0..MAX_ATTEMPTS {
+            // a complete hand with a few tiles swapped for random ones looks like a real hand
+            let swaps = shanten as usize + 1 + rng.gen_range(0, 2);
+            let mut hand = broken_complete_hand(swaps, &mut rng);
+
+            if hand.shanten() != shanten {
+                continue;
+            }
+
+            let answers = analysis::ukeire(&mut hand);
+            let difficulty = match answers.as_slice() {
+                [best, second, ..] if best.total > 0 => second.total as f32 / best.total as f32,
+                _ => 0.0,
+            };
+
+            return Some(NanikiruProblem {
+                hand,
+                shanten,
+                answers,
+                difficulty,
+            });
+        }
+
+        None
+    }
+
+    /// Discards with the most ukeire
+    pub fn best_discards(&self) -> Vec<Tile> {
+        let best = match self.answers.first() {
+            None => return vec![],
+            Some(answer) => answer.total,
+        };
+
+        self.answers
+            .iter()
+            .filter(|answer| answer.total == best)
+            .filter_map(|answer| answer.discard)
+            .collect()
+    }
+
+    pub fn is_correct(&self, discard: &Tile) -> bool {
+        self.best_discards()
+            .iter()
+            .any(|tile| tile.to_id() == discard.to_id())
+    }
+}
+
+/// How much is this hand worth? A complete hand in a random situation with the correct answer.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScoringProblem {
+    /// the last closed tile is the winning tile
+    pub hand: Hand,
+    pub tsumo: bool,
+    pub riichi: bool,
+    /// 1 = east - 4 = north, east is the dealer
+    pub seat_wind: u8,
+    pub prevalent_wind: u8,
+    pub dora_indicators: Vec<Tile>,
+    pub honba: u8,
+    /// who dealt in (1 = shimocha - 3 = kamicha), None for tsumo
+    pub discarder: Option<u8>,
+    pub yaku: Vec<Yaku>,
+    /// dora and red fives in the hand
+    pub dora: u8,
+    /// where the fu come from, empty when they are not counted (mangan and above)
+    pub fu: Vec<Fu>,
+    pub score: Score,
+    /// points paid by every player (relative, 0 = the winner), with honba
+    pub payments: [i32; 4],
+}
+
+impl ScoringProblem {
+    /// A random winning hand - closed, open, with kans, chiitoitsu or kokushi - in a random situation.
+    /// The same seed always gives the same problem, None when no hand with a yaku was found.
+    pub fn generate(seed: u64) -> Option<ScoringProblem> {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        for _ in 0..MAX_ATTEMPTS {
+            let hand = match generator::random_complete_hand(&mut rng) {
+                None => continue,
+                Some(hand) => hand,
+            };
+
+            let kans = hand
+                .get_shapes()
+                .iter()
+                .filter(|shape| {
+                    matches!(
+                        shape,
+                        CompleteShape::Closed(ClosedShape::Kantsu(_))
+                            | CompleteShape::Open(OpenShape::Kan(_))
+                    )
+                })
+                .count();
+            let tsumo = rng.gen_bool(0.5);
+
+            let mut problem = ScoringProblem {
+                riichi: hand.is_closed() && rng.gen_bool(0.5),
+                hand,
+                tsumo,
+                seat_wind: rng.gen_range(1, 5),
+                prevalent_wind: rng.gen_range(1, 3),
+                dora_indicators: vec![],
+                honba: rng.gen_range(0, 4),
+                discarder: if tsumo {
+                    None
+                } else {
+                    Some(rng.gen_range(1, 4))
+                },
+                yaku: vec![],
+                dora: 0,
+                fu: vec![],
+                score: Score::new(0, 0, false, false),
+                payments: [0; 4],
+            };
+
+            let mut counts = problem.hand.get_34_array(false);
+            let ids: Vec<u8> = (1..=34).collect();
+            for _ in 0..kans + 1 {
+                let id = generator::random_tile_id(&counts, 3, &ids, &mut rng).unwrap();
+                counts[(id - 1) as usize] += 1;
+                problem.dora_indicators.push(Tile::from_id(id).unwrap());
+            }
+
+            let mut table = problem.table();
+            let (yaku, score, fu) = match YakuFinder::new().find_with_fu(&mut table) {
+                Some(result) if !result.0.is_empty() => result,
+                _ => continue,
+            };
+
+            problem.dora = table.count_my_dora();
+            problem.payments = table.win_payments(&yaku, &score, problem.discarder);
+            problem.yaku = yaku;
+            problem.score = score;
+            problem.fu = fu;
+
+            return Some(problem);
+        }
+
+        None
+    }
+
+    /// The table of this situation, from my point of view
+    pub fn table(&self) -> Table {
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(self.hand.clone());
+        table.set_my_tsumo(self.tsumo);
+        table.set_my_riichi(self.riichi);
+        table.set_my_seat_wind(self.seat_wind);
+        table.set_prevalent_wind(self.prevalent_wind);
+        table.set_dora_indicators(self.dora_indicators.clone());
+        table.set_tsumibo(self.honba);
+
+        table
+    }
+
+    /// Points the winner gets from the other players, with honba
+    pub fn points(&self) -> u32 {
+        self.payments[0] as u32
+    }
+}
+
+/// One wait of a machi problem with every decomposition of the hand completed by it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MachiWait {
+    pub tile: Tile,
+    pub decompositions: Vec<Vec<Shape>>,
+}
+
+/// What does this hand wait on? A closed 13 tile chinitsu hand in tenpai with all of its waits.
+/// Waits on a tile the hand already has 4 of are not counted.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MachiProblem {
+    pub hand: Hand,
+    pub waits: Vec<MachiWait>,
+}
+
+impl MachiProblem {
+    /// Every tenpai hand of this suit (m, p or s), the most difficult first
+    pub fn all(suit: char) -> Vec<MachiProblem> {
+        if !['m', 'p', 's'].contains(&suit) {
+            return vec![];
+        }
+
+        let mut hands = vec![];
+        suit_hands(&mut [0; 9], 0, 13, &mut hands);
+
+        let mut problems: Vec<MachiProblem> = hands
+            .iter()
+            .filter_map(|counts| machi_problem(counts, suit))
+            .collect();
+        problems.sort_by_key(|problem| std::cmp::Reverse(problem.difficulty()));
+
+        problems
+    }
+
+    /// A random problem of this suit (m, p or s) with at least `min_waits` waits.
+    /// The same seed always gives the same problem.
+    pub fn generate(seed: u64, suit: char, min_waits: usize) -> Option<MachiProblem> {
+        if !['m', 'p', 's'].contains(&suit) {
+            return None;
+        }
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut wall: Vec<usize> = (0..36).map(|i| i / 4).collect();
+
+        for _ in 0..MAX_ATTEMPTS {
+            wall.shuffle(&mut rng);
+
+            let mut counts = [0u8; 9];
+            for i in wall.iter().take(13) {
+                counts[*i] += 1;
+            }
+
+            match machi_problem(&counts, suit) {
+                Some(problem) if problem.waits.len() >= min_waits => return Some(problem),
+                _ => continue,
+            }
+        }
+
+        None
+    }
+
+    pub fn wait_tiles(&self) -> Vec<Tile> {
+        self.waits.iter().map(|wait| wait.tile).collect()
+    }
+
+    /// Number of waits and of decompositions that explain them
+    pub fn difficulty(&self) -> usize {
+        self.waits.len()
+            + self
+                .waits
+                .iter()
+                .map(|wait| wait.decompositions.len())
+                .sum::<usize>()
+    }
+
+    /// The answer has to have every wait and nothing else
+    pub fn is_correct(&self, waits: &[Tile]) -> bool {
+        let mut answer: Vec<u8> = waits.iter().map(|tile| tile.to_id()).collect();
+        answer.sort_unstable();
+        answer.dedup();
+
+        let correct: Vec<u8> = self.waits.iter().map(|wait| wait.tile.to_id()).collect();
+
+        answer == correct
+    }
+}
+
+/// A random closed complete hand with `swaps` tiles replaced by random tiles, the last one is the drawn tile
+fn broken_complete_hand<R: Rng>(swaps: usize, rng: &mut R) -> Hand {
+    let mut tiles = Hand::random_complete_hand_with_rng(true, false, rng).get_closed_tiles();
+    let mut counts = [0u8; 34];
+    for tile in tiles.iter_mut() {
+        tile.is_draw = false;
+        counts[(tile.to_id() - 1) as usize] += 1;
+    }
+
+    tiles.shuffle(rng);
+
+    for tile in tiles.iter_mut().take(swaps) {
+        counts[(tile.to_id() - 1) as usize] -= 1;
+
+        let id = loop {
+            let id = rng.gen_range(1, 35);
+            if counts[(id - 1) as usize] < 4 {
+                break id;
+            }
+        };
+
+        counts[(id - 1) as usize] += 1;
+        *tile = Tile::from_id(id).unwrap();
+    }
+
+    tiles[swaps - 1].is_draw = true;
+    tiles.swap(swaps - 1, 13);
+
+    Hand::new(tiles.into_iter().map(Some).collect())
+}
+
+/// Every hand of one suit with `left` more tiles, as counts of 1 - 9
+fn suit_hands(counts: &mut [u8; 9], from: usize, left: u8, hands: &mut Vec<[u8; 9]>) {
+    if left == 0 {
+        hands.push(*counts);
+        return;
+    }
+
+    for i in from..9 {
+        if counts[i] < 4 {
+            counts[i] += 1;
+            suit_hands(counts, i, left - 1, hands);
+            counts[i] -= 1;
+        }
+    }
+}
+
+fn machi_problem(counts: &[u8; 9], suit: char) -> Option<MachiProblem> {
+    let text: String = (0..9)
+        .flat_map(|i| std::iter::repeat_n((b'1' + i as u8) as char, counts[i] as usize))
+        .collect();
+
+    let mut waits = vec![];
+    for i in 0..9 {
+        let mut complete = *counts;
+        complete[i] += 1;
+
+        if complete[i] > 4 || !is_complete_suit(&complete) {
+            continue;
+        }
+
+        waits.push(MachiWait {
+            tile: Tile::from_text(&format!("{}{}", i + 1, suit)).ok()?,
+            decompositions: suit_decompositions(&complete, suit),
+        });
+    }
+
+    if waits.is_empty() {
+        return None;
+    }
+
+    Some(MachiProblem {
+        hand: Hand::from_text(&format!("{}{}", text, suit), false).ok()?,
+        waits,
+    })
+}
+
+/// 14 tiles of one suit as 4 sets and a pair, or chiitoitsu
+fn is_complete_suit(counts: &[u8; 9]) -> bool {
+    if counts.iter().all(|count| *count == 0 || *count == 2) {
+        return true;
+    }
+
+    (0..9).any(|i| {
+        let mut sets = *counts;
+        if sets[i] < 2 {
+            return false;
+        }
+
+        sets[i] -= 2;
+        is_sets(&mut sets)
+    })
+}
+
+/// The same variants ShapeFinder finds in a complete hand of one suit, much faster
+fn suit_decompositions(counts: &[u8; 9], suit: char) -> Vec<Vec<Shape>> {
+    let tiles: Vec<Tile> = (1..=9)
+        .map(|i| Tile::from_text(&format!("{}{}", i, suit)).unwrap())
+        .collect();
+    let shape = |ids: &[usize]| {
+        let shape_tiles: Vec<Tile> = ids.iter().map(|i| tiles[*i]).collect();
+        Shape::from_tiles(&shape_tiles, false, true).unwrap()
+    };
+
+    let mut variants = vec![];
+
+    if counts.iter().all(|count| *count == 0 || *count == 2) {
+        variants.push(
+            (0..9)
+                .filter(|i| counts[*i] == 2)
+                .map(|i| shape(&[i, i]))
+                .collect(),
+        );
+    }
+
+    for i in 0..9 {
+        if counts[i] < 2 {
+            continue;
+        }
+
+        let mut sets = *counts;
+        sets[i] -= 2;
+        split_sets(&mut sets, &mut vec![shape(&[i, i])], &shape, &mut variants);
+    }
+
+    variants
+}
+
+fn split_sets<F>(
+    counts: &mut [u8; 9],
+    current: &mut Vec<Shape>,
+    shape: &F,
+    variants: &mut Vec<Vec<Shape>>,
+) where
+    F: Fn(&[usize]) -> Shape,
+{
+    let i = match counts.iter().position(|count| *count > 0) {
+        None => {
+            variants.push(current.clone());
+            return;
+        }
+        Some(i) => i,
+    };
+
+    if counts[i] >= 3 {
+        counts[i] -= 3;
+        current.push(shape(&[i, i, i]));
+        split_sets(counts, current, shape, variants);
+        current.pop();
+        counts[i] += 3;
+    }
+
+    if i < 7 && counts[i + 1] > 0 && counts[i + 2] > 0 {
+        for count in counts[i..i + 3].iter_mut() {
+            *count -= 1;
+        }
+        current.push(shape(&[i, i + 1, i + 2]));
+        split_sets(counts, current, shape, variants);
+        current.pop();
+        for count in counts[i..i + 3].iter_mut() {
+            *count += 1;
+        }
+    }
+}
+
+fn is_sets(counts: &mut [u8; 9]) -> bool {
+    let i = match counts.iter().position(|count| *count > 0) {
+        None => return true,
+        Some(i) => i,
+    };
+
+    if counts[i] >= 3 {
+        counts[i] -= 3;
+        let sets = is_sets(counts);
+        counts[i] += 3;
+
+        if sets {
+            return true;
+        }
+    }
+
+    if i < 7 && counts[i + 1] > 0 && counts[i + 2] > 0 {
+        for count in counts[i..i + 3].iter_mut() {
+            *count -= 1;
+        }
+        let sets = is_sets(counts);
+        for count in counts[i..i + 3].iter_mut() {
+            *count += 1;
+        }
+
+        return sets;
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riichi::fu::total_fu;
+    use crate::riichi::shape_finder::ShapeFinder;
+
+    #[test]
+    fn nanikiru_at_shanten() {
+        for shanten in 0..=MAX_NANIKIRU_SHANTEN {
+            let mut problem = NanikiruProblem::generate(shanten as u64, shanten).unwrap();
+
+            assert_eq!(problem.hand.count_tiles(), 14);
+            assert_eq!(problem.hand.shanten(), shanten);
+            assert!(!problem.answers.is_empty());
+            assert!((0.0..=1.0).contains(&problem.difficulty));
+
+            let best = problem.best_discards();
+            assert!(problem.is_correct(&best[0]));
+        }
+
+        assert!(NanikiruProblem::generate(0, 5).is_none());
+    }
+
+    #[test]
+    fn nanikiru_same_seed_same_problem() {
+        let first = NanikiruProblem::generate(42, 1).unwrap();
+        let second = NanikiruProblem::generate(42, 1).unwrap();
+
+        assert_eq!(first.hand.to_string(), second.hand.to_string());
+        assert_eq!(first.best_discards(), second.best_discards());
+    }
+
+    #[test]
+    fn scoring_problem_answers() {
+        for seed in 0..30 {
+            let problem = ScoringProblem::generate(seed).unwrap();
+            let (_, score) = problem.table().yaku().unwrap();
+
+            assert!(!problem.yaku.is_empty());
+            assert_eq!(score.han, problem.score.han);
+            assert_eq!(problem.payments.iter().sum::<i32>(), 0);
+            assert_eq!(
+                problem.points(),
+                problem.score.total_points() + 300 * problem.honba as u32
+            );
+
+            if !problem.fu.is_empty() && problem.score.fu != 25 {
+                assert_eq!(total_fu(&problem.fu).div_ceil(10) * 10, problem.score.fu);
+            }
+        }
+    }
+
+    #[test]
+    fn scoring_problems_cover_all_hands() {
+        let problems: Vec<ScoringProblem> = (0..200)
+            .map(|seed| ScoringProblem::generate(seed).unwrap())
+            .collect();
+        let has_yaku =
+            |problem: &ScoringProblem, yaku: fn(&Yaku) -> bool| problem.yaku.iter().any(yaku);
+
+        assert!(problems.iter().any(|p| !p.hand.is_closed()));
+        assert!(problems.iter().any(|p| p.dora_indicators.len() > 1));
+        assert!(problems
+            .iter()
+            .any(|p| has_yaku(p, |y| matches!(y, Yaku::Chiitoitsu))));
+        assert!(problems
+            .iter()
+            .any(|p| has_yaku(p, |y| matches!(y, Yaku::Kokushi))));
+    }
+
+    #[test]
+    fn scoring_same_seed_same_problem() {
+        let first = ScoringProblem::generate(7).unwrap();
+        let second = ScoringProblem::generate(7).unwrap();
+
+        assert_eq!(first.hand.to_string(), second.hand.to_string());
+        assert_eq!(first.payments, second.payments);
+    }
+
+    #[test]
+    fn machi_of_chinitsu_hands() {
+        let problems = MachiProblem::all('p');
+        let nine_sided = problems
+            .iter()
+            .find(|problem| problem.hand.to_string() == "1112345678999p")
+            .unwrap();
+
+        assert_eq!(nine_sided.waits.len(), 9);
+        assert!(problems[0].difficulty() >= nine_sided.difficulty());
+        assert!(problems.iter().all(|problem| !problem.waits.is_empty()));
+
+        let tiles: Vec<Tile> = (1..=9)
+            .map(|i| Tile::from_text(&format!("{}p", i)).unwrap())
+            .collect();
+        assert!(nine_sided.is_correct(&tiles));
+        assert!(!nine_sided.is_correct(&tiles[1..]));
+    }
+
+    #[test]
+    fn machi_decompositions() {
+        let mut problem = MachiProblem::generate(7, 's', 3).unwrap();
+
+        assert_eq!(problem.hand.count_tiles(), 13);
+        assert_eq!(problem.hand.shanten(), 0);
+        assert!(problem.waits.len() >= 3);
+        assert!(problem
+            .waits
+            .iter()
+            .all(|wait| !wait.decompositions.is_empty()));
+        assert_eq!(
+            problem.hand.to_string(),
+            MachiProblem::generate(7, 's', 3).unwrap().hand.to_string()
+        );
+
+        assert!(MachiProblem::generate(7, 'z', 1).is_none());
+    }
+
+    #[test]
+    fn machi_decompositions_match_shape_finder() {
+        for text in ["11122345678999m", "22334455667788m", "2223334445556m"].iter() {
+            let mut hand = Hand::from_text(text, false).unwrap();
+            let counts = hand.get_34_array(false);
+            let mut suit = [0u8; 9];
+            suit.copy_from_slice(&counts[..9]);
+
+            let mut ours: Vec<String> = suit_decompositions(&suit, 'm')
+                .iter()
+                .map(|variant| variant_string(variant))
+                .collect();
+            let mut theirs: Vec<String> = ShapeFinder::new()
+                .find(&mut hand)
+                .iter()
+                .map(|variant| variant_string(variant))
+                .collect();
+            ours.sort();
+            theirs.sort();
+
+            assert_eq!(ours, theirs);
+        }
+    }
+
+    fn variant_string(variant: &[Shape]) -> String {
+        let mut shapes: Vec<String> = variant.iter().map(|shape| shape.to_string()).collect();
+        shapes.sort();
+
+        shapes.join(" ")
+    }
+}
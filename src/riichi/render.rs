@@ -0,0 +1,222 @@
+use crate::riichi::hand::Hand;
+use crate::riichi::shapes::{ClosedShape, CompleteShape, OpenKan, OpenShape};
+use crate::riichi::tile::Tile;
+
+/// Discards in one row of the pond
+const POND_ROW: usize = 6;
+
+/// How tiles are written in text layouts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TileStyle {
+    /// mpsz notation - 5m, 0p, 7z
+    Text,
+    /// characters from the Unicode mahjong tiles block
+    Unicode,
+}
+
+/// One tile as it lies on the table
+#[derive(Debug, Clone, Copy)]
+pub struct TileView {
+    /// None for a face down tile
+    pub tile: Option<Tile>,
+    /// called tiles and riichi discards lie sideways
+    pub sideways: bool,
+}
+
+impl TileView {
+    fn new(tile: Tile, sideways: bool) -> TileView {
+        TileView {
+            tile: Some(tile),
+            sideways,
+        }
+    }
+
+    fn face_down() -> TileView {
+        TileView {
+            tile: None,
+            sideways: false,
+        }
+    }
+
+    /// Sideways tiles are in [brackets], face down tiles are ## (or the Unicode tile back)
+    pub fn render(&self, style: TileStyle) -> String {
+        let tile = match (&self.tile, style) {
+            (None, TileStyle::Text) => "##".to_string(),
+            (None, TileStyle::Unicode) => Tile::unicode_back().to_string(),
+            (Some(tile), TileStyle::Text) => tile.to_string(),
+            (Some(tile), TileStyle::Unicode) => tile.to_unicode().to_string(),
+        };
+
+        if self.sideways {
+            return format!("[{}]", tile);
+        }
+
+        tile
+    }
+}
+
+/// Groups of tiles from left to right: the closed tiles, the drawn tile and the melds.
+/// The first meld is on the far right, like on a real table.
+pub fn hand_layout(hand: &Hand) -> Vec<Vec<TileView>> {
+    let mut groups = vec![];
+    let closed_tiles = hand.get_closed_tiles();
+
+    groups.push(
+        closed_tiles
+            .iter()
+            .filter(|t| !t.is_draw)
+            .map(|t| TileView::new(*t, false))
+            .collect(),
+    );
+
+    if let Some(drawn) = closed_tiles.iter().find(|t| t.is_draw) {
+        groups.push(vec![TileView::new(*drawn, false)]);
+    }
+
+    for shape in hand.get_shapes().iter().rev() {
+        groups.push(meld_layout(shape));
+    }
+
+    groups
+}
+
+/// A meld with the called tile turned sideways on the side of the player it came from:
+/// left for kamicha, middle for toimen and right for shimocha.
+/// An added kan tile lies sideways next to the called one, closed kans have both ends face down.
+pub fn meld_layout(shape: &CompleteShape) -> Vec<TileView> {
+    let (tiles, added_kan) = match shape {
+        CompleteShape::Closed(ClosedShape::Kantsu(tiles)) => {
+            return vec![
+                TileView::face_down(),
+                TileView::new(tiles[1], false),
+                TileView::new(tiles[2], false),
+                TileView::face_down(),
+            ];
+        }
+        CompleteShape::Closed(closed) => {
+            return closed
+                .get_tiles()
+                .iter()
+                .map(|t| TileView::new(*t, false))
+                .collect()
+        }
+        CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(_))) => (shape_tiles(shape), true),
+        CompleteShape::Open(_) => (shape_tiles(shape), false),
+    };
+
+    let called = match tiles.iter().position(|t| t.called_from > 0) {
+        None => return tiles.iter().map(|t| TileView::new(*t, false)).collect(),
+        Some(index) => index,
+    };
+
+    let mut others: Vec<TileView> = tiles
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != called)
+        .map(|(_, t)| TileView::new(*t, false))
+        .collect();
+
+    let mut sideways = vec![TileView::new(tiles[called], true)];
+    if added_kan {
+        let mut added = others.pop().unwrap();
+        added.sideways = true;
+        sideways.push(added);
+    }
+
+    let position = match tiles[called].called_from {
+        3 => 0,
+        2 => 1,
+        _ => others.len(),
+    };
+
+    others.splice(position..position, sideways);
+
+    others
+}
+
+fn shape_tiles(shape: &CompleteShape) -> Vec<Tile> {
+    match shape {
+        CompleteShape::Closed(closed) => closed.get_tiles(),
+        CompleteShape::Open(open) => open.get_tiles(),
+    }
+}
+
+/// Discards in rows of 6, the riichi declaration tile lies sideways
+pub fn discard_pond(discards: &[Tile]) -> Vec<Vec<TileView>> {
+    discards
+        .chunks(POND_ROW)
+        .map(|row| row.iter().map(|t| TileView::new(*t, t.is_riichi)).collect())
+        .collect()
+}
+
+/// Hand layout as one line of text, groups separated by spaces
+pub fn render_hand(hand: &Hand, style: TileStyle) -> String {
+    hand_layout(hand)
+        .iter()
+        .filter(|group| !group.is_empty())
+        .map(|group| render_group(group, style))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Discard pond as text, one line per row
+pub fn render_pond(discards: &[Tile], style: TileStyle) -> String {
+    discard_pond(discards)
+        .iter()
+        .map(|row| render_group(row, style))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn render_group(group: &[TileView], style: TileStyle) -> String {
+    group.iter().map(|view| view.render(style)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_melds_sideways() {
+        let hand = Hand::from_text("123m55z(p1z2)(345p0)(k7z)", false).unwrap();
+
+        assert_eq!(
+            render_hand(&hand, TileStyle::Text),
+            "1m2m3m5z 5z ##7z7z## [3p]4p5p 1z[1z]1z"
+        );
+    }
+
+    #[test]
+    fn render_kans() {
+        let hand = Hand::from_text("123m456p55z(k2s1)(s9p3)", false).unwrap();
+
+        assert_eq!(
+            render_hand(&hand, TileStyle::Text),
+            "1m2m3m4p5p6p5z 5z [9p][9p]9p9p 2s2s2s[2s]"
+        );
+    }
+
+    #[test]
+    fn render_unicode_hand() {
+        let hand = Hand::from_text("123m456p789s1p(p7z3)", false).unwrap();
+
+        assert_eq!(
+            render_hand(&hand, TileStyle::Unicode),
+            "\u{1F007}\u{1F008}\u{1F009}\u{1F01C}\u{1F01D}\u{1F01E}\u{1F016}\u{1F017}\u{1F018} \u{1F019} [\u{1F004}]\u{1F004}\u{1F004}"
+        );
+    }
+
+    #[test]
+    fn render_pond_with_riichi() {
+        let mut discards: Vec<Tile> = ["1z", "9m", "2p", "5s", "7z", "1m", "3s"]
+            .iter()
+            .map(|t| Tile::from_text(t).unwrap())
+            .collect();
+        discards[4].is_riichi = true;
+
+        assert_eq!(
+            render_pond(&discards, TileStyle::Text),
+            "1z9m2p5s[7z]1m\n3s"
+        );
+    }
+}
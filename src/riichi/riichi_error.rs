@@ -1,31 +1,569 @@
+use crate::riichi::round::AbortiveDraw;
+use crate::riichi::tile::Tile;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
 use std::error::Error;
-use std::fmt::Formatter;
-use std::fmt::Result;
-use wasm_bindgen::__rt::core::fmt::Display;
+use std::fmt;
 
-#[derive(Debug)]
-pub struct RiichiError {
-    pub code: u16,
-    pub message: String,
+/// Errors of the library. Every error still has a numeric code and a message
+/// for places that can't use the enum (like the WASM boundary).
+#[derive(Debug, Clone, PartialEq)]
+pub enum RiichiError {
+    /// Text that could not be parsed, with the byte offset and the token when we know them
+    Parse {
+        error: ParseError,
+        offset: Option<usize>,
+        token: Option<String>,
+    },
+    /// Input that was parsed, but is not a valid tile, shape or hand
+    Validation(ValidationError),
+    /// Actions that are not possible in the current state of a round or game
+    IllegalAction(IllegalAction),
+    /// Requests that are against the rules in use, with the offset and the token in parsed text
+    Rules {
+        error: RulesError,
+        offset: Option<usize>,
+        token: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    TileLength,
+    TileColor(char),
+    ColorChar(char),
+    HonorNumber(u8),
+    TileDefinition,
+    ClosedHand,
+    UnclosedMeld,
+    MeldSyntax,
+    ChiNotConsecutive,
+    MeldIndex { index: u32, min: u32, max: u32 },
+    TooManyCopies(Tile),
+    TooManyRedFives { tile: Tile, limit: u8 },
+    HonorRedFive,
+    UnexpectedCharacter(char),
+    MissingColor,
+    RedMarker,
+    CalledTileMissing,
+    Json(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    InvalidHand,
+    ShantenHand,
+    TileId(u8),
+    TileId136(u8),
+    ShapeTileCount,
+    ShapeUnrelatedTiles,
+    BadShape,
+    NoShape,
+    UnknownHand(u8),
+    PlayerId(u8),
+    CallOrder,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IllegalAction {
+    NoPonToAddTo,
+    AddedTileNotInHand,
+    NoKita,
+    RoundEnded,
+    AlreadyDealt,
+    DealCount,
+    DrawNotPossible,
+    NotPlayersTurn,
+    WallEmpty,
+    DiscardNotPossible,
+    DiscardNotInHand,
+    RiichiDiscard,
+    RiichiDiscardNotTenpai,
+    RiichiNotPossible,
+    RiichiAlreadyDeclared,
+    RiichiOpenHand,
+    RiichiPoints,
+    RiichiTilesLeft,
+    RiichiNotTenpai,
+    NoDiscardToCall,
+    OwnDiscard,
+    ChiNotFromKamicha,
+    CallInRiichi,
+    NoMoreKans,
+    CallTileCount,
+    NotAChi,
+    NotAPon,
+    NotAKan,
+    CalledTilesNotInHand,
+    KanNotPossible,
+    AnkanTileCount,
+    KanChangesWait,
+    RinshanNotPossible,
+    NotDealt,
+    NoKanForDora,
+    TsumoNotPossible,
+    AlreadyWon,
+    NoTileToRon,
+    Furiten,
+    HandNotComplete,
+    NoYaku,
+    ExhaustiveDrawNotPossible,
+    TilesLeftInWall,
+    FifthCopy(Tile),
+    AbortiveDrawNotPossible(AbortiveDraw),
+    GameEnded,
+    KyuushuKyuuhaiNotPossible,
+    NoRinshanTiles,
+    NoSuchPlayer(u8),
+    CallOnLastDiscard,
+    Kuikae,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RulesError {
+    SanmaTile(Tile),
+    KitaNotSanma,
+    RedFiveCount(u8),
+    SanmaGame,
 }
 
 impl RiichiError {
-    pub fn new(code: u16, message: &str) -> RiichiError {
-        RiichiError {
-            code,
-            message: message.to_string(),
+    pub fn code(&self) -> u16 {
+        match self {
+            RiichiError::Parse { error, .. } => error.code(),
+            RiichiError::Validation(error) => error.code(),
+            RiichiError::IllegalAction(error) => error.code(),
+            RiichiError::Rules { error, .. } => error.code(),
+        }
+    }
+
+    /// Message without the code and position
+    pub fn message(&self) -> String {
+        match self {
+            RiichiError::Parse { error, .. } => error.to_string(),
+            RiichiError::Validation(error) => error.to_string(),
+            RiichiError::IllegalAction(error) => error.to_string(),
+            RiichiError::Rules { error, .. } => error.to_string(),
+        }
+    }
+
+    /// Byte offset of the problematic token in the parsed text
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            RiichiError::Parse { offset, .. } | RiichiError::Rules { offset, .. } => *offset,
+            _ => None,
+        }
+    }
+
+    /// The part of the parsed text that caused the error
+    pub fn token(&self) -> Option<&str> {
+        match self {
+            RiichiError::Parse { token, .. } | RiichiError::Rules { token, .. } => token.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Marks where in the parsed text a parse (or rules) error happened. Other errors are returned as they are.
+    pub fn at(self, offset: usize, token: &str) -> RiichiError {
+        match self {
+            RiichiError::Parse { error, .. } => RiichiError::Parse {
+                error,
+                offset: Some(offset),
+                token: Some(token.to_string()),
+            },
+            RiichiError::Rules { error, .. } => RiichiError::Rules {
+                error,
+                offset: Some(offset),
+                token: Some(token.to_string()),
+            },
+            error => error,
+        }
+    }
+}
+
+impl ParseError {
+    pub fn code(&self) -> u16 {
+        match self {
+            ParseError::TileLength => 105,
+            ParseError::TileColor(_) => 102,
+            ParseError::ColorChar(_) => 106,
+            ParseError::HonorNumber(_) => 103,
+            ParseError::TileDefinition => 104,
+            ParseError::ClosedHand => 333,
+            ParseError::UnclosedMeld | ParseError::MeldSyntax | ParseError::ChiNotConsecutive => {
+                130
+            }
+            ParseError::MeldIndex { .. } => 131,
+            ParseError::TooManyCopies(_) => 132,
+            ParseError::TooManyRedFives { .. } | ParseError::HonorRedFive => 133,
+            ParseError::UnexpectedCharacter(_) | ParseError::MissingColor => 134,
+            ParseError::RedMarker => 589,
+            ParseError::CalledTileMissing => 780,
+            ParseError::Json(_) => 135,
+        }
+    }
+}
+
+impl ValidationError {
+    pub fn code(&self) -> u16 {
+        match self {
+            ValidationError::InvalidHand => 100,
+            ValidationError::ShantenHand => 101,
+            ValidationError::TileId(_) => 107,
+            ValidationError::TileId136(_) => 108,
+            ValidationError::ShapeTileCount => 120,
+            ValidationError::ShapeUnrelatedTiles => 121,
+            ValidationError::BadShape => 122,
+            ValidationError::NoShape => 124,
+            ValidationError::UnknownHand(_) => 300,
+            ValidationError::PlayerId(_) => 301,
+            ValidationError::CallOrder => 302,
+        }
+    }
+}
+
+impl IllegalAction {
+    pub fn code(&self) -> u16 {
+        match self {
+            IllegalAction::NoPonToAddTo => 125,
+            IllegalAction::AddedTileNotInHand => 126,
+            IllegalAction::NoKita => 129,
+            IllegalAction::RoundEnded => 200,
+            IllegalAction::AlreadyDealt => 201,
+            IllegalAction::DealCount => 202,
+            IllegalAction::DrawNotPossible => 203,
+            IllegalAction::NotPlayersTurn => 204,
+            IllegalAction::WallEmpty => 205,
+            IllegalAction::DiscardNotPossible => 206,
+            IllegalAction::DiscardNotInHand => 207,
+            IllegalAction::RiichiDiscard => 208,
+            IllegalAction::RiichiDiscardNotTenpai => 209,
+            IllegalAction::RiichiNotPossible => 210,
+            IllegalAction::RiichiAlreadyDeclared => 211,
+            IllegalAction::RiichiOpenHand => 212,
+            IllegalAction::RiichiPoints => 213,
+            IllegalAction::RiichiTilesLeft => 214,
+            IllegalAction::RiichiNotTenpai => 215,
+            IllegalAction::NoDiscardToCall => 216,
+            IllegalAction::OwnDiscard => 217,
+            IllegalAction::ChiNotFromKamicha => 218,
+            IllegalAction::CallInRiichi => 219,
+            IllegalAction::NoMoreKans => 220,
+            IllegalAction::CallTileCount => 221,
+            IllegalAction::NotAChi => 222,
+            IllegalAction::NotAPon => 223,
+            IllegalAction::NotAKan => 224,
+            IllegalAction::CalledTilesNotInHand => 225,
+            IllegalAction::KanNotPossible => 226,
+            IllegalAction::AnkanTileCount => 227,
+            IllegalAction::KanChangesWait => 228,
+            IllegalAction::RinshanNotPossible => 229,
+            IllegalAction::NotDealt => 230,
+            IllegalAction::NoKanForDora => 231,
+            IllegalAction::TsumoNotPossible => 232,
+            IllegalAction::AlreadyWon => 233,
+            IllegalAction::NoTileToRon => 234,
+            IllegalAction::Furiten => 235,
+            IllegalAction::HandNotComplete => 236,
+            IllegalAction::NoYaku => 237,
+            IllegalAction::ExhaustiveDrawNotPossible => 238,
+            IllegalAction::TilesLeftInWall => 239,
+            IllegalAction::FifthCopy(_) => 240,
+            IllegalAction::AbortiveDrawNotPossible(_) => 241,
+            IllegalAction::GameEnded => 260,
+            IllegalAction::KyuushuKyuuhaiNotPossible => 261,
+            IllegalAction::NoRinshanTiles => 262,
+            IllegalAction::NoSuchPlayer(_) => 263,
+            IllegalAction::CallOnLastDiscard => 264,
+            IllegalAction::Kuikae => 265,
         }
     }
 }
 
-impl Display for RiichiError {
-    fn fmt(&self, f: &mut Formatter) -> Result {
-        write!(f, "{} : {}", self.code, self.message)
+impl RulesError {
+    pub fn code(&self) -> u16 {
+        match self {
+            RulesError::SanmaTile(_) => 127,
+            RulesError::KitaNotSanma => 128,
+            RulesError::RedFiveCount(_) => 250,
+            RulesError::SanmaGame => 251,
+        }
     }
 }
 
-impl Error for RiichiError {
-    fn description(&self) -> &str {
-        &self.message
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::TileLength => write!(f, "Tile length must be 2"),
+            ParseError::TileColor(_) => write!(f, "Wrong color, only m, p an s allowed"),
+            ParseError::ColorChar(_) => write!(f, "Wrong representation of tile color!"),
+            ParseError::HonorNumber(_) => write!(f, "Wrong number for honors!"),
+            ParseError::TileDefinition => write!(f, "Invalid tile definition"),
+            ParseError::ClosedHand => write!(f, "Closed hand not defined correctly"),
+            ParseError::UnclosedMeld => write!(f, "Meld is not closed"),
+            ParseError::MeldSyntax => write!(f, "Invalid meld syntax"),
+            ParseError::ChiNotConsecutive => write!(f, "Chi tiles must be consecutive"),
+            ParseError::MeldIndex { index, min, max } => {
+                write!(f, "Wrong meld index {}, must be {} - {}", index, min, max)
+            }
+            ParseError::TooManyCopies(tile) => {
+                write!(f, "Tile {} is in the hand more than 4 times", tile)
+            }
+            ParseError::TooManyRedFives { tile, limit } => {
+                write!(f, "There can't be more than {} red {}", limit, tile)
+            }
+            ParseError::HonorRedFive => write!(f, "Honors don't have a red 5"),
+            ParseError::UnexpectedCharacter(_) => write!(f, "Unexpected character"),
+            ParseError::MissingColor => write!(f, "Tile number without a color"),
+            ParseError::RedMarker => write!(f, "Only 0 can have r"),
+            ParseError::CalledTileMissing => write!(f, "Called tile was not specified"),
+            ParseError::Json(error) => write!(f, "Invalid JSON: {}", error),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::InvalidHand => write!(f, "Couldn't parse hand representation."),
+            ValidationError::ShantenHand => write!(f, "Invalid hand"),
+            ValidationError::TileId(id) | ValidationError::TileId136(id) => {
+                write!(f, "Wrong tile ID {}", id)
+            }
+            ValidationError::ShapeTileCount => write!(f, "Not a valid shape - wrong tile count"),
+            ValidationError::ShapeUnrelatedTiles => {
+                write!(
+                    f,
+                    "Not a valid shape - tiles are not relevant to each other"
+                )
+            }
+            ValidationError::BadShape => write!(f, "Bad shape"),
+            ValidationError::NoShape => write!(f, "No suitable shape found"),
+            ValidationError::UnknownHand(player) => {
+                write!(f, "Hand of player {} is not known", player)
+            }
+            ValidationError::PlayerId(player) => write!(f, "Wrong player ID {}", player),
+            ValidationError::CallOrder => write!(f, "The call order doesn't match the melds"),
+        }
+    }
+}
+
+impl fmt::Display for IllegalAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            IllegalAction::NoPonToAddTo => "No pon to add the tile to",
+            IllegalAction::AddedTileNotInHand => "The added tile is not in hand",
+            IllegalAction::NoKita => "There is no kita in hand",
+            IllegalAction::RoundEnded => "The round has already ended",
+            IllegalAction::AlreadyDealt => "Tiles have already been dealt",
+            IllegalAction::DealCount => "Every player has to be dealt 13 tiles",
+            IllegalAction::DrawNotPossible => "Drawing is not possible now",
+            IllegalAction::NotPlayersTurn => "It is not this player's turn",
+            IllegalAction::WallEmpty => "There are no tiles left in the wall",
+            IllegalAction::DiscardNotPossible => "Discarding is not possible now",
+            IllegalAction::DiscardNotInHand => "Discarded tile is not in hand",
+            IllegalAction::RiichiDiscard => "Only the drawn tile can be discarded in riichi",
+            IllegalAction::RiichiDiscardNotTenpai => {
+                "The hand has to be tenpai after the riichi discard"
+            }
+            IllegalAction::RiichiNotPossible => "Riichi is not possible now",
+            IllegalAction::RiichiAlreadyDeclared => "Riichi has already been declared",
+            IllegalAction::RiichiOpenHand => "Riichi is only possible with a closed hand",
+            IllegalAction::RiichiPoints => "Not enough points for riichi",
+            IllegalAction::RiichiTilesLeft => "Not enough tiles left for riichi",
+            IllegalAction::RiichiNotTenpai => "Riichi is only possible when tenpai",
+            IllegalAction::NoDiscardToCall => "There is no discard to call",
+            IllegalAction::OwnDiscard => "Can't call your own discard",
+            IllegalAction::ChiNotFromKamicha => "Chi is only possible from kamicha",
+            IllegalAction::CallInRiichi => "Can't call in riichi",
+            IllegalAction::NoMoreKans => "No more kans are possible",
+            IllegalAction::CallTileCount => "Wrong number of tiles for this call",
+            IllegalAction::NotAChi => "These tiles don't make a chi",
+            IllegalAction::NotAPon => "These tiles don't make a pon",
+            IllegalAction::NotAKan => "These tiles don't make a kan",
+            IllegalAction::CalledTilesNotInHand => "Called tiles are not in hand",
+            IllegalAction::KanNotPossible => "Kan is not possible now",
+            IllegalAction::AnkanTileCount => "Ankan needs 4 tiles in hand",
+            IllegalAction::KanChangesWait => "This kan would change the riichi wait",
+            IllegalAction::RinshanNotPossible => "Rinshan draw is not possible now",
+            IllegalAction::NotDealt => "Tiles have not been dealt yet",
+            IllegalAction::NoKanForDora => "No kan to reveal a new dora indicator for",
+            IllegalAction::TsumoNotPossible => "Tsumo is not possible now",
+            IllegalAction::AlreadyWon => "This player has already won",
+            IllegalAction::NoTileToRon => "There is no tile to ron",
+            IllegalAction::Furiten => "Can't ron in furiten",
+            IllegalAction::HandNotComplete => "The hand is not complete",
+            IllegalAction::NoYaku => "The hand has no yaku",
+            IllegalAction::ExhaustiveDrawNotPossible => "The round can't end in a draw now",
+            IllegalAction::TilesLeftInWall => "There are still tiles in the wall",
+            IllegalAction::FifthCopy(tile) => {
+                return write!(f, "There are only 4 copies of {}", tile);
+            }
+            IllegalAction::AbortiveDrawNotPossible(reason) => {
+                return write!(f, "{:?} is not possible now", reason);
+            }
+            IllegalAction::NoSuchPlayer(player) => {
+                return write!(f, "There is no player {}", player);
+            }
+            IllegalAction::GameEnded => "The game has already ended",
+            IllegalAction::KyuushuKyuuhaiNotPossible => "Kyuushu kyuuhai is not possible now",
+            IllegalAction::NoRinshanTiles => "No rinshan tiles left",
+            IllegalAction::CallOnLastDiscard => "The last discard can't be called",
+            IllegalAction::Kuikae => {
+                "The called tile or the other end of the chi can't be discarded right after the call"
+            }
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RulesError::SanmaTile(tile) => write!(f, "{} is not used in sanma", tile),
+            RulesError::KitaNotSanma => write!(f, "Kita can only be set aside in sanma"),
+            RulesError::RedFiveCount(count) => {
+                write!(f, "There can be only up to 4 red fives, not {}", count)
+            }
+            RulesError::SanmaGame => write!(f, "Games can only be played with 4 players"),
+        }
+    }
+}
+
+impl From<ParseError> for RiichiError {
+    fn from(error: ParseError) -> Self {
+        RiichiError::Parse {
+            error,
+            offset: None,
+            token: None,
+        }
+    }
+}
+
+impl From<ValidationError> for RiichiError {
+    fn from(error: ValidationError) -> Self {
+        RiichiError::Validation(error)
+    }
+}
+
+impl From<IllegalAction> for RiichiError {
+    fn from(error: IllegalAction) -> Self {
+        RiichiError::IllegalAction(error)
+    }
+}
+
+impl From<RulesError> for RiichiError {
+    fn from(error: RulesError) -> Self {
+        RiichiError::Rules {
+            error,
+            offset: None,
+            token: None,
+        }
+    }
+}
+
+/// The code/message pair for places that can't use the enum
+impl From<&RiichiError> for (u16, String) {
+    fn from(error: &RiichiError) -> Self {
+        (error.code(), error.message())
+    }
+}
+
+/// Errors are serialized as {code, message, offset, token}, offset and token can be null
+impl Serialize for RiichiError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("RiichiError", 4)?;
+        state.serialize_field("code", &self.code())?;
+        state.serialize_field("message", &self.message())?;
+        state.serialize_field("offset", &self.offset())?;
+        state.serialize_field("token", &self.token())?;
+        state.end()
+    }
+}
+
+impl fmt::Display for RiichiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} : {}", self.code(), self.message())?;
+
+        if let (Some(offset), Some(token)) = (self.offset(), self.token()) {
+            write!(f, " (at {}: \"{}\")", offset, token)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for RiichiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_and_message() {
+        let error: RiichiError = IllegalAction::Furiten.into();
+
+        assert_eq!(error.code(), 235);
+        assert_eq!(error.to_string(), "235 : Can't ron in furiten");
+
+        let (code, message) = (&error).into();
+        assert_eq!(code, 235);
+        assert_eq!(message, "Can't ron in furiten");
+    }
+
+    #[test]
+    fn parse_codes_stay_the_same() {
+        let error: RiichiError = ParseError::TileColor('x').into();
+        assert_eq!(
+            error.to_string(),
+            "102 : Wrong color, only m, p an s allowed"
+        );
+
+        let error: RiichiError = ParseError::ColorChar('x').into();
+        assert_eq!(
+            error.to_string(),
+            "106 : Wrong representation of tile color!"
+        );
+
+        let error: RiichiError = ParseError::HonorNumber(8).into();
+        assert_eq!(error.to_string(), "103 : Wrong number for honors!");
+    }
+
+    #[test]
+    fn parse_error_position() {
+        let error = RiichiError::from(ParseError::MeldSyntax).at(4, "(p1z)");
+
+        assert_eq!(error.offset(), Some(4));
+        assert_eq!(error.token(), Some("(p1z)"));
+
+        let error =
+            RiichiError::from(RulesError::SanmaTile(Tile::from_text("5m").unwrap())).at(4, "5m");
+        assert_eq!(error.offset(), Some(4));
+        assert_eq!(
+            error.to_string(),
+            "127 : 5m is not used in sanma (at 4: \"5m\")"
+        );
+
+        let error = RiichiError::from(IllegalAction::Furiten).at(4, "4z");
+        assert_eq!(error.offset(), None);
+    }
+
+    #[test]
+    fn error_as_json() {
+        let error = RiichiError::from(ParseError::MeldSyntax).at(4, "(p1z)");
+        assert_eq!(
+            serde_json::to_string(&error).unwrap(),
+            r#"{"code":130,"message":"Invalid meld syntax","offset":4,"token":"(p1z)"}"#
+        );
+
+        let error: RiichiError = IllegalAction::Furiten.into();
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({"code": 235, "message": "Can't ron in furiten", "offset": null, "token": null})
+        );
     }
 }
@@ -0,0 +1,2251 @@
+use crate::riichi::hand::Hand;
+use crate::riichi::riichi_error::{IllegalAction, RiichiError};
+use crate::riichi::rules::Rules;
+use crate::riichi::scores::Score;
+use crate::riichi::shapes::{ClosedShape, CompleteShape, OpenKan, OpenShape, Shape, ShapeType};
+use crate::riichi::table::Table;
+use crate::riichi::tile::Tile;
+use crate::riichi::yaku::Yaku;
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+
+/// Type of a call on another player's discard
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CallType {
+    Chi,
+    Pon,
+    Daiminkan,
+}
+
+/// Type of a kan made on the player's own turn
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum KanType {
+    Ankan,
+    Shouminkan,
+}
+
+/// Reason of an abortive draw (tochuu ryuukyoku)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AbortiveDraw {
+    /// 9 different terminals and honors on the first draw
+    KyuushuKyuuhai,
+    /// the same wind discarded by all 4 players on the first go-around
+    SuufonRenda,
+    /// all 4 players in riichi
+    SuuchaRiichi,
+    /// 4 kans by more than one player
+    Suukaikan,
+    /// triple ron
+    Sanchahou,
+}
+
+/// Everything that can happen during one round (kyoku).
+/// Players are absolute seats 0-3, their seat wind depends on who is the dealer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RoundEvent {
+    /// 13 tiles for every player and the first dora indicator
+    Deal {
+        hands: [Vec<Tile>; 4],
+        dora_indicator: Tile,
+    },
+    Draw {
+        player: u8,
+        tile: Tile,
+    },
+    Discard {
+        player: u8,
+        tile: Tile,
+    },
+    /// Riichi declaration - the next discard of this player is the riichi tile
+    Riichi {
+        player: u8,
+    },
+    /// Chi, pon or daiminkan of the last discard. `tiles` are the tiles used from the player's hand.
+    Call {
+        player: u8,
+        call_type: CallType,
+        tiles: Vec<Tile>,
+    },
+    Kan {
+        player: u8,
+        kan_type: KanType,
+        tile: Tile,
+    },
+    /// Replacement draw from the dead wall after a kan
+    Rinshan {
+        player: u8,
+        tile: Tile,
+    },
+    NewDora {
+        indicator: Tile,
+    },
+    /// `from` is the same as `player` for tsumo
+    Win {
+        player: u8,
+        from: u8,
+    },
+    ExhaustiveDraw,
+    /// `rons` are the players who called ron on the last discard, only for sanchahou
+    AbortiveDraw {
+        reason: AbortiveDraw,
+        #[serde(default)]
+        rons: Vec<u8>,
+    },
+}
+
+/// What the round is waiting for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Dealing,
+    /// this player has to draw
+    Draw(u8),
+    /// this player has 14 tiles and has to discard, kan or tsumo
+    Discard(u8),
+    /// the last discard of this player can be called or ronned
+    Calls(u8),
+    /// this player made a kan and has to draw from the dead wall
+    Rinshan(u8),
+    Ended,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WinResult {
+    pub player: u8,
+    pub from: u8,
+    pub yaku: Vec<Yaku>,
+    pub score: Score,
+    /// point changes of all 4 players, including honba and riichi sticks
+    pub payments: [i32; 4],
+}
+
+/// How a round that nobody won ended
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrawResult {
+    /// None for an exhaustive draw
+    pub abortive: Option<AbortiveDraw>,
+    pub tenpai: [bool; 4],
+    pub nagashi: [bool; 4],
+    /// noten (or nagashi mangan) payments of all 4 players
+    pub payments: [i32; 4],
+}
+
+#[derive(Clone)]
+pub struct PlayerState {
+    hand: Hand,
+    discards: Vec<Tile>,
+    points: i32,
+    riichi: bool,
+    riichi_declared: bool,
+    /// temporary furiten (or permanent one in riichi) from passing a winning tile
+    furiten: bool,
+    /// tiles that can't be discarded right after a chi or pon (kuikae)
+    kuikae: Vec<Tile>,
+    /// riichi declared on the first discard, before any call
+    double_riichi: bool,
+}
+
+impl PlayerState {
+    fn new(points: i32) -> PlayerState {
+        PlayerState {
+            hand: Hand::new(vec![]),
+            discards: vec![],
+            points,
+            riichi: false,
+            riichi_declared: false,
+            furiten: false,
+            kuikae: vec![],
+            double_riichi: false,
+        }
+    }
+
+    pub fn get_hand(&self) -> &Hand {
+        &self.hand
+    }
+
+    pub fn get_discards(&self) -> &Vec<Tile> {
+        &self.discards
+    }
+
+    pub fn get_points(&self) -> i32 {
+        self.points
+    }
+
+    pub fn is_riichi(&self) -> bool {
+        self.riichi
+    }
+
+    pub fn is_double_riichi(&self) -> bool {
+        self.double_riichi
+    }
+
+    pub fn is_furiten(&self) -> bool {
+        self.furiten
+    }
+}
+
+/// State of all 4 players and the table at some point of the round
+#[derive(Clone)]
+pub struct RoundState {
+    players: [PlayerState; 4],
+    // 1 = east, 2 = south, 3 = west, 4 = north
+    prevalent_wind: u8,
+    dealer: u8,
+    honba: u8,
+    riichi_sticks: u8,
+    phase: Phase,
+    tiles_remaining: u8,
+    dora_indicators: Vec<Tile>,
+    kans: u8,
+    last_discard: Option<Tile>,
+    /// shouminkan tile that can still be robbed (chankan)
+    chankan_tile: Option<Tile>,
+    /// player whose riichi stick gets paid when the riichi tile passes
+    pending_riichi: Option<u8>,
+    /// the player to discard drew from the dead wall
+    after_kan: bool,
+    /// the last discard came right after a kan (kanburi)
+    kan_discard: bool,
+    /// consecutive wins of the dealer before this round
+    renchan: u8,
+    wins: Vec<WinResult>,
+    draw: Option<DrawResult>,
+    /// how many copies of each tile have been dealt, drawn or revealed
+    tile_counts: [u8; 34],
+    rules: Rules,
+}
+
+impl RoundState {
+    fn new(
+        prevalent_wind: u8,
+        dealer: u8,
+        honba: u8,
+        riichi_sticks: u8,
+        points: [i32; 4],
+    ) -> RoundState {
+        RoundState {
+            players: [
+                PlayerState::new(points[0]),
+                PlayerState::new(points[1]),
+                PlayerState::new(points[2]),
+                PlayerState::new(points[3]),
+            ],
+            prevalent_wind,
+            dealer,
+            honba,
+            riichi_sticks,
+            phase: Phase::Dealing,
+            // 136 - 14 dead wall - 4 * 13 dealt
+            tiles_remaining: 70,
+            dora_indicators: vec![],
+            kans: 0,
+            last_discard: None,
+            chankan_tile: None,
+            pending_riichi: None,
+            after_kan: false,
+            kan_discard: false,
+            renchan: 0,
+            wins: vec![],
+            draw: None,
+            tile_counts: [0; 34],
+            rules: Rules::default(),
+        }
+    }
+
+    pub fn get_player(&self, player: u8) -> &PlayerState {
+        &self.players[player as usize]
+    }
+
+    pub fn get_phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn get_prevalent_wind(&self) -> u8 {
+        self.prevalent_wind
+    }
+
+    pub fn get_dealer(&self) -> u8 {
+        self.dealer
+    }
+
+    pub fn get_honba(&self) -> u8 {
+        self.honba
+    }
+
+    pub fn get_riichi_sticks(&self) -> u8 {
+        self.riichi_sticks
+    }
+
+    pub fn get_renchan(&self) -> u8 {
+        self.renchan
+    }
+
+    pub fn get_tiles_remaining(&self) -> u8 {
+        self.tiles_remaining
+    }
+
+    pub fn get_dora_indicators(&self) -> &Vec<Tile> {
+        &self.dora_indicators
+    }
+
+    pub fn get_kans(&self) -> u8 {
+        self.kans
+    }
+
+    pub fn get_last_discard(&self) -> Option<Tile> {
+        self.last_discard
+    }
+
+    pub fn get_wins(&self) -> &Vec<WinResult> {
+        &self.wins
+    }
+
+    pub fn get_draw(&self) -> Option<&DrawResult> {
+        self.draw.as_ref()
+    }
+
+    /// Points of all players, indexed by seat
+    pub fn get_points(&self) -> [i32; 4] {
+        [
+            self.players[0].points,
+            self.players[1].points,
+            self.players[2].points,
+            self.players[3].points,
+        ]
+    }
+
+    /// 1 = east ... 4 = north
+    pub fn seat_wind(&self, player: u8) -> u8 {
+        (player + 4 - self.dealer) % 4 + 1
+    }
+
+    /// Builds a Table from the point of view of this player.
+    /// Other players are relative to them, 1 = shimocha, 2 = toimen, 3 = kamicha.
+    pub fn table_for(&self, player: u8) -> Table {
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        let me = &self.players[player as usize];
+
+        table.set_my_hand(me.hand.clone());
+        table.set_my_riichi(me.riichi);
+        table.set_my_double_riichi(me.double_riichi);
+        table.set_my_seat_wind(self.seat_wind(player));
+        table.set_prevalent_wind(self.prevalent_wind);
+        table.set_tiles_remaining(self.tiles_remaining);
+        table.set_tsumibo(self.honba);
+        table.set_riichi_sticks(self.riichi_sticks);
+        table.set_renchan(self.renchan);
+        table.set_rules(self.rules.clone());
+
+        for indicator in self.dora_indicators.iter() {
+            table.add_dora_indicator(*indicator);
+        }
+
+        for relative in 0..4 {
+            let p = &self.players[((player + relative) % 4) as usize];
+
+            table.set_points(relative, p.points);
+
+            for tile in p.discards.iter() {
+                table.add_tile_to_discards(relative, *tile).unwrap();
+                table.add_tile_to_visible_tiles(*tile);
+            }
+
+            if relative == 0 {
+                continue;
+            }
+
+            match relative {
+                1 => table.set_p1_riichi(p.riichi),
+                2 => table.set_p2_riichi(p.riichi),
+                _ => table.set_p3_riichi(p.riichi),
+            }
+
+            for complete_shape in p.hand.get_shapes().iter() {
+                let (tile_count, is_open) = match complete_shape {
+                    CompleteShape::Closed(_) => (4, false),
+                    CompleteShape::Open(OpenShape::Kan(_)) => (4, true),
+                    CompleteShape::Open(_) => (3, true),
+                };
+
+                table
+                    .add_open_shape(
+                        relative,
+                        Shape::new(ShapeType::Complete(*complete_shape), tile_count, is_open),
+                    )
+                    .unwrap();
+            }
+        }
+
+        table
+    }
+
+    fn apply(&mut self, event: &RoundEvent) -> Result<(), RiichiError> {
+        if self.phase == Phase::Ended {
+            if let RoundEvent::Win { .. } = event {
+                // double ron is handled below
+            } else {
+                return Err(IllegalAction::RoundEnded.into());
+            }
+        }
+
+        let players = match event {
+            RoundEvent::Draw { player, .. }
+            | RoundEvent::Discard { player, .. }
+            | RoundEvent::Riichi { player }
+            | RoundEvent::Call { player, .. }
+            | RoundEvent::Kan { player, .. }
+            | RoundEvent::Rinshan { player, .. } => vec![*player],
+            RoundEvent::Win { player, from } => vec![*player, *from],
+            _ => vec![],
+        };
+
+        if let Some(player) = players.iter().find(|p| **p > 3) {
+            return Err(IllegalAction::NoSuchPlayer(*player).into());
+        }
+
+        match event {
+            RoundEvent::Deal {
+                hands,
+                dora_indicator,
+            } => self.deal(hands, dora_indicator),
+            RoundEvent::Draw { player, tile } => self.draw(*player, tile),
+            RoundEvent::Discard { player, tile } => self.discard(*player, tile),
+            RoundEvent::Riichi { player } => self.riichi(*player),
+            RoundEvent::Call {
+                player,
+                call_type,
+                tiles,
+            } => self.call(*player, *call_type, tiles),
+            RoundEvent::Kan {
+                player,
+                kan_type,
+                tile,
+            } => self.kan(*player, *kan_type, tile),
+            RoundEvent::Rinshan { player, tile } => self.rinshan(*player, tile),
+            RoundEvent::NewDora { indicator } => self.new_dora(indicator),
+            RoundEvent::Win { player, from } => self.win(*player, *from),
+            RoundEvent::ExhaustiveDraw => self.exhaustive_draw(),
+            RoundEvent::AbortiveDraw { reason, rons } => self.abortive_draw(*reason, rons),
+        }
+    }
+
+    fn deal(&mut self, hands: &[Vec<Tile>; 4], dora_indicator: &Tile) -> Result<(), RiichiError> {
+        if self.phase != Phase::Dealing {
+            return Err(IllegalAction::AlreadyDealt.into());
+        }
+
+        for (i, tiles) in hands.iter().enumerate() {
+            if tiles.len() != 13 {
+                return Err(IllegalAction::DealCount.into());
+            }
+
+            for tile in tiles.iter() {
+                self.count_tile(tile)?;
+            }
+
+            self.players[i].hand = Hand::new(
+                tiles
+                    .iter()
+                    .map(|t| {
+                        let mut tile = *t;
+                        tile.is_draw = false;
+                        Some(tile)
+                    })
+                    .collect(),
+            );
+        }
+
+        self.count_tile(dora_indicator)?;
+        self.dora_indicators.push(*dora_indicator);
+        self.phase = Phase::Draw(self.dealer);
+
+        Ok(())
+    }
+
+    fn draw(&mut self, player: u8, tile: &Tile) -> Result<(), RiichiError> {
+        let expected = match self.phase {
+            Phase::Draw(p) => p,
+            Phase::Calls(p) => (p + 1) % 4,
+            _ => return Err(IllegalAction::DrawNotPossible.into()),
+        };
+
+        if player != expected {
+            return Err(IllegalAction::NotPlayersTurn.into());
+        }
+
+        if self.tiles_remaining == 0 {
+            return Err(IllegalAction::WallEmpty.into());
+        }
+
+        self.count_tile(tile)?;
+        self.pass_discard();
+
+        self.tiles_remaining -= 1;
+        self.add_drawn_tile(player, tile);
+        self.phase = Phase::Discard(player);
+
+        Ok(())
+    }
+
+    fn discard(&mut self, player: u8, tile: &Tile) -> Result<(), RiichiError> {
+        if self.phase != Phase::Discard(player) {
+            return Err(IllegalAction::DiscardNotPossible.into());
+        }
+
+        let first_discard = self.players[player as usize].discards.is_empty() && !self.has_calls();
+
+        let state = &mut self.players[player as usize];
+        let drawn_tile = state.hand.get_drawn_tile().copied();
+
+        let in_hand = state.hand.get_tiles().iter().any(|t| match t {
+            None => false,
+            Some(hand_tile) => hand_tile.eq(tile) && !hand_tile.is_open && !hand_tile.is_kan,
+        });
+
+        if !in_hand {
+            return Err(IllegalAction::DiscardNotInHand.into());
+        }
+
+        let is_tsumogiri = match drawn_tile {
+            None => false,
+            Some(drawn) => drawn.eq(tile) && drawn.is_red == tile.is_red,
+        };
+
+        if state.riichi && !is_tsumogiri {
+            return Err(IllegalAction::RiichiDiscard.into());
+        }
+
+        if state.kuikae.contains(tile) {
+            return Err(IllegalAction::Kuikae.into());
+        }
+
+        let mut new_hand = state.hand.clone();
+        new_hand.remove_tile(tile);
+        new_hand.reset_drawn_tiles();
+        new_hand.reset_shanten();
+
+        let mut discarded = *tile;
+        discarded.is_draw = false;
+        discarded.is_tsumogiri = is_tsumogiri;
+
+        if state.riichi_declared {
+            if new_hand.shanten() != 0 {
+                return Err(IllegalAction::RiichiDiscardNotTenpai.into());
+            }
+
+            discarded.is_riichi = true;
+            state.riichi_declared = false;
+            state.riichi = true;
+            state.double_riichi = first_discard;
+            self.pending_riichi = Some(player);
+        }
+
+        state.hand = new_hand;
+        state.discards.push(discarded);
+        state.kuikae.clear();
+
+        if !state.riichi {
+            state.furiten = false;
+        }
+
+        self.last_discard = Some(discarded);
+        self.kan_discard = self.after_kan;
+        self.after_kan = false;
+        self.phase = Phase::Calls(player);
+
+        Ok(())
+    }
+
+    fn riichi(&mut self, player: u8) -> Result<(), RiichiError> {
+        if self.phase != Phase::Discard(player) {
+            return Err(IllegalAction::RiichiNotPossible.into());
+        }
+
+        let state = &mut self.players[player as usize];
+
+        if state.riichi || state.riichi_declared {
+            return Err(IllegalAction::RiichiAlreadyDeclared.into());
+        }
+
+        if !state.hand.is_closed() {
+            return Err(IllegalAction::RiichiOpenHand.into());
+        }
+
+        if state.points < 1000 {
+            return Err(IllegalAction::RiichiPoints.into());
+        }
+
+        if self.tiles_remaining < 4 {
+            return Err(IllegalAction::RiichiTilesLeft.into());
+        }
+
+        if state.hand.shanten() > 0 {
+            return Err(IllegalAction::RiichiNotTenpai.into());
+        }
+
+        state.riichi_declared = true;
+
+        Ok(())
+    }
+
+    fn call(&mut self, player: u8, call_type: CallType, tiles: &[Tile]) -> Result<(), RiichiError> {
+        let discarder = match self.phase {
+            Phase::Calls(p) => p,
+            _ => return Err(IllegalAction::NoDiscardToCall.into()),
+        };
+
+        if player == discarder {
+            return Err(IllegalAction::OwnDiscard.into());
+        }
+
+        if call_type == CallType::Chi && player != (discarder + 1) % 4 {
+            return Err(IllegalAction::ChiNotFromKamicha.into());
+        }
+
+        if self.players[player as usize].riichi {
+            return Err(IllegalAction::CallInRiichi.into());
+        }
+
+        // houtei - the last discard can only be ronned
+        if self.tiles_remaining == 0 {
+            return Err(IllegalAction::CallOnLastDiscard.into());
+        }
+
+        if call_type == CallType::Daiminkan && self.kans >= 4 {
+            return Err(IllegalAction::NoMoreKans.into());
+        }
+
+        let mut called_tile = self.last_discard.unwrap();
+        called_tile.called_from = (discarder + 4 - player) % 4;
+        called_tile.is_tsumogiri = false;
+        called_tile.is_riichi = false;
+
+        let expected_count = match call_type {
+            CallType::Chi | CallType::Pon => 2,
+            CallType::Daiminkan => 3,
+        };
+
+        if tiles.len() != expected_count {
+            return Err(IllegalAction::CallTileCount.into());
+        }
+
+        let mut meld: Vec<Tile> = tiles.to_vec();
+        meld.push(called_tile);
+        meld.sort();
+
+        let shape = match call_type {
+            CallType::Chi => match Shape::from_tiles(&meld, true, true) {
+                Ok(s) => match s.get_shape_type() {
+                    ShapeType::Complete(CompleteShape::Open(OpenShape::Chi(_))) => {
+                        OpenShape::Chi([meld[0], meld[1], meld[2]])
+                    }
+                    _ => return Err(IllegalAction::NotAChi.into()),
+                },
+                Err(_) => return Err(IllegalAction::NotAChi.into()),
+            },
+            CallType::Pon => {
+                if !meld.iter().all(|t| t.eq(&called_tile)) {
+                    return Err(IllegalAction::NotAPon.into());
+                }
+
+                OpenShape::Pon([meld[0], meld[1], meld[2]])
+            }
+            CallType::Daiminkan => {
+                if !meld.iter().all(|t| t.eq(&called_tile)) {
+                    return Err(IllegalAction::NotAKan.into());
+                }
+
+                OpenShape::Kan(OpenKan::Daiminkan([meld[0], meld[1], meld[2], meld[3]]))
+            }
+        };
+
+        // the caller has to have the tiles in the closed part of their hand
+        let mut check_hand = self.players[player as usize].hand.clone();
+        for tile in tiles.iter() {
+            let count = check_hand.get_34_array(true)[(tile.to_id() - 1) as usize];
+            if count == 0 {
+                return Err(IllegalAction::CalledTilesNotInHand.into());
+            }
+            check_hand.remove_tile(tile);
+        }
+
+        let kuikae = RoundState::kuikae_tiles(call_type, &meld, &called_tile);
+        let has_discard = check_hand
+            .get_tiles()
+            .iter()
+            .flatten()
+            .any(|t| !t.is_open && !t.is_kan && !kuikae.contains(t));
+
+        if !has_discard {
+            return Err(IllegalAction::Kuikae.into());
+        }
+
+        self.pass_discard();
+
+        // mark the discard as called
+        if let Some(discard) = self.players[discarder as usize].discards.last_mut() {
+            discard.is_open = true;
+        }
+
+        let state = &mut self.players[player as usize];
+        state.hand.reset_drawn_tiles();
+        state.hand.add_tile(called_tile);
+        state.hand.add_open_shape(&shape);
+        state.hand.reset_shanten();
+        state.kuikae = kuikae;
+
+        self.last_discard = None;
+
+        if call_type == CallType::Daiminkan {
+            self.kans += 1;
+            self.phase = Phase::Rinshan(player);
+        } else {
+            self.phase = Phase::Discard(player);
+        }
+
+        Ok(())
+    }
+
+    fn kan(&mut self, player: u8, kan_type: KanType, tile: &Tile) -> Result<(), RiichiError> {
+        if self.phase != Phase::Discard(player) {
+            return Err(IllegalAction::KanNotPossible.into());
+        }
+
+        if self.kans >= 4 || self.tiles_remaining == 0 {
+            return Err(IllegalAction::NoMoreKans.into());
+        }
+
+        let state = &mut self.players[player as usize];
+        let mut new_hand = state.hand.clone();
+
+        match kan_type {
+            KanType::Ankan => {
+                if new_hand.get_34_array(true)[(tile.to_id() - 1) as usize] != 4 {
+                    return Err(IllegalAction::AnkanTileCount.into());
+                }
+
+                if state.riichi && !RoundState::can_ankan_in_riichi(&state.hand, tile) {
+                    return Err(IllegalAction::KanChangesWait.into());
+                }
+
+                let mut kan_tiles = vec![];
+                for t in new_hand.get_tiles().iter().flatten() {
+                    if t.eq(tile) && !t.is_open && !t.is_kan {
+                        kan_tiles.push(*t);
+                    }
+                }
+
+                new_hand.reset_drawn_tiles();
+                new_hand.add_closed_kan(ClosedShape::Kantsu([
+                    kan_tiles[0],
+                    kan_tiles[1],
+                    kan_tiles[2],
+                    kan_tiles[3],
+                ]));
+                self.chankan_tile = None;
+            }
+            KanType::Shouminkan => {
+                new_hand.add_shouminkan(tile)?;
+                new_hand.reset_drawn_tiles();
+                self.chankan_tile = Some(*tile);
+            }
+        }
+
+        new_hand.reset_shanten();
+        state.hand = new_hand;
+        self.kans += 1;
+        self.phase = Phase::Rinshan(player);
+
+        Ok(())
+    }
+
+    /// Any chi, pon or kan so far, which interrupts the first go-around
+    fn has_calls(&self) -> bool {
+        self.players
+            .iter()
+            .any(|state| !state.hand.get_shapes().is_empty())
+    }
+
+    /// Tiles that can't be discarded right after this call: the called one,
+    /// and for a chi on one of its ends, the tile on the other side (3m called with 45m also forbids 6m).
+    /// Nothing is discarded right after a daiminkan.
+    fn kuikae_tiles(call_type: CallType, meld: &[Tile], called_tile: &Tile) -> Vec<Tile> {
+        if call_type == CallType::Daiminkan {
+            return vec![];
+        }
+
+        let mut tiles = vec![*called_tile];
+
+        if call_type == CallType::Chi {
+            let other_end = if meld[0].eq(called_tile) {
+                meld[2].next_id(false, 1)
+            } else if meld[2].eq(called_tile) {
+                meld[0].prev_id(false, 1)
+            } else {
+                0
+            };
+
+            if let Ok(tile) = Tile::from_id(other_end) {
+                tiles.push(tile);
+            }
+        }
+
+        tiles
+    }
+
+    /// In riichi, an ankan is only possible with the drawn tile and when it doesn't change the wait.
+    fn can_ankan_in_riichi(hand: &Hand, tile: &Tile) -> bool {
+        match hand.get_drawn_tile() {
+            None => return false,
+            Some(drawn) => {
+                if !drawn.eq(tile) {
+                    return false;
+                }
+            }
+        }
+
+        let mut without_kan = hand.clone();
+        without_kan.remove_tile(tile);
+        without_kan.reset_drawn_tiles();
+        let waits_before = RoundState::waits(&mut without_kan);
+
+        let mut with_kan = without_kan.clone();
+        let mut kan_tile = *tile;
+        kan_tile.is_draw = false;
+        with_kan.add_tile(kan_tile);
+        with_kan.ankan_tiles(kan_tile);
+        with_kan.reset_shanten();
+        let waits_after = RoundState::waits(&mut with_kan);
+
+        !waits_before.is_empty() && waits_before == waits_after
+    }
+
+    fn rinshan(&mut self, player: u8, tile: &Tile) -> Result<(), RiichiError> {
+        if self.phase != Phase::Rinshan(player) {
+            return Err(IllegalAction::RinshanNotPossible.into());
+        }
+
+        self.count_tile(tile)?;
+
+        // nobody robbed the kan
+        if let Some(kan_tile) = self.chankan_tile.take() {
+            self.pass_tile(player, kan_tile);
+        }
+
+        // the dead wall is replenished from the live wall
+        self.tiles_remaining -= 1;
+        self.add_drawn_tile(player, tile);
+        self.after_kan = true;
+        self.phase = Phase::Discard(player);
+
+        Ok(())
+    }
+
+    fn new_dora(&mut self, indicator: &Tile) -> Result<(), RiichiError> {
+        if self.phase == Phase::Dealing {
+            return Err(IllegalAction::NotDealt.into());
+        }
+
+        if self.dora_indicators.len() > self.kans as usize {
+            return Err(IllegalAction::NoKanForDora.into());
+        }
+
+        self.count_tile(indicator)?;
+        self.dora_indicators.push(*indicator);
+
+        Ok(())
+    }
+
+    fn win(&mut self, player: u8, from: u8) -> Result<(), RiichiError> {
+        let winning_tile = if player == from {
+            // right after a chi or pon there is no drawn tile to win on
+            let hand = &self.players[player as usize].hand;
+            if self.phase != Phase::Discard(player) || hand.get_drawn_tile().is_none() {
+                return Err(IllegalAction::TsumoNotPossible.into());
+            }
+
+            None
+        } else {
+            Some(self.ron_tile(player, from)?)
+        };
+
+        let (mut table, yaku, score) = self.winning_table(player, from, winning_tile)?;
+        let tsumo = winning_tile.is_none();
+
+        let first_win = self.wins.is_empty();
+        // only the first of multiple rons gets the honba
+        if !first_win {
+            table.set_tsumibo(0);
+        }
+
+        let discarder = if tsumo {
+            None
+        } else {
+            Some((from + 4 - player) % 4)
+        };
+
+        // relative to the winner, with pao applied
+        let relative_payments = table.win_payments(&yaku, &score, discarder);
+        let mut payments = [0i32; 4];
+        for (relative, payment) in relative_payments.iter().enumerate() {
+            payments[(player as usize + relative) % 4] = *payment;
+        }
+
+        if first_win {
+            // the riichi tile was ronned, so its stick is not paid
+            if !tsumo && self.pending_riichi == Some(from) {
+                self.pending_riichi = None;
+            }
+            self.pay_pending_riichi();
+
+            payments[player as usize] += 1000 * self.riichi_sticks as i32;
+            self.riichi_sticks = 0;
+        }
+
+        for (i, payment) in payments.iter().enumerate() {
+            self.players[i].points += payment;
+        }
+
+        self.wins.push(WinResult {
+            player,
+            from,
+            yaku,
+            score,
+            payments,
+        });
+        self.phase = Phase::Ended;
+
+        Ok(())
+    }
+
+    /// The tile this player can ron from the other one right now
+    fn ron_tile(&self, player: u8, from: u8) -> Result<Tile, RiichiError> {
+        let winning_tile = match self.phase {
+            Phase::Calls(p) if p == from => self.last_discard,
+            Phase::Rinshan(p) if p == from => self.chankan_tile,
+            Phase::Ended => match self.wins.last() {
+                // double / triple ron on the same tile
+                Some(previous) if previous.from == from && previous.player != from => {
+                    if self.wins.iter().any(|w| w.player == player) {
+                        return Err(IllegalAction::AlreadyWon.into());
+                    }
+                    // the robbed kan tile stays set after a chankan
+                    self.chankan_tile.or(self.last_discard)
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+
+        match winning_tile {
+            None => Err(IllegalAction::NoTileToRon.into()),
+            Some(tile) => Ok(tile),
+        }
+    }
+
+    /// Checks that the hand of this player is a legal win - complete, with a yaku and not furiten for a ron.
+    /// Returns the table of the winner with the winning hand, yaku and score.
+    fn winning_table(
+        &self,
+        player: u8,
+        from: u8,
+        ron_tile: Option<Tile>,
+    ) -> Result<(Table, Vec<Yaku>, Score), RiichiError> {
+        let mut hand = self.players[player as usize].hand.clone();
+
+        if let Some(mut winning_tile) = ron_tile {
+            if self.players[player as usize].furiten {
+                return Err(IllegalAction::Furiten.into());
+            }
+
+            let waits = RoundState::waits(&mut hand);
+            if self.players[player as usize]
+                .discards
+                .iter()
+                .any(|d| waits.contains(d))
+            {
+                return Err(IllegalAction::Furiten.into());
+            }
+
+            hand.reset_drawn_tiles();
+            winning_tile.is_draw = true;
+            winning_tile.is_open = false;
+            winning_tile.is_riichi = false;
+            winning_tile.is_tsumogiri = false;
+            winning_tile.called_from = 0;
+            hand.add_tile(winning_tile);
+            hand.reset_shanten();
+        }
+
+        if hand.shanten() != -1 {
+            return Err(IllegalAction::HandNotComplete.into());
+        }
+
+        let mut table = self.table_for(player);
+        table.set_my_hand(hand);
+        table.set_my_tsumo(ron_tile.is_none());
+
+        // a robbed kan tile is neither a riichi declaration nor a discard after a kan
+        if let (Some(tile), None) = (ron_tile, self.chankan_tile) {
+            let relative = (from + 4 - player) % 4;
+
+            if tile.is_riichi {
+                table.set_riichi_declaring_player(relative);
+            }
+
+            if self.kan_discard {
+                table.set_kan_declaring_player(relative);
+            }
+        }
+
+        match table.yaku() {
+            Some((yaku, score)) if !yaku.is_empty() => Ok((table, yaku, score)),
+            _ => Err(IllegalAction::NoYaku.into()),
+        }
+    }
+
+    fn exhaustive_draw(&mut self) -> Result<(), RiichiError> {
+        match self.phase {
+            Phase::Draw(_) | Phase::Calls(_) => {}
+            _ => return Err(IllegalAction::ExhaustiveDrawNotPossible.into()),
+        }
+
+        if self.tiles_remaining > 0 {
+            return Err(IllegalAction::TilesLeftInWall.into());
+        }
+
+        self.pass_discard();
+
+        // seat 0 is player 0, so relative positions are the same as seats
+        let mut table = self.table_for(0);
+        for player in 1..4 {
+            table.set_hand(player, self.players[player as usize].hand.clone())?;
+        }
+
+        let result = table.exhaustive_draw()?;
+        for (i, payment) in result.payments.iter().enumerate() {
+            self.players[i].points += payment;
+        }
+
+        self.draw = Some(DrawResult {
+            abortive: None,
+            tenpai: result.tenpai,
+            nagashi: result.nagashi,
+            payments: result.payments,
+        });
+        self.phase = Phase::Ended;
+
+        Ok(())
+    }
+
+    fn abortive_draw(&mut self, reason: AbortiveDraw, rons: &[u8]) -> Result<(), RiichiError> {
+        let possible = match (reason, self.phase) {
+            (AbortiveDraw::KyuushuKyuuhai, Phase::Discard(player)) => {
+                self.table_for(player).can_kyuushu_kyuuhai()
+            }
+            (AbortiveDraw::SuufonRenda, Phase::Calls(_)) => self.table_for(0).is_suufon_renda(),
+            (AbortiveDraw::SuuchaRiichi, Phase::Calls(_)) => self.table_for(0).is_suucha_riichi(),
+            (AbortiveDraw::Suukaikan, Phase::Calls(_)) => self.table_for(0).is_suukaikan(),
+            (AbortiveDraw::Sanchahou, Phase::Calls(from)) => {
+                self.check_rons(from, rons)?;
+                self.table_for(0).is_sanchahou(rons.len() as u8)
+            }
+            _ => false,
+        };
+
+        if !possible {
+            return Err(IllegalAction::AbortiveDrawNotPossible(reason).into());
+        }
+
+        self.pass_discard();
+
+        let mut tenpai = [false; 4];
+        for (i, state) in self.players.iter_mut().enumerate() {
+            tenpai[i] = state.hand.count_tiles() == 13 && state.hand.shanten() == 0;
+        }
+
+        self.draw = Some(DrawResult {
+            abortive: Some(reason),
+            tenpai,
+            nagashi: [false; 4],
+            payments: [0; 4],
+        });
+        self.phase = Phase::Ended;
+
+        Ok(())
+    }
+
+    /// Every player has to be able to ron this discard, and nobody can be named twice
+    fn check_rons(&self, from: u8, rons: &[u8]) -> Result<(), RiichiError> {
+        for (i, player) in rons.iter().enumerate() {
+            if *player > 3 || *player == from || rons[..i].contains(player) {
+                return Err(IllegalAction::NoTileToRon.into());
+            }
+
+            self.winning_table(*player, from, Some(self.ron_tile(*player, from)?))?;
+        }
+
+        Ok(())
+    }
+
+    /// The last discard was not ronned - riichi sticks get paid and players who let a winning tile pass are furiten.
+    fn pass_discard(&mut self) {
+        if let Phase::Calls(discarder) = self.phase {
+            if let Some(tile) = self.last_discard {
+                self.pass_tile(discarder, tile);
+            }
+        }
+
+        self.pay_pending_riichi();
+    }
+
+    /// Players who could ron this tile of another player and didn't are furiten
+    fn pass_tile(&mut self, from: u8, tile: Tile) {
+        for (i, state) in self.players.iter_mut().enumerate() {
+            if i as u8 == from || state.hand.shanten() != 0 {
+                continue;
+            }
+
+            let mut hand = state.hand.clone();
+            let mut winning_tile = tile;
+            winning_tile.is_open = false;
+            hand.add_tile(winning_tile);
+            hand.reset_shanten();
+
+            if hand.shanten() == -1 {
+                state.furiten = true;
+            }
+        }
+    }
+
+    fn pay_pending_riichi(&mut self) {
+        if let Some(player) = self.pending_riichi {
+            self.players[player as usize].points -= 1000;
+            self.riichi_sticks += 1;
+            self.pending_riichi = None;
+        }
+    }
+
+    fn add_drawn_tile(&mut self, player: u8, tile: &Tile) {
+        let mut drawn = *tile;
+        drawn.is_draw = true;
+
+        let state = &mut self.players[player as usize];
+        state.hand.reset_drawn_tiles();
+        state.hand.add_tile(drawn);
+        state.hand.reset_shanten();
+    }
+
+    fn count_tile(&mut self, tile: &Tile) -> Result<(), RiichiError> {
+        let index = (tile.to_id() - 1) as usize;
+        if self.tile_counts[index] >= 4 {
+            return Err(IllegalAction::FifthCopy(*tile).into());
+        }
+
+        self.tile_counts[index] += 1;
+
+        Ok(())
+    }
+
+    /// Tiles that complete a tenpai hand of 13 tiles
+    fn waits(hand: &mut Hand) -> Vec<Tile> {
+        if hand.count_tiles() != 13 || hand.shanten() != 0 {
+            return vec![];
+        }
+
+        match hand.find_shanten_improving_tiles(None).first() {
+            None => vec![],
+            Some((_, tiles, _)) => tiles.iter().map(|(t, _)| *t).collect(),
+        }
+    }
+}
+
+/// A recorded round - how it started and its events, for saving and replaying rounds as JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoundLog {
+    #[serde(default = "RoundLog::default_prevalent_wind")]
+    pub prevalent_wind: u8,
+    #[serde(default)]
+    pub dealer: u8,
+    #[serde(default)]
+    pub honba: u8,
+    #[serde(default)]
+    pub riichi_sticks: u8,
+    /// consecutive wins of the dealer before this round
+    #[serde(default)]
+    pub renchan: u8,
+    #[serde(default = "RoundLog::default_points")]
+    pub points: [i32; 4],
+    /// rules the round was played with, the default ones when missing
+    #[serde(default)]
+    pub rules: Rules,
+    pub events: Vec<RoundEvent>,
+}
+
+impl RoundLog {
+    fn default_prevalent_wind() -> u8 {
+        1
+    }
+
+    fn default_points() -> [i32; 4] {
+        [25000; 4]
+    }
+}
+
+/// Event-sourced state machine of one round.
+/// Every event is checked for legality before it's applied, and the round can be rewound with undo / redo.
+pub struct Round {
+    initial: RoundState,
+    state: RoundState,
+    events: Vec<RoundEvent>,
+    /// how many events from `events` are applied
+    position: usize,
+}
+
+impl Round {
+    pub fn new(
+        prevalent_wind: u8,
+        dealer: u8,
+        honba: u8,
+        riichi_sticks: u8,
+        points: [i32; 4],
+    ) -> Round {
+        Round::from_snapshot(RoundState::new(
+            prevalent_wind,
+            dealer,
+            honba,
+            riichi_sticks,
+            points,
+        ))
+    }
+
+    /// Replays a recorded round with its rules, stops at the first illegal event
+    pub fn from_log(log: &RoundLog) -> Result<Round, RiichiError> {
+        let mut round = Round::new(
+            log.prevalent_wind,
+            log.dealer,
+            log.honba,
+            log.riichi_sticks,
+            log.points,
+        );
+        round.set_rules(log.rules.clone());
+        round.set_renchan(log.renchan);
+
+        for event in log.events.iter() {
+            round.apply(event.clone())?;
+        }
+
+        Ok(round)
+    }
+
+    /// The applied events with the starting state
+    pub fn to_log(&self) -> RoundLog {
+        RoundLog {
+            prevalent_wind: self.initial.prevalent_wind,
+            dealer: self.initial.dealer,
+            honba: self.initial.honba,
+            riichi_sticks: self.initial.riichi_sticks,
+            renchan: self.initial.renchan,
+            points: self.initial.get_points(),
+            rules: self.initial.rules.clone(),
+            events: self.get_events().to_vec(),
+        }
+    }
+
+    /// Continues a round from a snapshot. Undo will not go further back than the snapshot.
+    pub fn from_snapshot(state: RoundState) -> Round {
+        Round {
+            initial: state.clone(),
+            state,
+            events: vec![],
+            position: 0,
+        }
+    }
+
+    /// Checks and applies the event. Events that were undone are discarded.
+    pub fn apply(&mut self, event: RoundEvent) -> Result<(), RiichiError> {
+        let mut next_state = self.state.clone();
+        next_state.apply(&event)?;
+
+        self.state = next_state;
+        self.events.truncate(self.position);
+        self.events.push(event);
+        self.position += 1;
+
+        Ok(())
+    }
+
+    /// Reverts the last applied event. Returns false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        if self.position == 0 {
+            return false;
+        }
+
+        self.position -= 1;
+        self.state = self.initial.clone();
+        for event in self.events[..self.position].iter() {
+            // these events have already been checked in this exact order
+            self.state.apply(event).unwrap();
+        }
+
+        true
+    }
+
+    /// Applies the last undone event again. Returns false if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        if self.position == self.events.len() {
+            return false;
+        }
+
+        self.state.apply(&self.events[self.position]).unwrap();
+        self.position += 1;
+
+        true
+    }
+
+    /// Rules for the abortive draws, and for the tables of the players. Set them before the first event.
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.initial.rules = rules.clone();
+        self.state.rules = rules;
+    }
+
+    /// Consecutive wins of the dealer before this round, for paarenchan. Set it before the first event.
+    pub fn set_renchan(&mut self, renchan: u8) {
+        self.initial.renchan = renchan;
+        self.state.renchan = renchan;
+    }
+
+    pub fn snapshot(&self) -> RoundState {
+        self.state.clone()
+    }
+
+    pub fn get_state(&self) -> &RoundState {
+        &self.state
+    }
+
+    /// State before the first event
+    pub fn get_initial_state(&self) -> &RoundState {
+        &self.initial
+    }
+
+    /// Applied events, in order
+    pub fn get_events(&self) -> &[RoundEvent] {
+        &self.events[..self.position]
+    }
+
+    pub fn is_ended(&self) -> bool {
+        self.state.phase == Phase::Ended
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiles(representation: &str) -> Vec<Tile> {
+        Hand::from_text(representation, true)
+            .unwrap()
+            .get_tiles()
+            .iter()
+            .flatten()
+            .map(|t| {
+                let mut tile = *t;
+                tile.is_draw = false;
+                tile
+            })
+            .collect()
+    }
+
+    fn tile(representation: &str) -> Tile {
+        Tile::from_text(representation).unwrap()
+    }
+
+    fn dealt_round() -> Round {
+        let mut round = Round::new(1, 0, 0, 0, [25000; 4]);
+        round
+            .apply(RoundEvent::Deal {
+                hands: [
+                    tiles("123m456p789s1122z"),
+                    tiles("123m456p789s3344z"),
+                    tiles("456m123p123s5566z"),
+                    tiles("789m789p456s1357z"),
+                ],
+                dora_indicator: tile("9m"),
+            })
+            .unwrap();
+
+        round
+    }
+
+    #[test]
+    fn draw_and_discard() {
+        let mut round = dealt_round();
+
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("7z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("7z"),
+            })
+            .unwrap();
+
+        let state = round.get_state();
+        assert_eq!(state.get_tiles_remaining(), 69);
+        assert_eq!(state.get_phase(), Phase::Calls(0));
+        assert!(state.get_player(0).get_discards()[0].is_tsumogiri);
+        assert_eq!(state.get_player(0).get_hand().count_tiles(), 13);
+    }
+
+    #[test]
+    fn wrong_player_draws() {
+        let mut round = dealt_round();
+
+        assert!(round
+            .apply(RoundEvent::Draw {
+                player: 1,
+                tile: tile("7z"),
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn fifth_copy_is_illegal() {
+        let mut round = dealt_round();
+
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("9m"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("9m"),
+            })
+            .unwrap();
+        // 9m: one in p3's hand, one as the dora indicator and two drawn
+        round
+            .apply(RoundEvent::Draw {
+                player: 1,
+                tile: tile("9m"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 1,
+                tile: tile("9m"),
+            })
+            .unwrap();
+
+        assert!(round
+            .apply(RoundEvent::Draw {
+                player: 2,
+                tile: tile("9m"),
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn discard_not_in_hand() {
+        let mut round = dealt_round();
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("7z"),
+            })
+            .unwrap();
+
+        assert!(round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("5z"),
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn pon_and_discard() {
+        let mut round = dealt_round();
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("5z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("5z"),
+            })
+            .unwrap();
+
+        // chi is only possible from kamicha
+        assert!(round
+            .apply(RoundEvent::Call {
+                player: 2,
+                call_type: CallType::Chi,
+                tiles: vec![tile("5z"), tile("5z")],
+            })
+            .is_err());
+
+        round
+            .apply(RoundEvent::Call {
+                player: 2,
+                call_type: CallType::Pon,
+                tiles: vec![tile("5z"), tile("5z")],
+            })
+            .unwrap();
+
+        let state = round.get_state();
+        assert_eq!(state.get_phase(), Phase::Discard(2));
+        assert!(!state.get_player(2).get_hand().is_closed());
+        assert!(state.get_player(0).get_discards()[0].is_open);
+
+        // player 1 was skipped
+        round
+            .apply(RoundEvent::Discard {
+                player: 2,
+                tile: tile("6z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Draw {
+                player: 3,
+                tile: tile("7z"),
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn no_tsumo_after_pon() {
+        let mut round = dealt_round();
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("5z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("5z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Call {
+                player: 2,
+                call_type: CallType::Pon,
+                tiles: vec![tile("5z"), tile("5z")],
+            })
+            .unwrap();
+
+        assert_eq!(
+            round.apply(RoundEvent::Win { player: 2, from: 2 }),
+            Err(IllegalAction::TsumoNotPossible.into())
+        );
+    }
+
+    #[test]
+    fn no_call_on_last_discard() {
+        let mut state = dealt_round().snapshot();
+        state.tiles_remaining = 1;
+        let mut round = Round::from_snapshot(state);
+
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("5z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("5z"),
+            })
+            .unwrap();
+
+        assert_eq!(
+            round.apply(RoundEvent::Call {
+                player: 2,
+                call_type: CallType::Pon,
+                tiles: vec![tile("5z"), tile("5z")],
+            }),
+            Err(IllegalAction::CallOnLastDiscard.into())
+        );
+        round.apply(RoundEvent::ExhaustiveDraw).unwrap();
+    }
+
+    #[test]
+    fn kuikae_after_chi() {
+        let mut round = dealt_round();
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("7p"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("7p"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Call {
+                player: 1,
+                call_type: CallType::Chi,
+                tiles: vec![tile("5p"), tile("6p")],
+            })
+            .unwrap();
+
+        // 7p called with 56p, so the 4p on the other end can't be discarded either
+        assert_eq!(
+            round.apply(RoundEvent::Discard {
+                player: 1,
+                tile: tile("4p"),
+            }),
+            Err(IllegalAction::Kuikae.into())
+        );
+        round
+            .apply(RoundEvent::Discard {
+                player: 1,
+                tile: tile("3z"),
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn player_out_of_range() {
+        let mut round = dealt_round();
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("5z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("5z"),
+            })
+            .unwrap();
+
+        assert_eq!(
+            round.apply(RoundEvent::Call {
+                player: 9,
+                call_type: CallType::Pon,
+                tiles: vec![tile("5z"), tile("5z")],
+            }),
+            Err(IllegalAction::NoSuchPlayer(9).into())
+        );
+        assert_eq!(
+            round.apply(RoundEvent::Win { player: 9, from: 0 }),
+            Err(IllegalAction::NoSuchPlayer(9).into())
+        );
+        assert_eq!(
+            round.apply(RoundEvent::Win { player: 1, from: 7 }),
+            Err(IllegalAction::NoSuchPlayer(7).into())
+        );
+    }
+
+    #[test]
+    fn ron_on_late_riichi_declaration() {
+        let mut round = dealt_round();
+        let mut rules = Rules::default();
+        rules.tsubame_gaeshi = true;
+        round.set_rules(rules);
+        for (player, t) in [(0, "7z"), (1, "6z"), (2, "7p"), (3, "8s")] {
+            round
+                .apply(RoundEvent::Draw {
+                    player,
+                    tile: tile(t),
+                })
+                .unwrap();
+            round
+                .apply(RoundEvent::Discard {
+                    player,
+                    tile: tile(t),
+                })
+                .unwrap();
+        }
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("4z"),
+            })
+            .unwrap();
+        round.apply(RoundEvent::Riichi { player: 0 }).unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("4z"),
+            })
+            .unwrap();
+        assert!(!round.get_state().get_player(0).is_double_riichi());
+
+        // p1 waits on 3z and 4z
+        round.apply(RoundEvent::Win { player: 1, from: 0 }).unwrap();
+
+        let win = &round.get_state().get_wins()[0];
+        assert!(win.yaku.iter().any(|y| matches!(y, Yaku::TsubameGaeshi)));
+    }
+
+    #[test]
+    fn riichi_and_ron() {
+        let mut round = dealt_round();
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("7z"),
+            })
+            .unwrap();
+        round.apply(RoundEvent::Riichi { player: 0 }).unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("7z"),
+            })
+            .unwrap();
+
+        // p0 waits on 1z and 2z
+        round
+            .apply(RoundEvent::Draw {
+                player: 1,
+                tile: tile("2z"),
+            })
+            .unwrap();
+
+        assert_eq!(round.get_state().get_riichi_sticks(), 1);
+        assert_eq!(round.get_state().get_player(0).get_points(), 24000);
+
+        round
+            .apply(RoundEvent::Discard {
+                player: 1,
+                tile: tile("2z"),
+            })
+            .unwrap();
+        round.apply(RoundEvent::Win { player: 0, from: 1 }).unwrap();
+
+        let state = round.get_state();
+        assert!(round.is_ended());
+        let win = &state.get_wins()[0];
+        // riichi on the first discard, before any call
+        assert!(state.get_player(0).is_double_riichi());
+        assert!(win.yaku.iter().any(|y| matches!(y, Yaku::DoubleRiichi)));
+        assert!(!win.yaku.iter().any(|y| matches!(y, Yaku::Riichi)));
+        // double riichi + dora (9m indicator), dealer 3 han 40 fu (double east pair, 2z triplet completed by ron)
+        // = 7700 + riichi stick back
+        assert_eq!(win.payments[1], -7700);
+        assert_eq!(state.get_player(0).get_points(), 32700);
+    }
+
+    #[test]
+    fn riichi_only_when_tenpai() {
+        let mut round = dealt_round();
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("7z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("7z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Draw {
+                player: 1,
+                tile: tile("6z"),
+            })
+            .unwrap();
+        round.apply(RoundEvent::Riichi { player: 1 }).unwrap();
+
+        // discarding 1m leaves the hand 1-shanten
+        assert!(round
+            .apply(RoundEvent::Discard {
+                player: 1,
+                tile: tile("1m"),
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn kyuushu_kyuuhai() {
+        let deal = RoundEvent::Deal {
+            hands: [
+                tiles("19m19p19s12345z23m"),
+                tiles("234m456p678s6677z"),
+                tiles("567m567p567s5556z"),
+                tiles("888m888p888s2223z"),
+            ],
+            dora_indicator: tile("9m"),
+        };
+        let kyuushu = RoundEvent::AbortiveDraw {
+            reason: AbortiveDraw::KyuushuKyuuhai,
+            rons: vec![],
+        };
+        let draw = RoundEvent::Draw {
+            player: 0,
+            tile: tile("4m"),
+        };
+
+        let mut round = Round::new(1, 0, 0, 0, [25000; 4]);
+        round.apply(deal.clone()).unwrap();
+        round.apply(draw.clone()).unwrap();
+        assert!(round
+            .apply(RoundEvent::AbortiveDraw {
+                reason: AbortiveDraw::SuuchaRiichi,
+                rons: vec![],
+            })
+            .is_err());
+        round.apply(kyuushu.clone()).unwrap();
+
+        assert!(round.is_ended());
+        assert_eq!(
+            round.get_state().get_draw().unwrap().abortive,
+            Some(AbortiveDraw::KyuushuKyuuhai)
+        );
+
+        let mut round = Round::new(1, 0, 0, 0, [25000; 4]);
+        round.set_rules(Rules {
+            kyuushu_kyuuhai: false,
+            ..Rules::default()
+        });
+        round.apply(deal).unwrap();
+        round.apply(draw).unwrap();
+        assert!(round.apply(kyuushu).is_err());
+    }
+
+    /// Players 1 and 2 (and 3 with the right hand) wait on the 5s player 0 discards
+    fn sanchahou_round(p3_hand: &str) -> Round {
+        let mut round = Round::new(1, 0, 0, 0, [25000; 4]);
+        round
+            .apply(RoundEvent::Deal {
+                hands: [
+                    tiles("111222333444z5s"),
+                    tiles("234m345p678p66s34s"),
+                    tiles("456m678m234p88p67s"),
+                    tiles(p3_hand),
+                ],
+                dora_indicator: tile("9m"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("5z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("5s"),
+            })
+            .unwrap();
+
+        round
+    }
+
+    fn sanchahou(rons: Vec<u8>) -> RoundEvent {
+        RoundEvent::AbortiveDraw {
+            reason: AbortiveDraw::Sanchahou,
+            rons,
+        }
+    }
+
+    #[test]
+    fn sanchahou_needs_three_rons() {
+        let mut round = sanchahou_round("345m567p234s222m5s");
+        assert!(round.apply(sanchahou(vec![1, 2])).is_err());
+        assert!(round.apply(sanchahou(vec![1, 2, 0])).is_err());
+        assert!(round.apply(sanchahou(vec![1, 2, 2])).is_err());
+        round.apply(sanchahou(vec![1, 2, 3])).unwrap();
+
+        assert_eq!(
+            round.get_state().get_draw().unwrap().abortive,
+            Some(AbortiveDraw::Sanchahou)
+        );
+    }
+
+    #[test]
+    fn sanchahou_with_a_player_who_cannot_ron() {
+        // player 3 waits on 9s
+        let mut round = sanchahou_round("345m567p234s222m9s");
+
+        assert_eq!(
+            round.apply(sanchahou(vec![1, 2, 3])).unwrap_err().code(),
+            RiichiError::from(IllegalAction::HandNotComplete).code()
+        );
+        assert!(!round.is_ended());
+    }
+
+    /// Player 0 pons 5p from player 3 and adds the last 5p to it, players 1 and 2 wait on 5p
+    fn chankan_round() -> Round {
+        let mut round = Round::new(1, 0, 0, 0, [25000; 4]);
+        round
+            .apply(RoundEvent::Deal {
+                hands: [
+                    tiles("55p123m789m111z22z"),
+                    tiles("22m34p234s567s888s"),
+                    tiles("67p345m678m456s99s"),
+                    tiles("19m19p19s1234567z"),
+                ],
+                dora_indicator: tile("9m"),
+            })
+            .unwrap();
+
+        for (player, drawn) in ["4z", "3z", "6z", "5p"].iter().enumerate() {
+            round
+                .apply(RoundEvent::Draw {
+                    player: player as u8,
+                    tile: tile(drawn),
+                })
+                .unwrap();
+            round
+                .apply(RoundEvent::Discard {
+                    player: player as u8,
+                    tile: tile(drawn),
+                })
+                .unwrap();
+        }
+
+        round
+            .apply(RoundEvent::Call {
+                player: 0,
+                call_type: CallType::Pon,
+                tiles: vec![tile("5p"), tile("5p")],
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("2z"),
+            })
+            .unwrap();
+
+        for (player, drawn) in ["4z", "6z", "7z"].iter().enumerate() {
+            let player = player as u8 + 1;
+            round
+                .apply(RoundEvent::Draw {
+                    player,
+                    tile: tile(drawn),
+                })
+                .unwrap();
+            round
+                .apply(RoundEvent::Discard {
+                    player,
+                    tile: tile(drawn),
+                })
+                .unwrap();
+        }
+
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("5p"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Kan {
+                player: 0,
+                kan_type: KanType::Shouminkan,
+                tile: tile("5p"),
+            })
+            .unwrap();
+
+        round
+    }
+
+    #[test]
+    fn double_ron_on_chankan() {
+        let mut round = chankan_round();
+        round.apply(RoundEvent::Win { player: 1, from: 0 }).unwrap();
+        round.apply(RoundEvent::Win { player: 2, from: 0 }).unwrap();
+
+        // both won on the kan tile, the last discard (7z) completes neither hand
+        let wins = round.get_state().get_wins();
+        assert_eq!(wins.len(), 2);
+        assert!(wins[0].yaku.contains(&Yaku::Tanyao));
+        assert!(wins[1].yaku.contains(&Yaku::Pinfu));
+    }
+
+    #[test]
+    fn furiten_after_passing_chankan() {
+        let mut round = chankan_round();
+        round
+            .apply(RoundEvent::Rinshan {
+                player: 0,
+                tile: tile("1p"),
+            })
+            .unwrap();
+
+        assert!(round.get_state().get_player(1).is_furiten());
+        assert!(round.get_state().get_player(2).is_furiten());
+        assert!(!round.get_state().get_player(3).is_furiten());
+    }
+
+    #[test]
+    fn ron_without_yaku_is_illegal() {
+        let mut round = dealt_round();
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("7z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("7z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Draw {
+                player: 1,
+                tile: tile("2z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 1,
+                tile: tile("2z"),
+            })
+            .unwrap();
+
+        // p0 is the east dealer, a triplet of 2z is not a yakuhai for them
+        assert_eq!(
+            round.apply(RoundEvent::Win { player: 0, from: 1 }),
+            Err(IllegalAction::NoYaku.into())
+        );
+    }
+
+    #[test]
+    fn furiten_after_passing() {
+        let mut round = dealt_round();
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("7z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("7z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Draw {
+                player: 1,
+                tile: tile("2z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 1,
+                tile: tile("2z"),
+            })
+            .unwrap();
+        // p0 lets the 2z pass (they would have no yaku with it anyway)
+        round
+            .apply(RoundEvent::Draw {
+                player: 2,
+                tile: tile("1z"),
+            })
+            .unwrap();
+        assert!(round.get_state().get_player(0).is_furiten());
+
+        round
+            .apply(RoundEvent::Discard {
+                player: 2,
+                tile: tile("1z"),
+            })
+            .unwrap();
+        assert!(round.apply(RoundEvent::Win { player: 0, from: 2 }).is_err());
+    }
+
+    #[test]
+    fn ankan_rinshan_and_dora() {
+        let mut round = Round::new(1, 0, 0, 0, [25000; 4]);
+        round
+            .apply(RoundEvent::Deal {
+                hands: [
+                    tiles("111m456p789s1122z"),
+                    tiles("234m456p789s3344z"),
+                    tiles("456m123p123s5566z"),
+                    tiles("789m789p456s1357z"),
+                ],
+                dora_indicator: tile("9m"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("1m"),
+            })
+            .unwrap();
+
+        // can't reveal a kan dora without a kan
+        assert!(round
+            .apply(RoundEvent::NewDora {
+                indicator: tile("3p"),
+            })
+            .is_err());
+
+        round
+            .apply(RoundEvent::Kan {
+                player: 0,
+                kan_type: KanType::Ankan,
+                tile: tile("1m"),
+            })
+            .unwrap();
+        assert_eq!(round.get_state().get_phase(), Phase::Rinshan(0));
+
+        round
+            .apply(RoundEvent::NewDora {
+                indicator: tile("3p"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Rinshan {
+                player: 0,
+                tile: tile("7z"),
+            })
+            .unwrap();
+
+        let state = round.get_state();
+        assert_eq!(state.get_kans(), 1);
+        assert_eq!(state.get_dora_indicators().len(), 2);
+        assert_eq!(state.get_tiles_remaining(), 68);
+        assert_eq!(state.get_player(0).get_hand().count_tiles(), 14);
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut round = dealt_round();
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("7z"),
+            })
+            .unwrap();
+        let snapshot = round.snapshot();
+
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("7z"),
+            })
+            .unwrap();
+        assert_eq!(round.get_state().get_phase(), Phase::Calls(0));
+
+        assert!(round.undo());
+        assert_eq!(round.get_state().get_phase(), Phase::Discard(0));
+        assert_eq!(
+            round.get_state().get_player(0).get_hand().to_string(),
+            snapshot.get_player(0).get_hand().to_string()
+        );
+
+        assert!(round.redo());
+        assert_eq!(round.get_state().get_phase(), Phase::Calls(0));
+        assert!(!round.redo());
+
+        // a new event after undo drops the redo history
+        assert!(round.undo());
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("1m"),
+            })
+            .unwrap();
+        assert!(!round.redo());
+        assert_eq!(round.get_events().len(), 3);
+
+        let resumed = Round::from_snapshot(snapshot);
+        assert_eq!(resumed.get_state().get_phase(), Phase::Discard(0));
+    }
+
+    #[test]
+    fn table_for_player() {
+        let mut round = dealt_round();
+        round
+            .apply(RoundEvent::Draw {
+                player: 0,
+                tile: tile("7z"),
+            })
+            .unwrap();
+        round
+            .apply(RoundEvent::Discard {
+                player: 0,
+                tile: tile("7z"),
+            })
+            .unwrap();
+
+        let table = round.get_state().table_for(1);
+        assert_eq!(table.get_my_seat_wind(), Some(2));
+        // player 0 is kamicha of player 1
+        assert_eq!(table.get_discards(3).unwrap().len(), 1);
+        assert_eq!(table.get_tiles_remaining(), Some(69));
+    }
+
+    #[test]
+    fn replay_from_json_log() {
+        let mut round = dealt_round();
+        for event in [
+            RoundEvent::Draw {
+                player: 0,
+                tile: tile("7z"),
+            },
+            RoundEvent::Riichi { player: 0 },
+            RoundEvent::Discard {
+                player: 0,
+                tile: tile("7z"),
+            },
+            RoundEvent::Draw {
+                player: 1,
+                tile: tile("2z"),
+            },
+            RoundEvent::Discard {
+                player: 1,
+                tile: tile("2z"),
+            },
+            RoundEvent::Win { player: 0, from: 1 },
+        ] {
+            round.apply(event).unwrap();
+        }
+
+        let json = serde_json::to_string(&round.to_log()).unwrap();
+        let log: RoundLog = serde_json::from_str(&json).unwrap();
+        let replayed = Round::from_log(&log).unwrap();
+
+        assert!(replayed.is_ended());
+        assert_eq!(
+            replayed.get_state().get_points(),
+            [32700, 17300, 25000, 25000]
+        );
+
+        // the log can't skip the deal
+        let mut log: RoundLog = serde_json::from_str(&json).unwrap();
+        log.events.remove(0);
+        assert!(Round::from_log(&log).is_err());
+
+        // the round is replayed with its own rules, missing ones are the defaults
+        let mut round = dealt_round();
+        round.set_rules(Rules::ema());
+        let json = serde_json::to_string(&round.to_log()).unwrap();
+        let replayed = Round::from_log(&serde_json::from_str(&json).unwrap()).unwrap();
+        assert!(!replayed.get_state().rules.kyuushu_kyuuhai);
+
+        let log: RoundLog =
+            serde_json::from_str(r#"{"rules": {"sanma": true}, "events": []}"#).unwrap();
+        assert!(log.rules.sanma);
+        assert!(log.rules.aka_ari);
+    }
+}
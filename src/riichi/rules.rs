@@ -1,11 +1,185 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum GameLength {
     Tonpuusen,
     Hanchan,
 }
 
+/// When is the kan dora indicator revealed after a kan
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum KanDoraTiming {
+    /// right after any kan
+    Immediate,
+    /// after the kan player's discard (or their next kan)
+    AfterDiscard,
+    /// immediately after a closed kan, after the discard for open kans (Tenhou)
+    ClosedImmediate,
+}
+
+/// Where counted yakuman (kazoe) is capped
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum KazoeLimit {
+    /// 13+ han without a yakuman is sanbaiman
+    Sanbaiman,
+    Yakuman,
+}
+
+/// How players with the same points are placed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TieBreak {
+    /// the player closer to the first dealer places higher
+    SeatOrder,
+    /// tied players share the placing
+    Split,
+}
+
+/// Missing fields in JSON get their default value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Rules {
     pub game_length: GameLength,
     pub aka_ari: bool,
+    /// number of red fives when aka_ari is on - 3 means one of each suit, 4 adds a second red 5p
+    pub aka_count: u8,
     pub kuitan_ari: bool,
+    /// three-player mahjong - 108 tiles without 2m-8m, kita is set aside as nukidora
+    pub sanma: bool,
+    /// sanma only - the missing north player's share of a tsumo payment is lost.
+    /// Without tsumo loss, it's split between the two paying players.
+    pub tsumo_loss: bool,
+    pub kan_dora_timing: KanDoraTiming,
+    /// 4 han 30 fu and 3 han 60 fu are rounded up to mangan
+    pub kiriage_mangan: bool,
+    pub kazoe_limit: KazoeLimit,
+    /// more yakuman in one hand add up to double yakuman and more
+    pub multiple_yakuman: bool,
+    pub starting_points: i32,
+    /// points needed to get oka - (return_points - starting_points) * 4 goes to the first place
+    pub return_points: i32,
+    /// uma for 1st - 4th place, in thousands of points
+    pub uma: [i32; 4],
+    pub tie_break: TieBreak,
+    /// the game ends when somebody goes below 0 points
+    pub tobi: bool,
+    /// the dealer can end the game in All Last when they win or are tenpai in the first place
+    pub agariyame: bool,
+    /// abortive draws (tochuu ryuukyoku)
+    pub kyuushu_kyuuhai: bool,
+    pub suufon_renda: bool,
+    pub suucha_riichi: bool,
+    pub suukaikan: bool,
+    /// triple ron is a draw instead of 3 wins
+    pub sanchahou: bool,
+    /// sekinin barai - whoever fed the final meld of daisangen or daisuushii pays for it
+    pub pao: bool,
+    /// pao also applies to suukantsu, for the discard of the fourth kan
+    pub pao_suukantsu: bool,
+    /// local yaku - ron before your first draw (yakuman)
+    pub renhou: bool,
+    /// local yaku - 22334455667788p (yakuman)
+    pub daisharin: bool,
+    /// local yaku - riichi with the hand shown, one han on top of riichi
+    pub open_riichi: bool,
+    /// local yaku - double riichi won on the last tile (yakuman)
+    pub ishiue_sannen: bool,
+    /// local yaku - ron on another player's riichi declaration tile (1 han)
+    pub tsubame_gaeshi: bool,
+    /// local yaku - ron on a tile discarded right after a kan (1 han)
+    pub kanburi: bool,
+    /// local yaku - 13 unrelated tiles and a pair on the first draw (yakuman)
+    pub shiisanpuutaa: bool,
+    /// local yaku - dealer's 8th consecutive win (yakuman)
+    pub paarenchan: bool,
+    /// local yaku - three triplets in a row in one suit (2 han)
+    pub sanrenkou: bool,
     // TODO more rules
 }
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            game_length: GameLength::Hanchan,
+            aka_ari: true,
+            aka_count: 3,
+            kuitan_ari: true,
+            sanma: false,
+            tsumo_loss: true,
+            kan_dora_timing: KanDoraTiming::ClosedImmediate,
+            kiriage_mangan: false,
+            kazoe_limit: KazoeLimit::Yakuman,
+            multiple_yakuman: true,
+            starting_points: 25000,
+            return_points: 30000,
+            uma: [20, 10, -10, -20],
+            tie_break: TieBreak::SeatOrder,
+            tobi: true,
+            agariyame: true,
+            kyuushu_kyuuhai: true,
+            suufon_renda: true,
+            suucha_riichi: true,
+            suukaikan: true,
+            sanchahou: true,
+            pao: true,
+            pao_suukantsu: false,
+            renhou: false,
+            daisharin: false,
+            open_riichi: false,
+            ishiue_sannen: false,
+            tsubame_gaeshi: false,
+            kanburi: false,
+            shiisanpuutaa: false,
+            paarenchan: false,
+            sanrenkou: false,
+        }
+    }
+}
+
+impl Rules {
+    /// Points of one honba for a win - 300, on tsumo 100 from each paying player
+    pub fn honba_points(&self, tsumo: bool) -> u32 {
+        match (tsumo, self.sanma) {
+            (true, true) => 200,
+            _ => 300,
+        }
+    }
+
+    /// Tenhou ranked games, same as the default rules
+    pub fn tenhou() -> Rules {
+        Rules::default()
+    }
+
+    /// M-League - bigger uma, tied players split uma and oka, no busting out, no agariyame
+    /// and no multiple yakuman
+    pub fn m_league() -> Rules {
+        Rules {
+            multiple_yakuman: false,
+            uma: [30, 10, -10, -30],
+            tie_break: TieBreak::Split,
+            tobi: false,
+            agariyame: false,
+            ..Rules::default()
+        }
+    }
+
+    /// EMA riichi rules - 30000 points without oka, no red fives, no abortive draws
+    /// and no multiple yakuman
+    pub fn ema() -> Rules {
+        Rules {
+            aka_ari: false,
+            multiple_yakuman: false,
+            starting_points: 30000,
+            return_points: 30000,
+            uma: [15, 5, -5, -15],
+            tie_break: TieBreak::Split,
+            tobi: false,
+            agariyame: false,
+            kyuushu_kyuuhai: false,
+            suufon_renda: false,
+            suucha_riichi: false,
+            suukaikan: false,
+            sanchahou: false,
+            ..Rules::default()
+        }
+    }
+}
@@ -1,13 +1,38 @@
-#[derive(Debug)]
+use crate::riichi::rules::{KazoeLimit, Rules};
+use serde::{Deserialize, Serialize};
+
+/// Points to reach with Score::all_from_points
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PointsTarget {
+    Exactly(u32),
+    AtLeast(u32),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Score {
     pub han: u8,
     pub fu: u8,
     oya: bool,
     tsumo: bool,
+    /// the han come from a yakuman, not from counting (kazoe)
+    yakuman: bool,
+    /// how many yakuman the hand has, 0 counts as one
+    #[serde(default)]
+    yakuman_count: u8,
+    kiriage_mangan: bool,
+    kazoe_limit: KazoeLimit,
+    #[serde(default)]
+    multiple_yakuman: bool,
 }
 
 impl Score {
+    /// Score with the default rules
     pub fn new(han: u8, fu: u8, oya: bool, tsumo: bool) -> Score {
+        Score::with_rules(han, fu, oya, tsumo, &Rules::default())
+    }
+
+    /// Score that honors kiriage mangan and the kazoe limit of these rules
+    pub fn with_rules(han: u8, fu: u8, oya: bool, tsumo: bool, rules: &Rules) -> Score {
         let mut new_fu = fu;
         if fu != 25 {
             new_fu = ((fu as f32 / 10f32).ceil() * 10f32) as u8;
@@ -18,174 +43,213 @@ impl Score {
             fu: new_fu,
             oya,
             tsumo,
+            yakuman: false,
+            yakuman_count: 0,
+            kiriage_mangan: rules.kiriage_mangan,
+            kazoe_limit: rules.kazoe_limit,
+            multiple_yakuman: rules.multiple_yakuman,
+        }
+    }
+
+    pub fn set_yakuman(&mut self, yakuman: bool) {
+        self.yakuman = yakuman;
+    }
+
+    pub fn is_yakuman(&self) -> bool {
+        self.yakuman
+    }
+
+    /// Number of yakuman in the hand, they add up when the rules count multiple yakuman
+    pub fn set_yakuman_count(&mut self, count: u8) {
+        self.yakuman_count = count;
+    }
+
+    /// How many yakuman this score is worth - 0 for other hands, 1 when multiple yakuman don't add up
+    pub fn get_yakuman_count(&self) -> u8 {
+        match (self.yakuman, self.multiple_yakuman) {
+            (false, _) => 0,
+            (true, false) => 1,
+            (true, true) => self.yakuman_count.max(1),
         }
     }
 
     /// Finds the first han + fu combination that reaches at least the value of points given.
     pub fn from_points(points: u32, oya: bool, tsumo: bool, fu_limit: u8) -> Option<Vec<Score>> {
-        let base_points: f32 = if oya {
-            points as f32 / 6f32
-        } else {
-            points as f32 / 4f32
-        };
-
-        //        println!("base points {}", base_points);
+        Score::from_points_with_rules(points, oya, tsumo, fu_limit, &Rules::default())
+    }
 
-        // 8000 is base for yakuman - if it's more, this score can't be reached by a hand
-        // TODO double yakumans?
-        if base_points > 8000f32 {
-            return None;
+    /// Same as from_points, with kiriage mangan and the kazoe limit of these rules.
+    /// Returns the cheapest scores that reach the points, with the lowest fu for each han.
+    pub fn from_points_with_rules(
+        points: u32,
+        oya: bool,
+        tsumo: bool,
+        fu_limit: u8,
+        rules: &Rules,
+    ) -> Option<Vec<Score>> {
+        let candidates: Vec<Score> =
+            Score::all_from_points(PointsTarget::AtLeast(points), oya, tsumo, 0, rules)
+                .into_iter()
+                .filter(|score| score.fu <= fu_limit)
+                .collect();
+
+        let cheapest = candidates.iter().map(|score| score.total_points()).min()?;
+        let mut scores: Vec<Score> = vec![];
+
+        for score in candidates {
+            if score.total_points() == cheapest && !scores.iter().any(|s| s.han == score.han) {
+                scores.push(score);
+            }
         }
 
+        Some(scores)
+    }
+
+    /// Every han + fu combination that reaches the target, including honba
+    /// (the honba points of the rules). Sorted by han, then fu.
+    /// Up to 4 han every fu is listed, even when it's already mangan. From 5 han on, every han is listed once, with 0 fu.
+    pub fn all_from_points(
+        target: PointsTarget,
+        oya: bool,
+        tsumo: bool,
+        honba: u8,
+        rules: &Rules,
+    ) -> Vec<Score> {
+        Score::all_combinations(oya, tsumo, rules)
+            .into_iter()
+            .filter(|score| {
+                let points = score.total_points_with_honba(honba, rules);
+                match target {
+                    PointsTarget::Exactly(target) => points == target,
+                    PointsTarget::AtLeast(target) => points >= target,
+                }
+            })
+            .collect()
+    }
+
+    /// All han + fu combinations a hand can have
+    fn all_combinations(oya: bool, tsumo: bool, rules: &Rules) -> Vec<Score> {
         let mut scores = vec![];
 
-        if base_points > 6000f32 {
-            scores.push(Score::new(13, 0, oya, tsumo));
-            return Some(scores);
-        }
+        for han in 1..=4 {
+            let mut fus = vec![];
 
-        if base_points > 4000f32 {
-            scores.push(Score::new(11, 0, oya, tsumo));
-            scores.push(Score::new(12, 0, oya, tsumo));
-            return Some(scores);
-        }
+            // 20 fu is pinfu tsumo, 25 fu is chiitoitsu (+ menzen tsumo with tsumo)
+            if han >= 2 && tsumo {
+                fus.push(20);
+            }
+            if han >= 2 && !tsumo || han >= 3 {
+                fus.push(25);
+            }
+            fus.extend((30..=110).step_by(10));
 
-        if base_points > 3000f32 {
-            scores.push(Score::new(8, 0, oya, tsumo));
-            scores.push(Score::new(9, 0, oya, tsumo));
-            scores.push(Score::new(10, 0, oya, tsumo));
-            return Some(scores);
+            for fu in fus {
+                scores.push(Score::with_rules(han, fu, oya, tsumo, rules));
+            }
         }
 
-        if base_points > 2000f32 {
-            scores.push(Score::new(6, 0, oya, tsumo));
-            scores.push(Score::new(7, 0, oya, tsumo));
-            return Some(scores);
+        for han in 5..=13 {
+            scores.push(Score::with_rules(han, 0, oya, tsumo, rules));
         }
 
-        if base_points > 1920f32 {
-            if fu_limit > 60 {
-                scores.push(Score::new(3, 70, oya, tsumo));
-            }
-            scores.push(Score::new(4, 40, oya, tsumo));
-            scores.push(Score::new(5, 0, oya, tsumo));
-            return Some(scores);
+        // kazoe is not enough for yakuman
+        if rules.kazoe_limit == KazoeLimit::Sanbaiman {
+            let mut yakuman = Score::with_rules(13, 0, oya, tsumo, rules);
+            yakuman.set_yakuman(true);
+            scores.push(yakuman);
         }
 
-        let mut done = false;
-        for mut han in 1..4 {
-            let mut fu = 20;
-
-            // 1 han hands and rons can't have 20 fu
-            if han == 1 || !tsumo {
-                fu = 30;
-            }
+        scores
+    }
 
-            while fu <= fu_limit {
-                let s = Score::new(han, fu, oya, tsumo);
-                if s.total_points() >= points {
-                    // found!
-                    scores.push(s);
-                    done = true;
-
-                    loop {
-                        han += 1;
-                        fu /= 2;
-                        if fu >= 20 && (fu == 25 || fu as f32 % 10f32 == 0f32) {
-                            // fu 20 can only be achieved with a tsumo
-                            if fu == 20 && !tsumo {
-                                break;
-                            }
-
-                            scores.push(Score::new(han, fu, oya, tsumo));
-
-                            if fu == 25 {
-                                break;
-                            }
-                        } else {
-                            break;
-                        }
-                    }
-
-                    break;
-                }
+    /// Total points including honba, with the honba points of these rules.
+    /// A sanma tsumo is only paid by two players.
+    pub fn total_points_with_honba(&self, honba: u8, rules: &Rules) -> u32 {
+        let points = if rules.sanma && self.tsumo {
+            self.sanma_tsumo_points(rules.tsumo_loss)
+        } else {
+            self.total_points()
+        };
 
-                fu += 10;
-            }
+        points + rules.honba_points(self.tsumo) * honba as u32
+    }
 
-            if done {
-                break;
-            }
-        }
+    /// Total points of a tsumo in sanma, where the empty seat doesn't pay.
+    /// Without tsumo loss both paying players add their share of the missing payment.
+    pub fn sanma_tsumo_points(&self, tsumo_loss: bool) -> u32 {
+        let paid = if self.oya {
+            2 * self.points_from_ko()
+        } else {
+            self.points_from_oya() + self.points_from_ko()
+        };
 
-        if !scores.is_empty() {
-            return Some(scores);
+        if tsumo_loss {
+            paid
+        } else {
+            paid + 2 * self.sanma_missing_share()
         }
+    }
 
-        None
+    /// What each paying player adds for the empty seat on a sanma tsumo without tsumo loss:
+    /// half of a non-dealer payment, rounded up to 100
+    pub fn sanma_missing_share(&self) -> u32 {
+        self.points_from_ko().div_ceil(200) * 100
     }
 
     /// Returns the base points of this Score. See http://arcturus.su/wiki/Japanese_mahjong_scoring_rules#Scoring_procedure
     fn base_points(&self) -> u32 {
-        if self.han == 5 || (self.han == 3 && self.fu >= 70) || (self.han == 4 && self.fu >= 40) {
-            // mangan
-            return 2000;
-        } else if self.han >= 6 && self.han <= 7 {
-            // haneman
-            return 3000;
-        } else if self.han >= 8 && self.han <= 10 {
-            // baiman
-            return 4000;
-        } else if self.han >= 11 && self.han <= 12 {
+        match self.limit_base_points() {
+            Some(base_points) => base_points,
+            None => self.fu as u32 * (2u32.pow(2u32 + self.han as u32)),
+        }
+    }
+
+    /// Base points of mangan and above, None for hands that are counted by han and fu
+    fn limit_base_points(&self) -> Option<u32> {
+        if self.yakuman {
+            return Some(8000 * self.get_yakuman_count() as u32);
+        }
+
+        if self.han >= 13 {
+            // kazoe yakuman
+            return match self.kazoe_limit {
+                KazoeLimit::Sanbaiman => Some(6000),
+                KazoeLimit::Yakuman => Some(8000),
+            };
+        } else if self.han >= 11 {
             // sanbaiman
-            return 6000;
-        } else if self.han >= 13 {
-            // yakuman
-            // TODO double yakuman?
-            return 8000;
+            return Some(6000);
+        } else if self.han >= 8 {
+            // baiman
+            return Some(4000);
+        } else if self.han >= 6 {
+            // haneman
+            return Some(3000);
+        } else if self.han == 5
+            || (self.han == 3 && self.fu >= 70)
+            || (self.han == 4 && self.fu >= 40)
+        {
+            // mangan
+            return Some(2000);
+        } else if self.kiriage_mangan
+            && ((self.han == 3 && self.fu == 60) || (self.han == 4 && self.fu == 30))
+        {
+            // kiriage mangan - 4 han 30 fu and 3 han 60 fu are rounded up to mangan
+            return Some(2000);
         }
 
-        self.fu as u32 * (2u32.pow(2u32 + self.han as u32))
+        None
     }
 
     /// Returns total points that will be distributed from this Score
     pub fn total_points(&self) -> u32 {
         let base_points = self.base_points();
 
-        if self.han == 5 || (self.han == 3 && self.fu >= 70) || (self.han == 4 && self.fu >= 40) {
-            // mangan
-            if self.oya {
-                12000
-            } else {
-                8000
-            }
-        } else if self.han >= 6 && self.han <= 7 {
-            // haneman
-            if self.oya {
-                18000
-            } else {
-                12000
-            }
-        } else if self.han >= 8 && self.han <= 10 {
-            // baiman
-            if self.oya {
-                24000
-            } else {
-                16000
-            }
-        } else if self.han >= 11 && self.han <= 12 {
-            // sanbaiman
-            if self.oya {
-                36000
-            } else {
-                24000
-            }
-        } else if self.han >= 13 {
-            // yakuman
+        if self.limit_base_points().is_some() {
             if self.oya {
-                48000
+                base_points * 6
             } else {
-                32000
+                base_points * 4
             }
         } else if self.oya {
             if self.tsumo {
@@ -426,4 +490,148 @@ mod tests {
         assert_eq!(score.han, 1);
         assert_eq!(score.fu, 30);
     }
+
+    fn kiriage_rules() -> Rules {
+        Rules {
+            kiriage_mangan: true,
+            ..Rules::default()
+        }
+    }
+
+    #[test]
+    fn kiriage_mangan() {
+        let rules = kiriage_rules();
+
+        let score = Score::with_rules(4, 30, false, false, &rules);
+        assert_eq!(score.total_points(), 8000);
+
+        let score = Score::with_rules(3, 60, true, true, &rules);
+        assert_eq!(score.total_points(), 12000);
+        assert_eq!(score.points_from_ko(), 4000);
+
+        // 4 han 25 fu stays below mangan
+        let score = Score::with_rules(4, 25, false, false, &rules);
+        assert_eq!(score.total_points(), 6400);
+    }
+
+    #[test]
+    fn kazoe_limit() {
+        let sanbaiman = Rules {
+            kazoe_limit: KazoeLimit::Sanbaiman,
+            ..Rules::default()
+        };
+
+        let score = Score::new(13, 0, false, false);
+        assert_eq!(score.total_points(), 32000);
+
+        let mut score = Score::with_rules(13, 0, false, true, &sanbaiman);
+        assert_eq!(score.total_points(), 24000);
+        assert_eq!(score.points_from_oya(), 12000);
+
+        score.set_yakuman(true);
+        assert_eq!(score.total_points(), 32000);
+        assert_eq!(score.points_from_oya(), 16000);
+    }
+
+    #[test]
+    fn multiple_yakuman() {
+        let mut score = Score::new(13, 0, true, true);
+        score.set_yakuman(true);
+        score.set_yakuman_count(2);
+        assert_eq!(score.get_yakuman_count(), 2);
+        assert_eq!(score.total_points(), 96000);
+        assert_eq!(score.points_from_ko(), 32000);
+
+        let mut score = Score::with_rules(13, 0, false, false, &Rules::m_league());
+        score.set_yakuman(true);
+        score.set_yakuman_count(3);
+        assert_eq!(score.get_yakuman_count(), 1);
+        assert_eq!(score.total_points(), 32000);
+
+        // kazoe yakuman is never more than one
+        let score = Score::new(26, 0, false, false);
+        assert_eq!(score.get_yakuman_count(), 0);
+        assert_eq!(score.total_points(), 32000);
+    }
+
+    #[test]
+    fn from_points_7700_kiriage() {
+        let scores =
+            Score::from_points_with_rules(7700, false, false, 60, &kiriage_rules()).unwrap();
+        assert_eq!(scores.len(), 3);
+        assert_eq!(scores[0].han, 3);
+        assert_eq!(scores[0].fu, 60);
+        assert_eq!(scores[0].total_points(), 8000);
+        assert_eq!(scores[1].han, 4);
+        assert_eq!(scores[1].fu, 30);
+    }
+
+    #[test]
+    fn all_from_points_exactly() {
+        let rules = Rules::default();
+        let scores = Score::all_from_points(PointsTarget::Exactly(2000), false, false, 0, &rules);
+        let combos: Vec<(u8, u8)> = scores.iter().map(|s| (s.han, s.fu)).collect();
+
+        assert_eq!(combos, vec![(1, 60), (2, 30)]);
+
+        // 2000 all for the dealer, + 100 all for the honba
+        let scores = Score::all_from_points(PointsTarget::Exactly(6300), true, true, 1, &rules);
+        let combos: Vec<(u8, u8)> = scores.iter().map(|s| (s.han, s.fu)).collect();
+
+        assert_eq!(combos, vec![(2, 60), (3, 30)]);
+
+        // in sanma only two players pay the tsumo and its honba: 2000 x 2 + 200
+        let mut sanma = Rules {
+            sanma: true,
+            ..Rules::default()
+        };
+        let scores = Score::all_from_points(PointsTarget::Exactly(4200), true, true, 1, &sanma);
+        let combos: Vec<(u8, u8)> = scores.iter().map(|s| (s.han, s.fu)).collect();
+
+        assert_eq!(combos, vec![(2, 60), (3, 30)]);
+
+        // without tsumo loss they split the missing 2000 as well
+        sanma.tsumo_loss = false;
+        let scores = Score::all_from_points(PointsTarget::Exactly(6200), true, true, 1, &sanma);
+        let combos: Vec<(u8, u8)> = scores.iter().map(|s| (s.han, s.fu)).collect();
+
+        assert_eq!(combos, vec![(2, 60), (3, 30)]);
+
+        // mangan below 5 han is listed with its fu
+        let scores = Score::all_from_points(PointsTarget::Exactly(8000), false, false, 0, &rules);
+        let combos: Vec<(u8, u8)> = scores.iter().map(|s| (s.han, s.fu)).collect();
+
+        assert_eq!(combos[0], (3, 70));
+        assert!(combos.contains(&(4, 40)));
+        assert_eq!(combos.last(), Some(&(5, 0)));
+    }
+
+    #[test]
+    fn all_from_points_at_least() {
+        let rules = Rules::default();
+        let scores = Score::all_from_points(PointsTarget::AtLeast(32000), false, false, 0, &rules);
+        assert_eq!(scores.len(), 1);
+        assert_eq!(scores[0].han, 13);
+
+        // 3 han 50 fu is 6400, so it needs two honba to reach 7000
+        let has_3_50 = |honba: u8| {
+            Score::all_from_points(PointsTarget::AtLeast(7000), false, false, honba, &rules)
+                .iter()
+                .any(|s| s.han == 3 && s.fu == 50)
+        };
+        assert!(!has_3_50(1));
+        assert!(has_3_50(2));
+    }
+
+    #[test]
+    fn serde_score() {
+        let mut score = Score::with_rules(13, 0, true, false, &Rules::tenhou());
+        score.set_yakuman(true);
+
+        let json = serde_json::to_string(&score).unwrap();
+        let back: Score = serde_json::from_str(&json).unwrap();
+
+        assert!(back.is_yakuman());
+        assert_eq!(back.total_points(), 48000);
+    }
 }
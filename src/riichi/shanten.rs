@@ -1,6 +1,6 @@
 use super::hand::Hand;
 use super::tile::Tile;
-use crate::riichi::riichi_error::RiichiError;
+use crate::riichi::riichi_error::{RiichiError, ValidationError};
 
 pub struct ShantenFinder {
     pairs: i8,
@@ -20,7 +20,7 @@ impl ShantenFinder {
 
     pub fn shanten(&mut self, hand: &mut Hand) -> Result<i8, RiichiError> {
         if !hand.validate() {
-            return Err(RiichiError::new(101, "Invalid hand"));
+            return Err(ValidationError::ShantenHand.into());
         }
         self.hand_count = hand.count_tiles();
 
@@ -1,7 +1,8 @@
 use super::tile::Tile;
-use crate::riichi::riichi_error::RiichiError;
+use crate::riichi::riichi_error::{RiichiError, ValidationError};
 use crate::riichi::tile::TileType::{Dragon, Number, Wind};
 use core::fmt;
+use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 use wasm_bindgen::__rt::core::fmt::Formatter;
 
@@ -10,26 +11,26 @@ use wasm_bindgen::__rt::core::fmt::Formatter;
 /// Exceptions are for example 23456 wait, where you can either have 234 (complete) & 56 (incomplete), or 23 (incomplete) and 456 (complete)
 /// Or, shanpon wait (1155 = 11 pair 55 incomplete, or 11 incomplete 55 pair)
 /// Or, 13-sided kokushi, or 9-sided nine gates
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Shape {
     shape_type: ShapeType,
     tile_count: u8,
     is_open: bool,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum ShapeType {
     Complete(CompleteShape),
     Incomplete(ClosedShape, Tile),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum CompleteShape {
     Closed(ClosedShape),
     Open(OpenShape),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum ClosedShape {
     // meld
     Shuntsu([Tile; 3]),
@@ -42,14 +43,14 @@ pub enum ClosedShape {
     Single(Tile),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum OpenShape {
     Chi([Tile; 3]),
     Pon([Tile; 3]),
     Kan(OpenKan),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum OpenKan {
     // kan opened by a call
     Daiminkan([Tile; 4]),
@@ -57,6 +58,28 @@ pub enum OpenKan {
     Shouminkan([Tile; 4]),
 }
 
+impl ClosedShape {
+    pub fn get_tiles(&self) -> Vec<Tile> {
+        match self {
+            ClosedShape::Shuntsu(tiles) | ClosedShape::Koutsu(tiles) => tiles.to_vec(),
+            ClosedShape::Kantsu(tiles) => tiles.to_vec(),
+            ClosedShape::Toitsu(tiles) => tiles.to_vec(),
+            ClosedShape::Single(tile) => vec![*tile],
+        }
+    }
+}
+
+impl OpenShape {
+    /// All tiles of this shape, including the called one
+    pub fn get_tiles(&self) -> Vec<Tile> {
+        match self {
+            OpenShape::Chi(tiles) | OpenShape::Pon(tiles) => tiles.to_vec(),
+            OpenShape::Kan(OpenKan::Daiminkan(tiles))
+            | OpenShape::Kan(OpenKan::Shouminkan(tiles)) => tiles.to_vec(),
+        }
+    }
+}
+
 impl Shape {
     pub fn new(shape_type: ShapeType, tile_count: u8, is_open: bool) -> Shape {
         Shape {
@@ -86,10 +109,7 @@ impl Shape {
 
         if !(1..=4).contains(&tile_count) {
             // 4 = kan?
-            return Err(RiichiError::new(
-                120,
-                "Not a valid shape - wrong tile count",
-            ));
+            return Err(ValidationError::ShapeTileCount.into());
         }
 
         // is this a valid shape?
@@ -99,10 +119,7 @@ impl Shape {
                     tiles.get(i).unwrap().to_id(),
                     tiles.get(i + 1).unwrap().to_id(),
                 ) {
-                    return Err(RiichiError::new(
-                        121,
-                        "Not a valid shape - tiles are not relevant to each other",
-                    ));
+                    return Err(ValidationError::ShapeUnrelatedTiles.into());
                 }
             } else {
                 break;
@@ -163,7 +180,7 @@ impl Shape {
             }
         }
 
-        Err(RiichiError::new(124, "No suitable shape found"))
+        Err(ValidationError::NoShape.into())
     }
 
     fn _koutsu_shape_type(
@@ -187,7 +204,7 @@ impl Shape {
             return Result::Ok(Shape::new(shape_type, 3, is_open));
         }
 
-        Err(RiichiError::new(122, "Bad shape"))
+        Err(ValidationError::BadShape.into())
     }
 
     /// Are these two tiles in a shape together?
@@ -1,20 +1,47 @@
 use crate::riichi::hand::Hand;
-use crate::riichi::riichi_error::RiichiError;
-use crate::riichi::rules::Rules;
-use crate::riichi::scores::Score;
-use crate::riichi::shapes::Shape;
+use crate::riichi::riichi_error::{ParseError, RiichiError, ValidationError};
+use crate::riichi::rules::{Rules, TieBreak};
+use crate::riichi::scores::{PointsTarget, Score};
+use crate::riichi::shapes::{ClosedShape, CompleteShape, OpenKan, OpenShape, Shape, ShapeType};
 use crate::riichi::tile::Tile;
 use crate::riichi::yaku::{Yaku, YakuFinder};
 use serde_json::{Map, Value};
 
+/// Result of an exhaustive draw (ryuukyoku).
+/// Players are relative, 0 = me, 1 = shimocha, 2 = toimen, 3 = kamicha.
+#[derive(Debug, Clone)]
+pub struct ExhaustiveDrawResult {
+    pub tenpai: [bool; 4],
+    pub nagashi: [bool; 4],
+    /// noten payments, or nagashi mangan payments instead of them
+    pub payments: [i32; 4],
+    pub dealer_repeats: bool,
+}
+
+/// The cheapest win that gets a player to a placing (or better) in the last round.
+/// Players are relative, 0 = me, 1 = shimocha, 2 = toimen, 3 = kamicha.
+#[derive(Debug, Clone)]
+pub struct PlacementCondition {
+    pub player: u8,
+    pub placing: u8,
+    /// None for tsumo, or the player who deals in
+    pub from: Option<u8>,
+    /// None if no hand is enough
+    pub score: Option<Score>,
+}
+
 /// Representation of the game state
+#[derive(Clone)]
 pub struct Table {
     my_hand: Option<Hand>,
     my_discards: Vec<Tile>,
     my_riichi: Option<bool>,
+    my_double_riichi: Option<bool>,
+    my_open_riichi: Option<bool>,
     my_tsumo: Option<bool>,
     my_points: Option<i32>,
     // player to the right (shimocha)
+    p1_hand: Option<Hand>,
     p1_discards: Vec<Tile>,
     p1_safe_tiles: Vec<Tile>,
     p1_open_tiles: Vec<Shape>,
@@ -22,6 +49,7 @@ pub struct Table {
     p1_tsumo: Option<bool>,
     p1_points: Option<i32>,
     // opposite player (toimen)
+    p2_hand: Option<Hand>,
     p2_discards: Vec<Tile>,
     p2_safe_tiles: Vec<Tile>,
     p2_open_tiles: Vec<Shape>,
@@ -29,6 +57,7 @@ pub struct Table {
     p2_tsumo: Option<bool>,
     p2_points: Option<i32>,
     // player to the left (kamicha)
+    p3_hand: Option<Hand>,
     p3_discards: Vec<Tile>,
     p3_safe_tiles: Vec<Tile>,
     p3_open_tiles: Vec<Shape>,
@@ -37,6 +66,8 @@ pub struct Table {
     p3_points: Option<i32>,
 
     riichi_declaring_player: Option<u8>,
+    /// player who called a kan and hasn't discarded yet
+    kan_declaring_player: Option<u8>,
 
     // 1 = east, 2 = south, 3 = west, 4 = north
     prevalent_wind: Option<u8>,
@@ -46,6 +77,8 @@ pub struct Table {
     dealer_turn: Option<u8>,
     total_round: Option<u8>,
     tiles_remaining: Option<u8>,
+    /// consecutive wins of the current dealer before this hand
+    renchan: Option<u8>,
 
     riichi_sticks_in_pot: Option<u8>,
     tsumibo: Option<u8>,
@@ -57,25 +90,40 @@ pub struct Table {
 }
 
 impl Table {
+    /// Table from a JSON object with the keys of from_map
+    pub fn from_json(json: &str) -> Result<Table, RiichiError> {
+        let params: Map<String, Value> = serde_json::from_str(json)
+            .map_err(|error| RiichiError::from(ParseError::Json(error.to_string())))?;
+
+        Table::from_map(&params)
+    }
+
+    /// Table from JSON-like parameters: my_hand, my_riichi, my_tsumo, p1_riichi - p3_riichi,
+    /// p1_discards - p3_discards and dora_indicators (tiles in mpsz), prevalent_wind and my_seat_wind.
     pub fn from_map(params: &Map<String, Value>) -> Result<Table, RiichiError> {
         let mut t = Table {
             my_hand: None,
             my_discards: vec![],
             my_riichi: None,
+            my_double_riichi: None,
+            my_open_riichi: None,
             my_tsumo: None,
             my_points: None,
+            p1_hand: None,
             p1_discards: vec![],
             p1_safe_tiles: vec![],
             p1_open_tiles: vec![],
             p1_riichi: None,
             p1_tsumo: None,
             p1_points: None,
+            p2_hand: None,
             p2_discards: vec![],
             p2_safe_tiles: vec![],
             p2_open_tiles: vec![],
             p2_riichi: None,
             p2_tsumo: None,
             p2_points: None,
+            p3_hand: None,
             p3_discards: vec![],
             p3_safe_tiles: vec![],
             p3_open_tiles: vec![],
@@ -83,12 +131,14 @@ impl Table {
             p3_tsumo: None,
             p3_points: None,
             riichi_declaring_player: None,
+            kan_declaring_player: None,
             prevalent_wind: None,
             my_seat_wind: None,
             my_initial_seat_wind: None,
             dealer_turn: None,
             total_round: None,
             tiles_remaining: None,
+            renchan: None,
             riichi_sticks_in_pot: None,
             tsumibo: None,
             dora_indicators: vec![],
@@ -123,6 +173,24 @@ impl Table {
                     }
                     _ => (),
                 }
+            } else if let Some(player) = Table::opponent_key(index, "_riichi") {
+                if let Value::Bool(b) = value {
+                    match player {
+                        1 => t.p1_riichi = Some(*b),
+                        2 => t.p2_riichi = Some(*b),
+                        _ => t.p3_riichi = Some(*b),
+                    }
+                }
+            } else if let Some(player) = Table::opponent_key(index, "_discards") {
+                if let Value::String(s) = value {
+                    for tile in Tile::list_from_text(s)? {
+                        t.add_tile_to_discards(player, tile)?;
+                    }
+                }
+            } else if index.eq(&String::from("dora_indicators")) {
+                if let Value::String(s) = value {
+                    t.dora_indicators = Tile::list_from_text(s)?;
+                }
             } else if index.eq(&String::from("my_seat_wind")) {
                 match value {
                     Value::Number(v) => {
@@ -140,6 +208,16 @@ impl Table {
         Ok(t)
     }
 
+    /// Player 1 - 3 from keys like p2_discards
+    fn opponent_key(key: &str, suffix: &str) -> Option<u8> {
+        match key.strip_suffix(suffix)? {
+            "p1" => Some(1),
+            "p2" => Some(2),
+            "p3" => Some(3),
+            _ => None,
+        }
+    }
+
     pub fn set_seat(&mut self, seat: u8) {
         self.my_seat_wind = Some(seat);
     }
@@ -220,6 +298,42 @@ impl Table {
         }
     }
 
+    /// Double riichi also counts as riichi
+    pub fn set_my_double_riichi(&mut self, value: bool) {
+        self.my_double_riichi = Some(value);
+        if value {
+            self.my_riichi = Some(true);
+        }
+    }
+
+    pub fn did_i_double_riichi(&self) -> bool {
+        self.my_double_riichi.unwrap_or(false)
+    }
+
+    /// Open riichi also counts as riichi
+    pub fn set_my_open_riichi(&mut self, value: bool) {
+        self.my_open_riichi = Some(value);
+        if value {
+            self.my_riichi = Some(true);
+        }
+    }
+
+    pub fn did_i_open_riichi(&self) -> bool {
+        self.my_open_riichi.unwrap_or(false)
+    }
+
+    pub fn set_kan_declaring_player(&mut self, player_id: u8) {
+        self.kan_declaring_player = Some(player_id);
+    }
+
+    pub fn unset_kan_declaring_player(&mut self) {
+        self.kan_declaring_player = None;
+    }
+
+    pub fn get_kan_declaring_player(&self) -> Option<u8> {
+        self.kan_declaring_player
+    }
+
     pub fn set_my_hand(&mut self, hand: Hand) {
         self.my_hand = Some(hand);
     }
@@ -235,6 +349,31 @@ impl Table {
         &self.my_hand
     }
 
+    /// Sets the hand of any player, for when all hands are known (replays, simulations).
+    /// 0 = me, 1 = shimocha, 2 = toimen, 3 = kamicha
+    pub fn set_hand(&mut self, player: u8, hand: Hand) -> Result<(), RiichiError> {
+        match player {
+            0 => self.my_hand = Some(hand),
+            1 => self.p1_hand = Some(hand),
+            2 => self.p2_hand = Some(hand),
+            3 => self.p3_hand = Some(hand),
+            _ => return Err(ValidationError::PlayerId(player).into()),
+        }
+
+        Ok(())
+    }
+
+    /// None if the hand is not known, or there is no such player
+    pub fn get_hand(&self, player: u8) -> Option<&Hand> {
+        match player {
+            0 => self.my_hand.as_ref(),
+            1 => self.p1_hand.as_ref(),
+            2 => self.p2_hand.as_ref(),
+            3 => self.p3_hand.as_ref(),
+            _ => None,
+        }
+    }
+
     pub fn get_my_winning_tile(&self) -> Tile {
         match &self.my_hand {
             None => panic!("No drawn tile in hand!"),
@@ -261,6 +400,14 @@ impl Table {
         }
     }
 
+    pub fn set_renchan(&mut self, value: u8) {
+        self.renchan = Some(value);
+    }
+
+    pub fn get_renchan(&self) -> u8 {
+        self.renchan.unwrap_or(0)
+    }
+
     pub fn set_my_seat_wind(&mut self, value: u8) {
         self.my_seat_wind = Some(value);
     }
@@ -293,6 +440,28 @@ impl Table {
         self.dealer_turn
     }
 
+    /// Three-player mahjong, based on the rules
+    pub fn is_sanma(&self) -> bool {
+        match &self.rules {
+            None => false,
+            Some(rules) => rules.sanma,
+        }
+    }
+
+    /// In sanma, the relative position of the missing north player
+    pub fn get_empty_seat(&self) -> Option<u8> {
+        if !self.is_sanma() {
+            return None;
+        }
+
+        Some((4 - self.my_seat_wind.unwrap_or(1)) % 4)
+    }
+
+    /// Relative position of the dealer, based on my seat wind
+    pub fn get_dealer(&self) -> u8 {
+        (5 - self.my_seat_wind.unwrap_or(1)) % 4
+    }
+
     pub fn set_points(&mut self, player: u8, value: i32) {
         match player {
             0 => self.my_points = Some(value),
@@ -308,28 +477,103 @@ impl Table {
     }
 
     pub fn get_placing(&self) -> u8 {
-        // TODO: account for different tie-breaking rules
-        let initial_seat_winds = match self.my_initial_seat_wind {
-            Some(1) => vec![1, 2, 3, 4],
-            Some(2) => vec![2, 3, 4, 1],
-            Some(3) => vec![3, 4, 1, 2],
-            _ => vec![4, 1, 2, 3],
-        };
-        let points = vec![
+        self.placing_with_points(0, &self.all_points())
+    }
+
+    /// Points of all players, 25000 if unknown
+    fn all_points(&self) -> [i32; 4] {
+        [
             self.my_points.unwrap_or(25000),
             self.p1_points.unwrap_or(25000),
             self.p2_points.unwrap_or(25000),
             self.p3_points.unwrap_or(25000),
-        ];
-        let mut all_scores: Vec<_> = (points.iter()).zip(initial_seat_winds.iter()).collect();
-        // sort from lowest to hightest
-        all_scores.sort_by(|a, b| b.0.cmp(a.0).then(a.1.cmp(b.1)));
+        ]
+    }
+
+    /// Initial seat wind of a player, used for tie-breaking by seat order
+    fn initial_seat_wind(&self, player: u8) -> u8 {
+        (self.my_initial_seat_wind.unwrap_or(4) + player - 1) % 4 + 1
+    }
+
+    /// Placing of a player with these points, ties are broken based on the rules
+    fn placing_with_points(&self, player: u8, points: &[i32; 4]) -> u8 {
+        let empty_seat = self.get_empty_seat();
+        let tie_break = self.rules_or_default().tie_break;
+        let mine = points[player as usize];
+
+        let above = (0..4u8)
+            .filter(|other| *other != player && Some(*other) != empty_seat)
+            .filter(|other| {
+                let theirs = points[*other as usize];
+                theirs > mine
+                    || (theirs == mine
+                        && tie_break == TieBreak::SeatOrder
+                        && self.initial_seat_wind(*other) < self.initial_seat_wind(player))
+            })
+            .count();
 
-        all_scores
+        above as u8 + 1
+    }
+
+    /// Points of all players after this win, with honba and riichi sticks on the table.
+    /// from is None for tsumo, or the player who dealt in.
+    pub fn points_after_win(&self, winner: u8, from: Option<u8>, score: &Score) -> [i32; 4] {
+        let mut points = self.all_points();
+
+        for (player, payment) in self
+            .payments_for(winner, &[], score, from)
             .iter()
-            .position(|score| *score.1 == self.my_initial_seat_wind.unwrap_or(4))
-            .unwrap_or(4) as u8
-            + 1
+            .enumerate()
+        {
+            points[player] += payment;
+        }
+
+        points[winner as usize] += 1000 * self.get_riichi_sticks() as i32;
+
+        points
+    }
+
+    /// All Last conditions - the cheapest hand every player needs for every placing,
+    /// by tsumo and by ron off each opponent.
+    pub fn placement_conditions(&self) -> Vec<PlacementCondition> {
+        let rules = self.rules_or_default();
+        let dealer = self.get_dealer();
+        let empty_seat = self.get_empty_seat();
+        let players: Vec<u8> = (0..4).filter(|p| Some(*p) != empty_seat).collect();
+        let mut conditions = vec![];
+
+        for winner in players.iter() {
+            let mut wins = vec![None];
+            wins.extend(players.iter().filter(|p| *p != winner).map(|p| Some(*p)));
+
+            for from in wins {
+                let mut scores = Score::all_from_points(
+                    PointsTarget::AtLeast(0),
+                    *winner == dealer,
+                    from.is_none(),
+                    0,
+                    &rules,
+                );
+                // cheapest first, lower han first for the same points
+                scores.sort_by_key(|score| score.total_points());
+
+                for placing in 1..=players.len() as u8 {
+                    let score = scores.iter().find(|score| {
+                        let points = self.points_after_win(*winner, from, score);
+                        self.placing_with_points(*winner, &points) <= placing
+                    });
+
+                    conditions.push(PlacementCondition {
+                        player: *winner,
+                        placing,
+                        from,
+                        score: score.cloned(),
+                    });
+                }
+            }
+        }
+
+        conditions
     }
 
     pub fn set_dora_indicators(&mut self, indicators: Vec<Tile>) {
@@ -345,6 +589,37 @@ impl Table {
         &self.dora_indicators
     }
 
+    /// Dora in my hand: tiles after the dora indicators and red fives (kita are counted by the hand).
+    /// In sanma the dora after a 1m indicator is 9m.
+    pub fn count_my_dora(&self) -> u8 {
+        let hand = match &self.my_hand {
+            None => return 0,
+            Some(hand) => hand,
+        };
+
+        let doras: Vec<Tile> = self
+            .dora_indicators
+            .iter()
+            .filter_map(|indicator| {
+                if hand.is_sanma() && indicator.to_string() == "1m" {
+                    return Tile::from_text("9m").ok();
+                }
+
+                indicator.next(true)
+            })
+            .collect();
+
+        hand.get_tiles()
+            .iter()
+            .flatten()
+            .map(|tile| {
+                let dora = doras.iter().filter(|d| d.to_id() == tile.to_id()).count() as u8;
+
+                dora + tile.is_red as u8
+            })
+            .sum()
+    }
+
     pub fn add_tile_to_visible_tiles(&mut self, tile: Tile) {
         self.visible_tiles[(tile.to_id() - 1) as usize] += 1;
     }
@@ -400,23 +675,59 @@ impl Table {
         &self.my_discards
     }
 
-    pub fn add_tile_to_discards(&mut self, player: u8, tile: Tile) {
+    pub fn add_tile_to_discards(&mut self, player: u8, tile: Tile) -> Result<(), RiichiError> {
         match player {
             0 => self.my_discards.push(tile),
             1 => self.p1_discards.push(tile),
             2 => self.p2_discards.push(tile),
             3 => self.p3_discards.push(tile),
-            _ => panic!("Invalid player"),
+            _ => return Err(ValidationError::PlayerId(player).into()),
+        }
+
+        Ok(())
+    }
+
+    pub fn get_discards(&self, player: u8) -> Option<&Vec<Tile>> {
+        match player {
+            0 => Some(&self.my_discards),
+            1 => Some(&self.p1_discards),
+            2 => Some(&self.p2_discards),
+            3 => Some(&self.p3_discards),
+            _ => None,
+        }
+    }
+
+    /// Adds a called shape (or a closed kan) of another player
+    pub fn add_open_shape(&mut self, player: u8, shape: Shape) -> Result<(), RiichiError> {
+        match player {
+            1 => self.p1_open_tiles.push(shape),
+            2 => self.p2_open_tiles.push(shape),
+            3 => self.p3_open_tiles.push(shape),
+            _ => return Err(ValidationError::PlayerId(player).into()),
+        }
+
+        Ok(())
+    }
+
+    /// Called shapes of another player, my own are in my hand
+    pub fn get_open_shapes(&self, player: u8) -> Option<&Vec<Shape>> {
+        match player {
+            1 => Some(&self.p1_open_tiles),
+            2 => Some(&self.p2_open_tiles),
+            3 => Some(&self.p3_open_tiles),
+            _ => None,
         }
     }
 
-    pub fn add_tile_to_safe_tiles(&mut self, player: u8, tile: Tile) {
+    pub fn add_tile_to_safe_tiles(&mut self, player: u8, tile: Tile) -> Result<(), RiichiError> {
         match player {
             1 => self.p1_safe_tiles.push(tile),
             2 => self.p2_safe_tiles.push(tile),
             3 => self.p3_safe_tiles.push(tile),
-            _ => panic!("Invalid player"),
+            _ => return Err(ValidationError::PlayerId(player).into()),
         }
+
+        Ok(())
     }
 
     /// Finds yaku based on the table state. Some yaku depend on winds, tsumo / ron, tiles remaining etc.
@@ -425,6 +736,437 @@ impl Table {
         yf.find(self)
     }
 
+    /// Resolves an exhaustive draw (ryuukyoku). Hands of all players have to be known.
+    /// Tenpai players get 3000 points from noten players, unless somebody has nagashi mangan -
+    /// then nagashi mangan is paid like a mangan tsumo instead.
+    pub fn exhaustive_draw(&self) -> Result<ExhaustiveDrawResult, RiichiError> {
+        let dealer = self.get_dealer();
+        let empty_seat = self.get_empty_seat();
+        let mut tenpai = [false; 4];
+        let mut nagashi = [false; 4];
+
+        for player in 0..4 {
+            if Some(player) == empty_seat {
+                continue;
+            }
+
+            let mut hand = match self.get_hand(player) {
+                Some(hand) => hand.clone(),
+                None => return Err(ValidationError::UnknownHand(player).into()),
+            };
+
+            tenpai[player as usize] = hand.count_tiles() == 13 && hand.shanten() == 0;
+            nagashi[player as usize] = self.is_nagashi_mangan(player);
+        }
+
+        let mut payments = [0i32; 4];
+
+        if nagashi.iter().any(|n| *n) {
+            for (winner, _) in nagashi.iter().enumerate().filter(|(_, n)| **n) {
+                let score = Score::new(5, 30, winner as u8 == dealer, true);
+
+                for (player, paid) in self.tsumo_payments(winner as u8, &score).iter().enumerate() {
+                    payments[player] += paid;
+                }
+            }
+        } else {
+            // 3000 points, 2000 in sanma
+            let players = if empty_seat.is_some() { 3 } else { 4 };
+            let pool = 1000 * (players - 1);
+            let tenpai_count = tenpai.iter().filter(|t| **t).count() as i32;
+
+            if tenpai_count > 0 && tenpai_count < players {
+                for (player, payment) in payments.iter_mut().enumerate() {
+                    if Some(player as u8) == empty_seat {
+                        continue;
+                    }
+
+                    *payment = if tenpai[player] {
+                        pool / tenpai_count
+                    } else {
+                        -pool / (players - tenpai_count)
+                    };
+                }
+            }
+        }
+
+        Ok(ExhaustiveDrawResult {
+            tenpai,
+            nagashi,
+            payments,
+            dealer_repeats: tenpai[dealer as usize],
+        })
+    }
+
+    /// Payments of all players for a tsumo of this player with this score, without honba.
+    /// Players are relative, 0 = me. In sanma, the empty seat's share is lost with tsumo loss,
+    /// or split between the two paying players without it.
+    pub fn tsumo_payments(&self, winner: u8, score: &Score) -> [i32; 4] {
+        let dealer = self.get_dealer();
+        let empty_seat = self.get_empty_seat();
+        let mut payments = [0i32; 4];
+        let mut missing = 0;
+
+        for player in 0..4u8 {
+            if player == winner {
+                continue;
+            }
+
+            let paid = if player == dealer {
+                score.points_from_oya()
+            } else {
+                score.points_from_ko()
+            } as i32;
+
+            if Some(player) == empty_seat {
+                missing = paid;
+                continue;
+            }
+
+            payments[player as usize] -= paid;
+            payments[winner as usize] += paid;
+        }
+
+        if missing > 0 && !self.rules_or_default().tsumo_loss {
+            let half = score.sanma_missing_share() as i32;
+
+            for player in 0..4u8 {
+                if player == winner || Some(player) == empty_seat {
+                    continue;
+                }
+
+                payments[player as usize] -= half;
+                payments[winner as usize] += half;
+            }
+        }
+
+        payments
+    }
+
+    /// Sekinin barai - the player (relative) who fed the final meld of my daisangen, daisuushii
+    /// or, if the rules say so, suukantsu. The final set is the last one called, it must be a called meld,
+    /// so there is no pao when the last set came from the closed hand.
+    pub fn get_pao_player(&self, yakus: &[Yaku]) -> Option<u8> {
+        let rules = self.rules_or_default();
+        if !rules.pao {
+            return None;
+        }
+
+        let hand = self.my_hand.as_ref()?;
+        let set_tile = |shape: &CompleteShape| match shape {
+            CompleteShape::Closed(ClosedShape::Kantsu(tiles)) => Some(tiles[0]),
+            CompleteShape::Open(OpenShape::Pon(tiles)) => Some(tiles[0]),
+            CompleteShape::Open(OpenShape::Kan(OpenKan::Daiminkan(tiles)))
+            | CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(tiles))) => Some(tiles[0]),
+            _ => None,
+        };
+
+        for yaku in yakus.iter() {
+            // the fourth kan can be a shouminkan of an earlier pon
+            let shapes = if matches!(yaku, Yaku::Suukantsu) {
+                hand.get_shapes_in_kan_order()
+            } else {
+                hand.get_shapes_in_call_order()
+            };
+
+            let (sets, needed): (Vec<&CompleteShape>, usize) = match yaku {
+                Yaku::Daisangen => (
+                    shapes
+                        .iter()
+                        .filter(|s| set_tile(s).is_some_and(|t| t.is_dragon()))
+                        .collect(),
+                    3,
+                ),
+                Yaku::Daisuushii => (
+                    shapes
+                        .iter()
+                        .filter(|s| set_tile(s).is_some_and(|t| t.is_wind()))
+                        .collect(),
+                    4,
+                ),
+                Yaku::Suukantsu if rules.pao_suukantsu => (
+                    shapes
+                        .iter()
+                        .filter(|s| {
+                            matches!(
+                                s,
+                                CompleteShape::Closed(ClosedShape::Kantsu(_))
+                                    | CompleteShape::Open(OpenShape::Kan(_))
+                            )
+                        })
+                        .collect(),
+                    4,
+                ),
+                _ => continue,
+            };
+
+            if sets.len() < needed {
+                continue;
+            }
+
+            let fed_from = match sets.last() {
+                // the fourth kan has to be called from a discard
+                Some(CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(_))))
+                    if matches!(yaku, Yaku::Suukantsu) =>
+                {
+                    None
+                }
+                Some(CompleteShape::Open(open)) => open
+                    .get_tiles()
+                    .iter()
+                    .map(|t| t.called_from)
+                    .find(|from| *from > 0),
+                _ => None,
+            };
+
+            if fed_from.is_some() {
+                return fed_from;
+            }
+        }
+
+        None
+    }
+
+    /// Payments (relative, 0 = me) for my win with this score, including honba.
+    /// discarder is None for tsumo. With pao, the liable player pays the whole tsumo,
+    /// and a ron off somebody else is split in half between them and the discarder,
+    /// who also pays the honba. With multiple yakuman, the liable player only pays for one of them,
+    /// the rest is paid like without pao.
+    pub fn win_payments(&self, yakus: &[Yaku], score: &Score, discarder: Option<u8>) -> [i32; 4] {
+        self.payments_for(0, yakus, score, discarder)
+    }
+
+    /// Payments of all players for a win of any player, see win_payments.
+    /// Pao is only known for my own hand.
+    fn payments_for(
+        &self,
+        winner: u8,
+        yakus: &[Yaku],
+        score: &Score,
+        discarder: Option<u8>,
+    ) -> [i32; 4] {
+        let rules = self.rules_or_default();
+        let honba = self.get_tsumibo() as i32;
+        let ron_honba = rules.honba_points(false) as i32 * honba;
+        let tsumo_honba = rules.honba_points(true) as i32 * honba;
+        let players = if self.is_sanma() { 2 } else { 3 };
+        let pao_player = if winner == 0 {
+            self.get_pao_player(yakus)
+        } else {
+            None
+        };
+        let yakuman = score.get_yakuman_count().max(1) as i32;
+        let pao_points = score.total_points() as i32 / yakuman;
+        let mut payments = [0i32; 4];
+
+        let mut pay = |player: u8, points: i32| {
+            payments[player as usize] -= points;
+            payments[winner as usize] += points;
+        };
+
+        match (discarder, pao_player) {
+            (None, None) => {
+                let empty_seat = self.get_empty_seat();
+                for (player, paid) in self.tsumo_payments(winner, score).iter().enumerate() {
+                    if player as u8 == winner || Some(player as u8) == empty_seat {
+                        continue;
+                    }
+
+                    pay(player as u8, -paid + tsumo_honba / players);
+                }
+            }
+            (None, Some(liable)) => {
+                pay(liable, pao_points + tsumo_honba);
+
+                let empty_seat = self.get_empty_seat();
+                for (player, paid) in self.tsumo_payments(winner, score).iter().enumerate() {
+                    if player as u8 == winner || Some(player as u8) == empty_seat || yakuman == 1 {
+                        continue;
+                    }
+
+                    // rounded up to 100
+                    pay(
+                        player as u8,
+                        (-paid * (yakuman - 1) / yakuman + 99) / 100 * 100,
+                    );
+                }
+            }
+            (Some(from), Some(liable)) if liable != from => {
+                let half = pao_points / 2;
+                pay(liable, half);
+                pay(from, score.total_points() as i32 - half + ron_honba);
+            }
+            (Some(from), _) => {
+                pay(from, score.total_points() as i32 + ron_honba);
+            }
+        }
+
+        payments
+    }
+
+    /// Nagashi mangan - this player discarded only terminals and honors and none of them were called.
+    pub fn is_nagashi_mangan(&self, player: u8) -> bool {
+        let discards = match self.get_discards(player) {
+            Some(discards) => discards,
+            None => return false,
+        };
+
+        if discards.is_empty()
+            || discards
+                .iter()
+                .any(|t| !t.is_terminal_or_honor() || t.is_open)
+        {
+            return false;
+        }
+
+        // a called discard is in somebody's open shape, with called_from pointing to this player
+        for caller in 0..4u8 {
+            if caller == player {
+                continue;
+            }
+
+            let mut shapes: Vec<OpenShape> = match self.get_hand(caller) {
+                Some(hand) => hand.get_open_shapes(),
+                None => vec![],
+            };
+
+            if caller > 0 {
+                for shape in self.get_open_shapes(caller).into_iter().flatten() {
+                    if let ShapeType::Complete(CompleteShape::Open(open_shape)) =
+                        shape.get_shape_type()
+                    {
+                        shapes.push(*open_shape);
+                    }
+                }
+            }
+
+            let called_from_player = shapes.iter().any(|shape| {
+                shape
+                    .get_tiles()
+                    .iter()
+                    .any(|t| t.called_from > 0 && (caller + t.called_from) % 4 == player)
+            });
+
+            if called_from_player {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Kyuushu kyuuhai - on my first draw with no calls made yet, I can end the round
+    /// when I have 9 or more different terminals and honors.
+    pub fn can_kyuushu_kyuuhai(&self) -> bool {
+        if !self.rules_or_default().kyuushu_kyuuhai
+            || !self.my_discards.is_empty()
+            || self.has_calls()
+        {
+            return false;
+        }
+
+        let hand = match &self.my_hand {
+            None => return false,
+            Some(hand) => hand,
+        };
+
+        if hand.count_tiles() != 14 {
+            return false;
+        }
+
+        let array_34 = hand.get_34_array(false);
+        let kinds = [1, 9, 10, 18, 19, 27, 28, 29, 30, 31, 32, 33, 34]
+            .iter()
+            .filter(|id| array_34[(*id - 1) as usize] > 0)
+            .count();
+
+        kinds >= 9
+    }
+
+    /// Suufon renda - all 4 players discarded the same wind on the first go-around.
+    pub fn is_suufon_renda(&self) -> bool {
+        if !self.rules_or_default().suufon_renda || self.has_calls() {
+            return false;
+        }
+
+        let first = match self.my_discards.first() {
+            Some(tile) if tile.is_wind() => *tile,
+            _ => return false,
+        };
+
+        (0..4).all(|player| {
+            self.get_discards(player)
+                .is_some_and(|discards| discards.len() == 1 && discards[0].eq(&first))
+        })
+    }
+
+    /// Suucha riichi - all 4 players are in riichi.
+    pub fn is_suucha_riichi(&self) -> bool {
+        self.rules_or_default().suucha_riichi
+            && self.did_i_riichi()
+            && self.get_p1_riichi()
+            && self.get_p2_riichi()
+            && self.get_p3_riichi()
+    }
+
+    /// Suukaikan - 4 kans were made, but not all by the same player.
+    pub fn is_suukaikan(&self) -> bool {
+        if !self.rules_or_default().suukaikan {
+            return false;
+        }
+
+        let kans: Vec<usize> = (0..4).map(|player| self.count_kans(player)).collect();
+
+        kans.iter().sum::<usize>() == 4 && !kans.contains(&4)
+    }
+
+    /// Sanchahou - the round is a draw when 3 players ron the same tile.
+    pub fn is_sanchahou(&self, rons: u8) -> bool {
+        self.rules_or_default().sanchahou && rons == 3
+    }
+
+    /// The rules of this table, or the default ones if none were set
+    pub fn rules_or_default(&self) -> Rules {
+        self.rules.clone().unwrap_or_default()
+    }
+
+    /// Has anybody called or made a kan yet?
+    pub fn has_calls(&self) -> bool {
+        let my_shapes = match &self.my_hand {
+            None => false,
+            Some(hand) => !hand.get_shapes().is_empty(),
+        };
+
+        my_shapes
+            || (1..4).any(|player| self.get_open_shapes(player).is_some_and(|s| !s.is_empty()))
+    }
+
+    fn count_kans(&self, player: u8) -> usize {
+        let is_kan = |shape: &CompleteShape| {
+            matches!(
+                shape,
+                CompleteShape::Closed(ClosedShape::Kantsu(_))
+                    | CompleteShape::Open(OpenShape::Kan(_))
+            )
+        };
+
+        if player == 0 {
+            return match &self.my_hand {
+                None => 0,
+                Some(hand) => hand.get_shapes().iter().filter(|s| is_kan(s)).count(),
+            };
+        }
+
+        self.get_open_shapes(player)
+            .into_iter()
+            .flatten()
+            .filter(|shape| match shape.get_shape_type() {
+                ShapeType::Complete(complete_shape) => is_kan(complete_shape),
+                _ => false,
+            })
+            .count()
+    }
+
     /// Can my hand pon this tile? And if so, with which tiles?
     pub fn can_pon(&self, tile: &Tile) -> Option<Vec<Tile>> {
         let hand = self.get_my_hand().clone();
@@ -633,17 +1375,17 @@ impl Table {
             1 => (
                 &self.p1_open_tiles,
                 &self.p1_discards,
-                self.p1_riichi.unwrap(),
+                self.p1_riichi.unwrap_or(false),
             ),
             2 => (
                 &self.p2_open_tiles,
                 &self.p2_discards,
-                self.p2_riichi.unwrap(),
+                self.p2_riichi.unwrap_or(false),
             ),
             3 => (
                 &self.p3_open_tiles,
                 &self.p3_discards,
-                self.p3_riichi.unwrap(),
+                self.p3_riichi.unwrap_or(false),
             ),
             _ => panic!("Wrong player ID"),
         };
@@ -663,6 +1405,7 @@ impl Table {
     }
 }
 
+#[cfg(test)]
 mod tests {
 
     #[test]
@@ -677,6 +1420,55 @@ mod tests {
         assert_eq!(table.get_placing(), 4);
     }
 
+    #[test]
+    fn placing_tie_break() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_initial_seat_wind(2);
+        assert_eq!(table.get_placing(), 2);
+
+        table.set_rules(Rules {
+            tie_break: TieBreak::Split,
+            ..Rules::default()
+        });
+        assert_eq!(table.get_placing(), 1);
+    }
+
+    #[test]
+    fn all_last_conditions() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_seat_wind(1);
+        table.set_my_initial_seat_wind(1);
+        table.set_points(0, 20000);
+        table.set_points(1, 30000);
+
+        let conditions = table.placement_conditions();
+        let first_place = |from: Option<u8>| {
+            conditions
+                .iter()
+                .find(|c| c.player == 0 && c.placing == 1 && c.from == from)
+                .unwrap()
+                .score
+                .clone()
+                .unwrap()
+        };
+
+        // a direct hit needs 5000, I win the tie as the first dealer
+        assert_eq!(first_place(Some(1)).total_points(), 5300);
+        assert_eq!(first_place(Some(2)).total_points(), 10600);
+        assert_eq!(first_place(None).points_from_ko(), 2600);
+
+        // riichi sticks count too
+        table.set_riichi_sticks(2);
+        let conditions = table.placement_conditions();
+        let condition = conditions
+            .iter()
+            .find(|c| c.player == 0 && c.placing == 1 && c.from == Some(2))
+            .unwrap();
+        assert_eq!(condition.score.as_ref().unwrap().total_points(), 8700);
+    }
+
     #[test]
     fn pon_test() {
         use super::*;
@@ -730,11 +1522,468 @@ mod tests {
         table.set_p2_riichi(false);
         table.set_p3_riichi(false);
 
-        table.add_tile_to_discards(1, tile);
-        table.add_tile_to_safe_tiles(1, tile);
+        table.add_tile_to_discards(1, tile).unwrap();
+        table.add_tile_to_safe_tiles(1, tile).unwrap();
 
         let safety = table.tile_safety(&tile);
 
         println!("{}", safety);
     }
+
+    fn draw_table(hands: [&str; 4], my_seat_wind: u8) -> super::Table {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_seat_wind(my_seat_wind);
+
+        for (player, hand) in hands.iter().enumerate() {
+            table
+                .set_hand(player as u8, Hand::from_text(hand, false).unwrap())
+                .unwrap();
+        }
+
+        table
+    }
+
+    #[test]
+    fn noten_payments() {
+        let tenpai = "123m456p789s1122z";
+        let noten = "13579m13579p135s";
+
+        let result = draw_table([tenpai, noten, noten, noten], 1)
+            .exhaustive_draw()
+            .unwrap();
+        assert_eq!(result.tenpai, [true, false, false, false]);
+        assert_eq!(result.payments, [3000, -1000, -1000, -1000]);
+        assert!(result.dealer_repeats);
+
+        // toimen is the dealer when I'm west
+        let result = draw_table([noten, tenpai, noten, tenpai], 3)
+            .exhaustive_draw()
+            .unwrap();
+        assert_eq!(result.payments, [-1500, 1500, -1500, 1500]);
+        assert!(!result.dealer_repeats);
+
+        let result = draw_table([tenpai, tenpai, tenpai, tenpai], 1)
+            .exhaustive_draw()
+            .unwrap();
+        assert_eq!(result.payments, [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn unknown_hand_is_error() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("123m456p789s1122z", false).unwrap());
+
+        assert!(table.exhaustive_draw().is_err());
+    }
+
+    #[test]
+    fn nagashi_mangan() {
+        use super::*;
+        let noten = "13579m13579p135s";
+        let mut table = draw_table([noten, noten, "123m456p789s1122z", noten], 2);
+
+        for tile in ["1m", "9p", "1z", "7z"].iter() {
+            table
+                .add_tile_to_discards(1, Tile::from_text(tile).unwrap())
+                .unwrap();
+        }
+        table
+            .add_tile_to_discards(0, Tile::from_text("1m").unwrap())
+            .unwrap();
+        table
+            .add_tile_to_discards(0, Tile::from_text("5p").unwrap())
+            .unwrap();
+
+        assert!(table.is_nagashi_mangan(1));
+        assert!(!table.is_nagashi_mangan(0));
+
+        // shimocha is not the dealer (kamicha is), toimen stays tenpai but gets no noten payment
+        let result = table.exhaustive_draw().unwrap();
+        assert_eq!(result.nagashi, [false, true, false, false]);
+        assert_eq!(result.payments, [-2000, 8000, -2000, -4000]);
+        assert!(!result.dealer_repeats);
+    }
+
+    #[test]
+    fn called_discard_breaks_nagashi() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+
+        let mut called = Tile::from_text("1z").unwrap();
+        table
+            .add_tile_to_discards(1, Tile::from_text("9s").unwrap())
+            .unwrap();
+        table.add_tile_to_discards(1, called).unwrap();
+        assert!(table.is_nagashi_mangan(1));
+
+        // toimen ponned 1z from their kamicha, who is my shimocha
+        called.called_from = 3;
+        called.is_open = true;
+        let pon = OpenShape::Pon([called, called, called]);
+        table
+            .add_open_shape(
+                2,
+                Shape::new(ShapeType::Complete(CompleteShape::Open(pon)), 3, true),
+            )
+            .unwrap();
+
+        assert!(!table.is_nagashi_mangan(1));
+    }
+
+    #[test]
+    fn kyuushu_kyuuhai() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("19m19p1s1234z2345m6p", false).unwrap());
+        assert!(table.can_kyuushu_kyuuhai());
+
+        table.set_rules(Rules {
+            kyuushu_kyuuhai: false,
+            ..Rules::default()
+        });
+        assert!(!table.can_kyuushu_kyuuhai());
+
+        // only 8 kinds
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("19m19p1s123z23455m6p", false).unwrap());
+        assert!(!table.can_kyuushu_kyuuhai());
+
+        // not my first draw anymore
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("19m19p1s1234z2345m6p", false).unwrap());
+        table
+            .add_tile_to_discards(0, Tile::from_text("5z").unwrap())
+            .unwrap();
+        assert!(!table.can_kyuushu_kyuuhai());
+    }
+
+    #[test]
+    fn suufon_renda() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+
+        for player in 0..3 {
+            table
+                .add_tile_to_discards(player, Tile::from_text("2z").unwrap())
+                .unwrap();
+        }
+        assert!(!table.is_suufon_renda());
+
+        table
+            .add_tile_to_discards(3, Tile::from_text("2z").unwrap())
+            .unwrap();
+        assert!(table.is_suufon_renda());
+
+        table.set_rules(Rules {
+            suufon_renda: false,
+            ..Rules::default()
+        });
+        assert!(!table.is_suufon_renda());
+
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        for player in 0..4 {
+            table
+                .add_tile_to_discards(player, Tile::from_text("5z").unwrap())
+                .unwrap();
+        }
+        assert!(!table.is_suufon_renda());
+    }
+
+    #[test]
+    fn suucha_riichi() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_riichi(true);
+        table.set_p1_riichi(true);
+        table.set_p2_riichi(true);
+        assert!(!table.is_suucha_riichi());
+
+        table.set_p3_riichi(true);
+        assert!(table.is_suucha_riichi());
+    }
+
+    #[test]
+    fn suukaikan() {
+        use super::*;
+        let kan = |tile: &str| {
+            let t = Tile::from_text(tile).unwrap();
+            Shape::new(
+                ShapeType::Complete(CompleteShape::Closed(ClosedShape::Kantsu([t, t, t, t]))),
+                4,
+                false,
+            )
+        };
+
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("123m456p789s1z(k5z1)", false).unwrap());
+        table.add_open_shape(1, kan("1m")).unwrap();
+        table.add_open_shape(1, kan("2m")).unwrap();
+        assert!(!table.is_suukaikan());
+
+        table.add_open_shape(3, kan("3m")).unwrap();
+        assert!(table.is_suukaikan());
+
+        // 4 kans of one player are suukantsu, not a draw
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        for tile in ["1m", "2m", "3m", "4m"].iter() {
+            table.add_open_shape(2, kan(tile)).unwrap();
+        }
+        assert!(!table.is_suukaikan());
+    }
+
+    #[test]
+    fn table_from_json() {
+        use super::*;
+        let table =
+            Table::from_json(r#"{"my_hand": "123m456p789s11155z", "my_seat_wind": 2}"#).unwrap();
+        assert_eq!(table.get_my_seat_wind(), Some(2));
+        assert!(table.get_my_hand_option().is_some());
+
+        assert_eq!(Table::from_json("[]").err().unwrap().code(), 135);
+        assert_eq!(
+            Table::from_json(r#"{"my_hand": "123x"}"#)
+                .err()
+                .unwrap()
+                .code(),
+            Hand::from_text("123x", false).err().unwrap().code()
+        );
+    }
+
+    #[test]
+    fn player_out_of_range() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        let tile = Tile::from_text("1z").unwrap();
+
+        assert_eq!(
+            table.add_tile_to_discards(4, tile),
+            Err(ValidationError::PlayerId(4).into())
+        );
+        // my own calls are in my hand
+        assert_eq!(
+            table.add_tile_to_safe_tiles(0, tile),
+            Err(ValidationError::PlayerId(0).into())
+        );
+        assert!(table
+            .set_hand(5, Hand::from_text("123m456p789s1122z", false).unwrap())
+            .is_err());
+        assert!(table.get_hand(5).is_none());
+        assert!(table.get_discards(4).is_none());
+        assert!(table.get_open_shapes(0).is_none());
+        assert!(!table.is_nagashi_mangan(4));
+    }
+
+    #[test]
+    fn sanchahou() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        assert!(table.is_sanchahou(3));
+        assert!(!table.is_sanchahou(2));
+
+        table.set_rules(Rules {
+            sanchahou: false,
+            ..Rules::default()
+        });
+        assert!(!table.is_sanchahou(3));
+    }
+
+    fn sanma_table(my_seat_wind: u8, tsumo_loss: bool) -> super::Table {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_seat_wind(my_seat_wind);
+        table.set_rules(Rules {
+            sanma: true,
+            tsumo_loss,
+            ..Rules::default()
+        });
+
+        table
+    }
+
+    #[test]
+    fn sanma_noten_payments() {
+        use super::*;
+        let tenpai = "9m456p789s111222z";
+        let noten = "19m13579p13579s1z";
+
+        // I'm east, so the empty north seat is kamicha
+        let mut table = sanma_table(1, true);
+        assert_eq!(table.get_empty_seat(), Some(3));
+
+        for (player, hand) in [tenpai, noten, noten].iter().enumerate() {
+            table
+                .set_hand(player as u8, Hand::from_text_sanma(hand, false).unwrap())
+                .unwrap();
+        }
+
+        let result = table.exhaustive_draw().unwrap();
+        assert_eq!(result.payments, [2000, -1000, -1000, 0]);
+
+        table
+            .set_hand(1, Hand::from_text_sanma(tenpai, false).unwrap())
+            .unwrap();
+        let result = table.exhaustive_draw().unwrap();
+        assert_eq!(result.payments, [1000, 1000, -2000, 0]);
+    }
+
+    #[test]
+    fn sanma_tsumo_payments() {
+        use super::*;
+        // 30 fu 2 han ko tsumo: 500 / 1000
+        let score = Score::new(2, 30, false, true);
+
+        // I'm south, dealer is kamicha, empty seat is toimen
+        let table = sanma_table(2, true);
+        assert_eq!(table.get_empty_seat(), Some(2));
+        assert_eq!(table.tsumo_payments(0, &score), [1500, -500, 0, -1000]);
+
+        let table = sanma_table(2, false);
+        assert_eq!(table.tsumo_payments(0, &score), [2100, -800, 0, -1300]);
+    }
+
+    #[test]
+    fn points_after_win_with_honba() {
+        use super::*;
+        let mut table = sanma_table(2, false);
+        table.set_tsumibo(2);
+        table.set_riichi_sticks(1);
+        let score = Score::new(5, 30, false, true);
+
+        // the empty seat's share is split, and both paying players pay 100 per honba
+        let points = table.points_after_win(1, None, &score);
+        assert_eq!(points, [21800, 34400, 25000, 19800]);
+
+        let score = Score::new(5, 30, false, false);
+        assert_eq!(
+            table.points_after_win(1, Some(0), &score),
+            [16400, 34600, 25000, 25000]
+        );
+    }
+
+    fn pao_table(hand: &str, rules: super::Rules) -> super::Table {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_seat_wind(2);
+        table.set_my_hand(Hand::from_text(hand, false).unwrap());
+        table.set_rules(rules);
+
+        table
+    }
+
+    #[test]
+    fn pao_daisangen() {
+        use super::*;
+        let yakus = [Yaku::Daisangen];
+        let mut table = pao_table("123m11z(p5z1)(p6z2)(p7z3)", Rules::default());
+        assert_eq!(table.get_pao_player(&yakus), Some(3));
+
+        let score = Score::new(13, 0, false, true);
+        assert_eq!(
+            table.win_payments(&yakus, &score, None),
+            [32000, 0, 0, -32000]
+        );
+
+        // ron off the liable player
+        let score = Score::new(13, 0, false, false);
+        assert_eq!(
+            table.win_payments(&yakus, &score, Some(3)),
+            [32000, 0, 0, -32000]
+        );
+
+        // ron off somebody else is split, the discarder pays honba
+        table.set_tsumibo(1);
+        assert_eq!(
+            table.win_payments(&yakus, &score, Some(1)),
+            [32300, -16300, 0, -16000]
+        );
+
+        let table = pao_table(
+            "123m11z(p5z1)(p6z2)(p7z3)",
+            Rules {
+                pao: false,
+                ..Rules::default()
+            },
+        );
+        assert_eq!(table.get_pao_player(&yakus), None);
+    }
+
+    #[test]
+    fn no_pao_when_last_set_is_closed() {
+        use super::*;
+        let table = pao_table("123m11z(p5z1)(p6z2)(k7z)", Rules::default());
+        assert_eq!(table.get_pao_player(&[Yaku::Daisangen]), None);
+
+        let table = pao_table("123m11z777z(p5z1)(p6z2)", Rules::default());
+        assert_eq!(table.get_pao_player(&[Yaku::Daisangen]), None);
+    }
+
+    #[test]
+    fn pao_daisuushii_and_suukantsu() {
+        use super::*;
+        let table = pao_table("1m1m(p1z2)(p2z1)(k3z)(k4z3)", Rules::default());
+        assert_eq!(table.get_pao_player(&[Yaku::Daisuushii]), Some(3));
+
+        let hand = "1m1m(k1p)(k2p1)(k3p)(k4p2)";
+        let table = pao_table(hand, Rules::default());
+        assert_eq!(table.get_pao_player(&[Yaku::Suukantsu]), None);
+
+        let rules = Rules {
+            pao_suukantsu: true,
+            ..Rules::default()
+        };
+        let table = pao_table(hand, rules.clone());
+        assert_eq!(table.get_pao_player(&[Yaku::Suukantsu]), Some(2));
+
+        // an added kan is not fed by anybody
+        let table = pao_table("1m1m(k1p)(k2p1)(k3p)(s4p2)", rules.clone());
+        assert_eq!(table.get_pao_player(&[Yaku::Suukantsu]), None);
+
+        // the last kan is a shouminkan on the first pon
+        let mut hand = Hand::from_text("1m1m4p(p4p2)(k1p)(k2p1)(k3p3)", true).unwrap();
+        hand.add_shouminkan(&Tile::from_text("4p").unwrap())
+            .unwrap();
+        let mut table = pao_table("1m1m(k1p)(k2p1)(k3p)(k4p)", rules);
+        table.set_my_hand(hand);
+        assert_eq!(table.get_pao_player(&[Yaku::Suukantsu]), None);
+    }
+
+    #[test]
+    fn pao_daisangen_with_shouminkan() {
+        use super::*;
+        // upgrading the first pon doesn't complete daisangen again
+        let mut hand = Hand::from_text("123m11z5z(p5z1)(p6z2)(p7z3)", true).unwrap();
+        hand.add_shouminkan(&Tile::from_text("5z").unwrap())
+            .unwrap();
+        let mut table = pao_table("123m11z(p5z1)(p6z2)(p7z3)", Rules::default());
+        table.set_my_hand(hand);
+        assert_eq!(table.get_pao_player(&[Yaku::Daisangen]), Some(3));
+    }
+
+    #[test]
+    fn pao_of_one_yakuman_in_two() {
+        use super::*;
+        let yakus = [Yaku::Daisangen, Yaku::Tsuuiisou];
+        let table = pao_table("22z(p1z1)(p5z1)(p6z2)(p7z3)", Rules::default());
+        assert_eq!(table.get_pao_player(&yakus), Some(3));
+
+        // the liable dealer pays all of daisangen, tsuuiisou is a normal tsumo
+        let mut score = Score::new(13, 0, false, true);
+        score.set_yakuman(true);
+        score.set_yakuman_count(2);
+        assert_eq!(
+            table.win_payments(&yakus, &score, None),
+            [64000, -8000, -8000, -48000]
+        );
+
+        // daisangen is split with the discarder, who pays all of tsuuiisou
+        let mut score = Score::new(13, 0, false, false);
+        score.set_yakuman(true);
+        score.set_yakuman_count(2);
+        assert_eq!(
+            table.win_payments(&yakus, &score, Some(1)),
+            [64000, -48000, 0, -16000]
+        );
+    }
 }
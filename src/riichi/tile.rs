@@ -1,5 +1,5 @@
-use crate::riichi::riichi_error::RiichiError;
-use serde::Serializer;
+use crate::riichi::riichi_error::{ParseError, RiichiError, ValidationError};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::cmp::Ordering;
 use std::fmt;
 use wasm_bindgen::__rt::core::fmt::{Display, Formatter};
@@ -42,7 +42,7 @@ impl TileColor {
             'm' => Ok(TileColor::Manzu),
             'p' => Ok(TileColor::Pinzu),
             's' => Ok(TileColor::Souzu),
-            _ => Err(RiichiError::new(106, "Wrong representation of tile color!")),
+            _ => Err(ParseError::ColorChar(*rep).into()),
         }
     }
 
@@ -103,14 +103,17 @@ impl Tile {
     }
 
     pub fn from_text(representation: &str) -> Result<Tile, RiichiError> {
-        if representation.len() != 2 {
-            return Err(RiichiError::new(105, "Tile length must be 2"));
+        if representation.len() != 2 || !representation.is_ascii() {
+            return Err(ParseError::TileLength.into());
         }
 
         let mut r_chars = representation.chars();
         let first_char = &r_chars.next().unwrap();
         let second_char = &r_chars.next().unwrap();
-        let mut number = first_char.to_string().parse().unwrap();
+        let mut number = match first_char.to_digit(10) {
+            Some(number) => number as u8,
+            None => return Err(ParseError::TileDefinition.into()),
+        };
 
         if ['m', 'p', 's'].contains(second_char) {
             let color: TileColor;
@@ -121,7 +124,7 @@ impl Tile {
             } else if *second_char == 's' {
                 color = TileColor::Souzu;
             } else {
-                return Err(RiichiError::new(102, "Wrong color, only m, p an s allowed"));
+                return Err(ParseError::TileColor(*second_char).into());
             }
 
             // red fives are represented by a 0
@@ -142,10 +145,10 @@ impl Tile {
                 // dragons
                 Ok(Tile::new(TileType::Dragon(number)))
             } else {
-                Err(RiichiError::new(103, "Wrong number for honors!"))
+                Err(ParseError::HonorNumber(number).into())
             }
         } else {
-            Err(RiichiError::new(104, "Invalid tile definition"))
+            Err(ParseError::TileDefinition.into())
         }
     }
 
@@ -153,7 +156,7 @@ impl Tile {
     /// The order is Manzu - Pinzu - Souzu - Winds - Dragons
     pub fn from_id(id: u8) -> Result<Tile, RiichiError> {
         if !(1..=34).contains(&id) {
-            return Err(RiichiError::new(107, &format!("Wrong tile ID {}", id)[..]));
+            return Err(ValidationError::TileId(id).into());
         }
 
         if id <= 9 {
@@ -175,6 +178,43 @@ impl Tile {
         Ok(Tile::new(TileType::Dragon(id - 27)))
     }
 
+    /// Tiles in the order they are written, like discards: "15z19m" = 1z 5z 1m 9m
+    pub fn list_from_text(representation: &str) -> Result<Vec<Tile>, RiichiError> {
+        let mut tiles = vec![];
+        let mut numbers = String::new();
+
+        for ch in representation.chars() {
+            if ch.is_ascii_digit() {
+                numbers.push(ch);
+                continue;
+            }
+
+            for number in numbers.chars() {
+                tiles.push(Tile::from_text(&format!("{}{}", number, ch)[..])?);
+            }
+            numbers.clear();
+        }
+
+        if !numbers.is_empty() {
+            return Err(ParseError::MissingColor.into());
+        }
+
+        Ok(tiles)
+    }
+
+    /// id_136 is the index of a physical tile in a 136 tile set, 4 copies for every tile id.
+    /// 0-3 = 1m, 4-7 = 2m ... 132-135 = 7z
+    pub fn from_id_136(id_136: u8) -> Result<Tile, RiichiError> {
+        if id_136 > 135 {
+            return Err(ValidationError::TileId136(id_136).into());
+        }
+
+        let mut tile = Tile::from_id(id_136 / 4 + 1)?;
+        tile.id_136 = Some(id_136);
+
+        Ok(tile)
+    }
+
     /// Gets the id of this tile based on its type
     pub fn to_id(&self) -> u8 {
         match &self.tile_type {
@@ -380,6 +420,30 @@ impl Tile {
         }
     }
 
+    pub fn is_wind(&self) -> bool {
+        match &self.tile_type {
+            TileType::Wind(_) => true,
+            TileType::Number(_, _) | TileType::Dragon(_) => false,
+        }
+    }
+
+    pub fn is_dragon(&self) -> bool {
+        match &self.tile_type {
+            TileType::Dragon(_) => true,
+            TileType::Number(_, _) | TileType::Wind(_) => false,
+        }
+    }
+
+    /// 2m - 8m are not used in three-player mahjong (sanma)
+    pub fn is_removed_in_sanma(&self) -> bool {
+        match &self.tile_type {
+            TileType::Number(number, color) => {
+                matches!(color, TileColor::Manzu) && *number >= 2 && *number <= 8
+            }
+            TileType::Wind(_) | TileType::Dragon(_) => false,
+        }
+    }
+
     pub fn is_terminal_or_honor(&self) -> bool {
         self.is_terminal() || self.is_honor()
     }
@@ -406,6 +470,83 @@ impl Tile {
         }
     }
 
+    /// The tile from the Unicode mahjong tiles block (U+1F000 - U+1F021).
+    /// Red fives don't have their own character.
+    pub fn to_unicode(&self) -> char {
+        let code = match &self.tile_type {
+            TileType::Number(number, color) => {
+                let first = match color {
+                    TileColor::Manzu => 0x1F007,
+                    TileColor::Souzu => 0x1F010,
+                    TileColor::Pinzu => 0x1F019,
+                };
+                first + *number as u32 - 1
+            }
+            TileType::Wind(number) => 0x1F000 + *number as u32 - 1,
+            // chun, hatsu, haku
+            TileType::Dragon(number) => 0x1F004 + 7 - *number as u32,
+        };
+
+        std::char::from_u32(code).unwrap()
+    }
+
+    /// Unicode character of a face down tile
+    pub fn unicode_back() -> char {
+        '\u{1F02B}'
+    }
+
+    /// English name, like "Three of Bamboo", "Red Five of Dots" or "Red Dragon"
+    pub fn get_name_en(&self) -> String {
+        match &self.tile_type {
+            TileType::Number(number, color) => {
+                let numbers = [
+                    "One", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight", "Nine",
+                ];
+                let suit = match color {
+                    TileColor::Manzu => "Characters",
+                    TileColor::Pinzu => "Dots",
+                    TileColor::Souzu => "Bamboo",
+                };
+                let red = if self.is_red { "Red " } else { "" };
+
+                format!("{}{} of {}", red, numbers[(*number - 1) as usize], suit)
+            }
+            TileType::Wind(number) => {
+                let winds = ["East", "South", "West", "North"];
+                format!("{} Wind", winds[(*number - 1) as usize])
+            }
+            TileType::Dragon(number) => {
+                let dragons = ["White", "Green", "Red"];
+                format!("{} Dragon", dragons[(*number - 5) as usize])
+            }
+        }
+    }
+
+    /// Japanese name in romaji, like "sansou", "akauupin" or "chun"
+    pub fn get_name_jp(&self) -> String {
+        match &self.tile_type {
+            TileType::Number(number, color) => {
+                let numbers = [
+                    "ii", "ryan", "san", "suu", "uu", "rou", "chii", "paa", "kyuu",
+                ];
+                let suit = match color {
+                    TileColor::Manzu => "man",
+                    TileColor::Pinzu => "pin",
+                    TileColor::Souzu => "sou",
+                };
+                let red = if self.is_red { "aka" } else { "" };
+
+                format!("{}{}{}", red, numbers[(*number - 1) as usize], suit)
+            }
+            TileType::Wind(number) => {
+                ["ton", "nan", "shaa", "pei"][(*number - 1) as usize].to_string()
+            }
+            TileType::Dragon(number) => {
+                ["haku", "hatsu", "chun"][(*number - 5) as usize].to_string()
+            }
+        }
+    }
+
     /// Returns an array of 3 values: type, color and number for this tile
     /// TODO red 5s
     fn get_ordering_values(&self) -> [u8; 3] {
@@ -516,6 +657,68 @@ impl Ord for Tile {
     }
 }
 
+/// Structured serde form of a tile with all the flags the mpsz notation can't hold
+#[derive(Serialize, Deserialize)]
+struct TileFields {
+    tile: String,
+    #[serde(default)]
+    is_red: bool,
+    #[serde(default)]
+    is_open: bool,
+    #[serde(default)]
+    is_draw: bool,
+    #[serde(default)]
+    is_chi: bool,
+    #[serde(default)]
+    is_pon: bool,
+    #[serde(default)]
+    called_from: u8,
+    #[serde(default)]
+    is_kan: bool,
+    #[serde(default)]
+    is_riichi: bool,
+    #[serde(default)]
+    is_tsumogiri: bool,
+    #[serde(default)]
+    id_136: Option<u8>,
+}
+
+impl TileFields {
+    fn from_tile(tile: &Tile) -> TileFields {
+        TileFields {
+            tile: tile.to_string(),
+            is_red: tile.is_red,
+            is_open: tile.is_open,
+            is_draw: tile.is_draw,
+            is_chi: tile.is_chi,
+            is_pon: tile.is_pon,
+            called_from: tile.called_from,
+            is_kan: tile.is_kan,
+            is_riichi: tile.is_riichi,
+            is_tsumogiri: tile.is_tsumogiri,
+            id_136: tile.id_136,
+        }
+    }
+
+    fn to_tile(&self) -> Result<Tile, RiichiError> {
+        let mut tile = Tile::from_text(&self.tile[..])?;
+        // "0p" is red by itself
+        tile.is_red |= self.is_red;
+        tile.is_open = self.is_open;
+        tile.is_draw = self.is_draw;
+        tile.is_chi = self.is_chi;
+        tile.is_pon = self.is_pon;
+        tile.called_from = self.called_from;
+        tile.is_kan = self.is_kan;
+        tile.is_riichi = self.is_riichi;
+        tile.is_tsumogiri = self.is_tsumogiri;
+        tile.id_136 = self.id_136;
+
+        Ok(tile)
+    }
+}
+
+/// Tiles are serialized in the mpsz notation ("5m", "0p"), see the fields module for the structured form.
 impl serde::Serialize for Tile {
     fn serialize<S>(&self, serializer: S) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
     where
@@ -525,6 +728,46 @@ impl serde::Serialize for Tile {
     }
 }
 
+/// Reads both the mpsz notation and the structured form
+impl<'de> serde::Deserialize<'de> for Tile {
+    fn deserialize<D>(deserializer: D) -> Result<Tile, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum TileForm {
+            Compact(String),
+            Structured(TileFields),
+        }
+
+        match TileForm::deserialize(deserializer)? {
+            TileForm::Compact(text) => Tile::from_text(&text[..]).map_err(de::Error::custom),
+            TileForm::Structured(fields) => fields.to_tile().map_err(de::Error::custom),
+        }
+    }
+}
+
+/// Structured serde form of a tile with all its flags, for `#[serde(with = "...::tile::fields")]` fields.
+pub mod fields {
+    use super::{Tile, TileFields};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S>(tile: &Tile, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        TileFields::from_tile(tile).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Tile, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Tile::deserialize(deserializer)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -604,4 +847,78 @@ mod tests {
 
         assert_eq!(prev, 7);
     }
+
+    #[test]
+    fn unicode_tiles() {
+        let unicode = |t: &str| Tile::from_text(t).unwrap().to_unicode();
+
+        assert_eq!(unicode("1m"), '\u{1F007}');
+        assert_eq!(unicode("9s"), '\u{1F018}');
+        assert_eq!(unicode("0p"), '\u{1F01D}');
+        assert_eq!(unicode("4z"), '\u{1F003}');
+        assert_eq!(unicode("5z"), '\u{1F006}');
+        assert_eq!(unicode("7z"), '\u{1F004}');
+    }
+
+    #[test]
+    fn tile_names() {
+        let tile = Tile::from_text("7z").unwrap();
+        assert_eq!(tile.get_name_en(), "Red Dragon");
+        assert_eq!(tile.get_name_jp(), "chun");
+
+        let tile = Tile::from_text("0p").unwrap();
+        assert_eq!(tile.get_name_en(), "Red Five of Dots");
+        assert_eq!(tile.get_name_jp(), "akauupin");
+
+        let tile = Tile::from_text("3s").unwrap();
+        assert_eq!(tile.get_name_en(), "Three of Bamboo");
+        assert_eq!(tile.get_name_jp(), "sansou");
+    }
+
+    #[test]
+    fn serde_compact_tile() {
+        let tile = Tile::from_text("0p").unwrap();
+        let json = serde_json::to_string(&tile).unwrap();
+        assert_eq!(json, "\"0p\"");
+
+        let back: Tile = serde_json::from_str(&json).unwrap();
+        assert!(back.is_red);
+        assert_eq!(back, tile);
+
+        assert!(serde_json::from_str::<Tile>("\"8z\"").is_err());
+        assert!(serde_json::from_str::<Tile>("\"xm\"").is_err());
+    }
+
+    #[test]
+    fn serde_structured_tile() {
+        let mut tile = Tile::from_id_136(52).unwrap();
+        tile.is_red = true;
+        tile.is_draw = true;
+        tile.called_from = 2;
+
+        #[derive(Serialize, Deserialize)]
+        struct Discard {
+            #[serde(with = "fields")]
+            tile: Tile,
+        }
+
+        // the default form is always the notation
+        assert_eq!(serde_json::to_string(&tile).unwrap(), "\"0p\"");
+
+        let json = serde_json::to_string(&Discard { tile }).unwrap();
+        let back = serde_json::from_str::<Discard>(&json).unwrap().tile;
+
+        assert_eq!(back.to_string(), "0p");
+        assert!(back.is_draw);
+        assert_eq!(back.called_from, 2);
+        assert_eq!(back.id_136, Some(52));
+
+        let partial: Tile =
+            serde_json::from_str("{\"tile\": \"3s\", \"is_riichi\": true}").unwrap();
+        assert!(partial.is_riichi);
+        assert!(!partial.is_open);
+
+        let red: Tile = serde_json::from_str("{\"tile\": \"0p\"}").unwrap();
+        assert!(red.is_red);
+    }
 }
@@ -0,0 +1,400 @@
+use crate::riichi::riichi_error::{IllegalAction, RiichiError, RulesError};
+use crate::riichi::rules::{KanDoraTiming, Rules};
+use crate::riichi::tile::Tile;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+/// Size of the dead wall (wanpai)
+const DEAD_WALL_SIZE: usize = 14;
+/// Indexes of the dora indicators in the dead wall, ura dora indicators are right next to them
+const DORA_POSITIONS: [usize; 5] = [4, 6, 8, 10, 12];
+
+/// A shuffled wall of 136 tiles, or 108 tiles without 2m-8m for sanma.
+///
+/// The last 14 tiles are the dead wall:
+/// - 0-3 are the rinshan tiles drawn after kans
+/// - 4, 6, 8, 10, 12 are the dora indicators
+/// - 5, 7, 9, 11, 13 are the ura dora indicators under them
+///
+/// Every rinshan draw moves the end of the live wall by one tile, so haitei comes earlier.
+/// In sanma, the replacement tiles for kita are drawn the same way as rinshan tiles.
+pub struct Wall {
+    tiles: Vec<Tile>,
+    sanma: bool,
+    kan_dora_timing: KanDoraTiming,
+    /// index of the next tile drawn from the live wall
+    next_draw: usize,
+    rinshan_drawn: usize,
+    dora_revealed: usize,
+    /// kans whose dora indicator will be revealed after the next discard
+    pending_kan_dora: usize,
+}
+
+impl Wall {
+    /// Shuffles a new wall. The same seed always gives the same wall.
+    pub fn new(seed: u64, aka_count: u8) -> Result<Wall, RiichiError> {
+        Wall::shuffle(seed, aka_count, false)
+    }
+
+    /// Shuffles a new sanma wall of 108 tiles. There is no red 5m, so there are at most 3 red fives.
+    pub fn new_sanma(seed: u64, aka_count: u8) -> Result<Wall, RiichiError> {
+        Wall::shuffle(seed, aka_count, true)
+    }
+
+    fn shuffle(seed: u64, aka_count: u8, sanma: bool) -> Result<Wall, RiichiError> {
+        if aka_count > 4 {
+            return Err(RulesError::RedFiveCount(aka_count).into());
+        }
+
+        // red fives are the first copies of 5m (16), 5p (52) and 5s (88), the 4th one is a second 5p (53)
+        let red_ids: Vec<u8> = [52, 16, 88, 53][..aka_count as usize].to_vec();
+
+        let mut tiles = vec![];
+        for id_136 in 0..136u8 {
+            let mut tile = Tile::from_id_136(id_136)?;
+            if sanma && tile.is_removed_in_sanma() {
+                continue;
+            }
+
+            tile.is_red = red_ids.contains(&id_136);
+            tiles.push(tile);
+        }
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        tiles.shuffle(&mut rng);
+
+        Ok(Wall {
+            tiles,
+            sanma,
+            kan_dora_timing: KanDoraTiming::ClosedImmediate,
+            next_draw: 0,
+            rinshan_drawn: 0,
+            dora_revealed: 1,
+            pending_kan_dora: 0,
+        })
+    }
+
+    /// Shuffles a new wall with the red fives, kan dora timing and number of players from these rules.
+    pub fn from_rules(seed: u64, rules: &Rules) -> Result<Wall, RiichiError> {
+        let aka_count = if rules.aka_ari { rules.aka_count } else { 0 };
+        let mut wall = Wall::shuffle(seed, aka_count, rules.sanma)?;
+        wall.kan_dora_timing = rules.kan_dora_timing;
+
+        Ok(wall)
+    }
+
+    /// Deals 13 tiles to every player - 3 times 4 tiles, then 1 tile, starting with the dealer.
+    /// Players are absolute seats 0-3, in sanma seat 3 stays empty.
+    pub fn deal(&mut self, dealer: u8) -> Result<[Vec<Tile>; 4], RiichiError> {
+        if self.next_draw > 0 {
+            return Err(IllegalAction::AlreadyDealt.into());
+        }
+
+        let mut hands: [Vec<Tile>; 4] = [vec![], vec![], vec![], vec![]];
+        let players = self.players();
+
+        for round in 0..4 {
+            let count = if round < 3 { 4 } else { 1 };
+            for i in 0..players {
+                let player = ((dealer + i) % players) as usize;
+                for _ in 0..count {
+                    hands[player].push(self.tiles[self.next_draw]);
+                    self.next_draw += 1;
+                }
+            }
+        }
+
+        Ok(hands)
+    }
+
+    /// Draws the next tile from the live wall
+    pub fn draw(&mut self) -> Option<Tile> {
+        if self.tiles_remaining() == 0 {
+            return None;
+        }
+
+        let tile = self.tiles[self.next_draw];
+        self.next_draw += 1;
+
+        Some(tile)
+    }
+
+    /// Draws a replacement tile from the dead wall after a kan (or kita in sanma).
+    pub fn draw_rinshan(&mut self) -> Option<Tile> {
+        // 4 kans, plus 4 kita in sanma
+        let max_replacements = if self.sanma { 8 } else { 4 };
+        if self.rinshan_drawn >= max_replacements || self.tiles_remaining() == 0 {
+            return None;
+        }
+
+        // after the first 4, the dead wall is refilled from the end of the live wall
+        let tile = if self.rinshan_drawn < 4 {
+            self.dead_wall()[self.rinshan_drawn]
+        } else {
+            self.tiles[self.live_wall_end() - 1]
+        };
+        self.rinshan_drawn += 1;
+
+        Some(tile)
+    }
+
+    /// Tiles left in the live wall
+    pub fn tiles_remaining(&self) -> u8 {
+        (self.live_wall_end() - self.next_draw) as u8
+    }
+
+    /// A kan has been made. Returns the dora indicators revealed right now - the new one and those
+    /// still waiting from a previous open kan. Otherwise the new indicator is revealed by `discard`.
+    pub fn kan(&mut self, closed: bool) -> Vec<Tile> {
+        let immediate = match self.kan_dora_timing {
+            KanDoraTiming::Immediate => true,
+            KanDoraTiming::AfterDiscard => false,
+            KanDoraTiming::ClosedImmediate => closed,
+        };
+
+        // a previous open kan's dora comes first
+        let mut revealed = self.reveal_pending();
+
+        if immediate {
+            if let Some(indicator) = self.reveal_next() {
+                revealed.push(indicator);
+            }
+        } else {
+            self.pending_kan_dora += 1;
+        }
+
+        revealed
+    }
+
+    /// The kan player discarded (or made another kan) - reveals dora indicators that were waiting for that.
+    pub fn discard(&mut self) -> Vec<Tile> {
+        self.reveal_pending()
+    }
+
+    /// Currently visible dora indicators
+    pub fn dora_indicators(&self) -> Vec<Tile> {
+        DORA_POSITIONS[..self.dora_revealed]
+            .iter()
+            .map(|i| self.dead_wall()[*i])
+            .collect()
+    }
+
+    /// Ura dora indicators under the visible dora indicators
+    pub fn ura_dora_indicators(&self) -> Vec<Tile> {
+        DORA_POSITIONS[..self.dora_revealed]
+            .iter()
+            .map(|i| self.dead_wall()[*i + 1])
+            .collect()
+    }
+
+    pub fn is_sanma(&self) -> bool {
+        self.sanma
+    }
+
+    /// All tiles in drawing order, the dead wall is at the end
+    pub fn get_tiles(&self) -> &Vec<Tile> {
+        &self.tiles
+    }
+
+    fn players(&self) -> u8 {
+        if self.sanma {
+            3
+        } else {
+            4
+        }
+    }
+
+    fn reveal_pending(&mut self) -> Vec<Tile> {
+        let mut revealed = vec![];
+        while self.pending_kan_dora > 0 {
+            self.pending_kan_dora -= 1;
+            if let Some(indicator) = self.reveal_next() {
+                revealed.push(indicator);
+            }
+        }
+
+        revealed
+    }
+
+    fn reveal_next(&mut self) -> Option<Tile> {
+        if self.dora_revealed >= DORA_POSITIONS.len() {
+            return None;
+        }
+
+        self.dora_revealed += 1;
+        Some(self.dead_wall()[DORA_POSITIONS[self.dora_revealed - 1]])
+    }
+
+    fn dead_wall(&self) -> &[Tile] {
+        &self.tiles[self.tiles.len() - DEAD_WALL_SIZE..]
+    }
+
+    fn live_wall_end(&self) -> usize {
+        self.tiles.len() - DEAD_WALL_SIZE - self.rinshan_drawn
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_wall() {
+        let wall_1 = Wall::new(42, 3).unwrap();
+        let wall_2 = Wall::new(42, 3).unwrap();
+        let wall_3 = Wall::new(43, 3).unwrap();
+
+        let ids = |wall: &Wall| -> Vec<u8> {
+            wall.get_tiles().iter().map(|t| t.id_136.unwrap()).collect()
+        };
+
+        assert_eq!(ids(&wall_1), ids(&wall_2));
+        assert_ne!(ids(&wall_1), ids(&wall_3));
+    }
+
+    #[test]
+    fn all_136_tiles() {
+        let wall = Wall::new(1, 3).unwrap();
+        let mut ids: Vec<u8> = wall.get_tiles().iter().map(|t| t.id_136.unwrap()).collect();
+        ids.sort_unstable();
+
+        assert_eq!(ids, (0..136).collect::<Vec<u8>>());
+
+        for tile in wall.get_tiles().iter() {
+            assert_eq!(tile.to_id(), tile.id_136.unwrap() / 4 + 1);
+        }
+    }
+
+    #[test]
+    fn aka_count() {
+        for aka in 0..5 {
+            let wall = Wall::new(7, aka).unwrap();
+            let reds = wall.get_tiles().iter().filter(|t| t.is_red).count();
+            assert_eq!(reds, aka as usize);
+        }
+
+        assert!(Wall::new(7, 5).is_err());
+
+        let rules = Rules {
+            aka_ari: false,
+            ..Rules::default()
+        };
+        let wall = Wall::from_rules(7, &rules).unwrap();
+        assert_eq!(wall.get_tiles().iter().filter(|t| t.is_red).count(), 0);
+    }
+
+    #[test]
+    fn deal_and_draw() {
+        let mut wall = Wall::new(3, 3).unwrap();
+        let hands = wall.deal(2).unwrap();
+
+        for hand in hands.iter() {
+            assert_eq!(hand.len(), 13);
+        }
+
+        // the dealer gets the first 4 tiles
+        assert_eq!(hands[2][0].id_136, wall.get_tiles()[0].id_136);
+        assert_eq!(wall.tiles_remaining(), 70);
+        assert!(wall.deal(2).is_err());
+
+        let first = wall.draw().unwrap();
+        assert_eq!(first.id_136, wall.get_tiles()[52].id_136);
+        assert_eq!(wall.tiles_remaining(), 69);
+
+        for _ in 0..69 {
+            assert!(wall.draw().is_some());
+        }
+        assert!(wall.draw().is_none());
+    }
+
+    #[test]
+    fn rinshan_moves_haitei() {
+        let mut wall = Wall::new(5, 3).unwrap();
+        wall.deal(0).unwrap();
+
+        let rinshan = wall.draw_rinshan().unwrap();
+        assert_eq!(rinshan.id_136, wall.get_tiles()[122].id_136);
+        assert_eq!(wall.tiles_remaining(), 69);
+
+        for _ in 0..3 {
+            assert!(wall.draw_rinshan().is_some());
+        }
+        assert!(wall.draw_rinshan().is_none());
+        assert_eq!(wall.tiles_remaining(), 66);
+    }
+
+    #[test]
+    fn dora_and_ura_positions() {
+        let wall = Wall::new(11, 3).unwrap();
+        let dora = wall.dora_indicators();
+        let ura = wall.ura_dora_indicators();
+
+        assert_eq!(dora.len(), 1);
+        assert_eq!(dora[0].id_136, wall.get_tiles()[126].id_136);
+        assert_eq!(ura[0].id_136, wall.get_tiles()[127].id_136);
+    }
+
+    #[test]
+    fn kan_dora_timing() {
+        let mut rules = Rules {
+            kan_dora_timing: KanDoraTiming::ClosedImmediate,
+            ..Rules::default()
+        };
+        let mut wall = Wall::from_rules(9, &rules).unwrap();
+        assert_eq!(wall.kan(true).len(), 1);
+        assert!(wall.kan(false).is_empty());
+        assert_eq!(wall.dora_indicators().len(), 2);
+        assert_eq!(wall.discard().len(), 1);
+        assert_eq!(wall.dora_indicators().len(), 3);
+
+        // an open kan's indicator is revealed by the next kan
+        assert!(wall.kan(false).is_empty());
+        assert_eq!(wall.kan(true).len(), 2);
+
+        rules.kan_dora_timing = KanDoraTiming::AfterDiscard;
+        let mut wall = Wall::from_rules(9, &rules).unwrap();
+        assert!(wall.kan(true).is_empty());
+        assert_eq!(wall.discard().len(), 1);
+
+        rules.kan_dora_timing = KanDoraTiming::Immediate;
+        let mut wall = Wall::from_rules(9, &rules).unwrap();
+        assert_eq!(wall.kan(false).len(), 1);
+        assert!(wall.discard().is_empty());
+
+        for _ in 0..3 {
+            wall.kan(false);
+        }
+        assert_eq!(wall.dora_indicators().len(), 5);
+        assert!(wall.kan(false).is_empty());
+    }
+
+    #[test]
+    fn sanma_wall() {
+        let rules = Rules {
+            sanma: true,
+            ..Rules::default()
+        };
+        let mut wall = Wall::from_rules(13, &rules).unwrap();
+
+        assert!(wall.is_sanma());
+        assert_eq!(wall.get_tiles().len(), 108);
+        assert!(!wall.get_tiles().iter().any(|t| t.is_removed_in_sanma()));
+        // no red 5m
+        assert_eq!(wall.get_tiles().iter().filter(|t| t.is_red).count(), 2);
+
+        let hands = wall.deal(1).unwrap();
+        assert_eq!(hands[0].len(), 13);
+        assert_eq!(hands[1].len(), 13);
+        assert_eq!(hands[2].len(), 13);
+        assert!(hands[3].is_empty());
+        assert_eq!(wall.tiles_remaining(), 55);
+
+        // 4 kita + 4 kans
+        for _ in 0..8 {
+            assert!(wall.draw_rinshan().is_some());
+        }
+        assert!(wall.draw_rinshan().is_none());
+        assert_eq!(wall.tiles_remaining(), 47);
+    }
+}
@@ -1,13 +1,15 @@
+use crate::riichi::fu::{count_fu, total_fu, Fu};
 use crate::riichi::scores::Score;
 use crate::riichi::shape_finder::ShapeFinder;
 use crate::riichi::shapes::{ClosedShape, CompleteShape, OpenKan, OpenShape, Shape, ShapeType};
 use crate::riichi::table::Table;
 use crate::riichi::tile::{Tile, TileType};
 use enum_iterator::IntoEnumIterator;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use wasm_bindgen::__rt::std::collections::hash_map::Entry;
 
-#[derive(IntoEnumIterator, Debug, Clone)]
+#[derive(IntoEnumIterator, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Yaku {
     // 1 han closed
     MenzenTsumo,
@@ -64,6 +66,16 @@ pub enum Yaku {
     // special
     Tenhou,
     Chiihou,
+    // local yaku, only with their Rules flag on
+    Renhou,
+    Daisharin,
+    OpenRiichi,
+    IshiueSannen,
+    TsubameGaeshi,
+    Kanburi,
+    Shiisanpuutaa,
+    Paarenchan,
+    Sanrenkou,
 }
 
 pub struct YakuFinder {}
@@ -74,220 +86,105 @@ impl YakuFinder {
     }
 
     /// Finds the best variant of the hand + its score
-    pub fn find(&self, mut table: &mut Table) -> Option<(Vec<Yaku>, Score)> {
+    pub fn find(&self, table: &mut Table) -> Option<(Vec<Yaku>, Score)> {
+        self.find_with_fu(table)
+            .map(|(yakus, score, _fu)| (yakus, score))
+    }
+
+    /// Same as find, with where the fu of the best variant come from.
+    /// The fu are not counted for yakuman and hands of 5 han and more.
+    pub fn find_with_fu(&self, table: &mut Table) -> Option<(Vec<Yaku>, Score, Vec<Fu>)> {
         // only complete hands
-        let mut hand = &mut table.get_my_hand().clone();
+        let mut hand = table.get_my_hand().clone();
 
         if hand.shanten() != -1 {
+            // the only yaku without a complete hand
+            if Yaku::Shiisanpuutaa.is_in_hand(table, &[]) {
+                let mut score =
+                    Score::with_rules(13, 0, table.am_i_oya(), true, &table.rules_or_default());
+                score.set_yakuman(true);
+                return Some((vec![Yaku::Shiisanpuutaa], score, vec![]));
+            }
+
             return None;
         }
 
         let mut sf = ShapeFinder::new();
         let variants = sf.find(&mut hand);
-        let mut best_variant: (Vec<Yaku>, Score) = (vec![], Score::new(0, 0, false, false));
+        let mut best_variant: (Vec<Yaku>, Score, Vec<Fu>) =
+            (vec![], Score::new(0, 0, false, false), vec![]);
 
         for (i, variant) in variants.iter().enumerate() {
-            let mut yakus: Vec<Yaku> = vec![];
-            let mut han: u8 = 0;
-            let mut fu: u8 = 0;
-
-            // first find potential yakumans
-            for yaku_type in Yaku::into_enum_iter() {
-                if !yaku_type.is_yakuman() {
-                    continue;
-                }
+            let (yakus, score, fu) = self.score_variant(table, variant);
 
-                if yaku_type.is_in_hand(&mut table, variant) {
-                    yakus.push(yaku_type.clone());
-                }
+            if i == 0 || score.total_points() > best_variant.1.total_points() {
+                best_variant = (yakus, score, fu);
             }
+        }
 
-            if !yakus.is_empty() {
-                han = 13;
-            } else {
-                for yaku_type in Yaku::into_enum_iter() {
-                    if yaku_type.is_yakuman() {
-                        continue;
-                    }
-
-                    if yaku_type.is_in_hand(&mut table, variant) {
-                        match yaku_type {
-                            Yaku::Pinfu => {
-                                if table.did_i_tsumo() {
-                                    fu = 20;
-                                } else {
-                                    fu = 30;
-                                }
-                            }
-                            Yaku::Chiitoitsu => {
-                                fu = 25;
-                            }
-                            _ => (),
-                        }
+        Some(best_variant)
+    }
 
-                        yakus.push(yaku_type.clone());
-                        han += yaku_type.get_han(table);
-                    }
-                }
+    /// Yaku, score and fu of one decomposition (ShapeFinder variant) of my winning hand
+    pub fn score_variant(
+        &self,
+        mut table: &mut Table,
+        variant: &[Shape],
+    ) -> (Vec<Yaku>, Score, Vec<Fu>) {
+        let mut yakus: Vec<Yaku> = vec![];
+        let mut han: u8 = 0;
+
+        // first find potential yakumans
+        for yaku_type in Yaku::into_enum_iter() {
+            if !yaku_type.is_yakuman() {
+                continue;
             }
 
-            if han >= 5 {
-                fu = 0;
-            } else if han > 0 && fu == 0 {
-                // if we did not set fu based on yaku, we search shapes for fu
-                if table.did_i_tsumo() {
-                    fu = 20;
-                } else {
-                    fu = 30;
-                }
-
-                // set this for tanki, shanpon, kanchan and penchan waits
-                let mut has_value_wait = false;
-
-                let winning_tile = table.get_my_winning_tile();
-
-                for shape in variant {
-                    match shape.get_shape_type() {
-                        ShapeType::Complete(cs) => {
-                            match cs {
-                                CompleteShape::Closed(closed) => {
-                                    match closed {
-                                        ClosedShape::Shuntsu(tiles) => {
-                                            if tiles[1].eq(&winning_tile) || // kanchan
-                                                tiles[0].prev_id(false, 1) == 0 && tiles[2].eq(&winning_tile) ||
-                                                tiles[2].next_id(false, 1) == 0 && tiles[0].eq(&winning_tile)
-                                            {
-                                                // penchans
-                                                has_value_wait = true;
-                                            }
-                                        }
-                                        ClosedShape::Koutsu(tiles) => {
-                                            match tiles[0].tile_type {
-                                                TileType::Number(value, _) => {
-                                                    if value == 1 || value == 9 {
-                                                        fu += 8;
-                                                    } else {
-                                                        fu += 4;
-                                                    }
-                                                }
-                                                TileType::Wind(_) | TileType::Dragon(_) => {
-                                                    fu += 8;
-                                                }
-                                            }
-
-                                            if tiles[0].eq(&winning_tile) {
-                                                has_value_wait = true;
-                                            }
-                                        }
-                                        ClosedShape::Kantsu(tiles) => {
-                                            match tiles[0].tile_type {
-                                                TileType::Number(value, _) => {
-                                                    if value == 1 || value == 9 {
-                                                        fu += 32;
-                                                    } else {
-                                                        fu += 16;
-                                                    }
-                                                }
-                                                TileType::Wind(_) | TileType::Dragon(_) => {
-                                                    fu += 32;
-                                                }
-                                            }
-
-                                            if tiles[0].eq(&winning_tile) {
-                                                panic!("Can't win on a tile in kan");
-                                            }
-                                        }
-                                        ClosedShape::Toitsu(tiles) => {
-                                            match tiles[0].tile_type {
-                                                TileType::Number(_, _) => {}
-                                                TileType::Wind(value) => {
-                                                    // prevalent wind: +2 fu
-                                                    // my wind: another +2 fu
-                                                    match table.get_prevalent_wind() {
-                                                        None => {}
-                                                        Some(pw) => {
-                                                            if pw == value {
-                                                                fu += 2;
-                                                            }
-                                                        }
-                                                    }
-
-                                                    match table.get_my_seat_wind() {
-                                                        None => {}
-                                                        Some(msw) => {
-                                                            if msw == value {
-                                                                fu += 2;
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                                TileType::Dragon(_) => {
-                                                    fu += 2;
-                                                }
-                                            }
-
-                                            if tiles[0].eq(&winning_tile) {
-                                                has_value_wait = true;
-                                            }
-                                        }
-                                        ClosedShape::Single(_) => {}
-                                    }
-                                }
+            if yaku_type.is_in_hand(&mut table, variant) {
+                yakus.push(yaku_type.clone());
+            }
+        }
 
-                                CompleteShape::Open(open) => match open {
-                                    OpenShape::Chi(_) => {}
-                                    OpenShape::Pon(tiles) => match tiles[0].tile_type {
-                                        TileType::Number(value, _) => {
-                                            if value == 1 || value == 9 {
-                                                fu += 4;
-                                            } else {
-                                                fu += 2;
-                                            }
-                                        }
-                                        TileType::Wind(_) | TileType::Dragon(_) => {
-                                            fu += 4;
-                                        }
-                                    },
-                                    OpenShape::Kan(open_kan) => {
-                                        let tiles = match open_kan {
-                                            OpenKan::Daiminkan(tls) | OpenKan::Shouminkan(tls) => {
-                                                tls
-                                            }
-                                        };
-
-                                        match tiles[0].tile_type {
-                                            TileType::Number(value, _) => {
-                                                if value == 1 || value == 9 {
-                                                    fu += 8;
-                                                } else {
-                                                    fu += 4;
-                                                }
-                                            }
-                                            TileType::Wind(_) | TileType::Dragon(_) => {
-                                                fu += 8;
-                                            }
-                                        }
-                                    }
-                                },
-                            }
-                        }
-                        ShapeType::Incomplete(..) => {}
-                    }
+        if !yakus.is_empty() {
+            han = 13;
+        } else {
+            for yaku_type in Yaku::into_enum_iter() {
+                if yaku_type.is_yakuman() {
+                    continue;
                 }
 
-                if has_value_wait {
-                    fu += 2;
+                if yaku_type.is_in_hand(&mut table, variant) {
+                    yakus.push(yaku_type.clone());
+                    han += yaku_type.get_han(table);
                 }
             }
+        }
 
-            let score = Score::new(han, fu, table.am_i_oya(), table.did_i_tsumo());
-            if i == 0 || score.total_points() > best_variant.1.total_points() {
-                best_variant = (yakus, score);
-            }
+        let yakuman_count = yakus.iter().filter(|y| y.is_yakuman()).count() as u8;
+        let yakuman = yakuman_count > 0;
+
+        // dora only count with a yaku, kita set aside in sanma count as dora too
+        if han > 0 && !yakuman {
+            han += table.count_my_dora() + table.get_my_hand().get_kita_count();
         }
 
-        println!("{:#?}", best_variant);
-        Some(best_variant)
+        let fu = if han > 0 && han < 5 && !yakuman {
+            count_fu(table, variant, &yakus)
+        } else {
+            vec![]
+        };
+
+        let mut score = Score::with_rules(
+            han,
+            total_fu(&fu),
+            table.am_i_oya(),
+            table.did_i_tsumo(),
+            &table.rules_or_default(),
+        );
+        score.set_yakuman(yakuman);
+        score.set_yakuman_count(yakuman_count);
+
+        (yakus, score, fu)
     }
 }
 
@@ -350,6 +247,15 @@ impl Yaku {
             Yaku::Suukantsu => "Suukantsu",
             Yaku::Tenhou => "Tenhou",
             Yaku::Chiihou => "Chiihou",
+            Yaku::Renhou => "Renhou",
+            Yaku::Daisharin => "Daisharin",
+            Yaku::OpenRiichi => "Open riichi",
+            Yaku::IshiueSannen => "Ishiue sannen",
+            Yaku::TsubameGaeshi => "Tsubame gaeshi",
+            Yaku::Kanburi => "Kanburi",
+            Yaku::Shiisanpuutaa => "Shiisanpuutaa",
+            Yaku::Paarenchan => "Paarenchan",
+            Yaku::Sanrenkou => "Sanrenkou",
         }
     }
 
@@ -445,14 +351,31 @@ impl Yaku {
             Yaku::Suukantsu => 13,
             Yaku::Tenhou => 13,
             Yaku::Chiihou => 13,
+            Yaku::Renhou => 13,
+            Yaku::Daisharin => 13,
+            Yaku::OpenRiichi => 1,
+            Yaku::IshiueSannen => 13,
+            Yaku::TsubameGaeshi => 1,
+            Yaku::Kanburi => 1,
+            Yaku::Shiisanpuutaa => 13,
+            Yaku::Paarenchan => 13,
+            Yaku::Sanrenkou => 2,
         }
     }
 
     /// Check if this Yaku exists in this shape variant
     fn is_in_hand(&self, table: &mut Table, variant: &[Shape]) -> bool {
+        if !self.is_enabled(table) {
+            return false;
+        }
+
         match self {
             Yaku::MenzenTsumo => return table.get_my_hand().is_closed() && table.did_i_tsumo(),
-            Yaku::Riichi => return table.get_my_hand().is_closed() && table.did_i_riichi(),
+            Yaku::Riichi => {
+                return table.get_my_hand().is_closed()
+                    && table.did_i_riichi()
+                    && !table.did_i_double_riichi()
+            }
             Yaku::Ippatsu => {
                 if !table.get_my_hand().is_closed() || !table.did_i_riichi() {
                     return false;
@@ -643,7 +566,9 @@ impl Yaku {
             Yaku::WhiteDragons => return self.find_yakuhai(variant, 32),
             Yaku::GreenDragons => return self.find_yakuhai(variant, 33),
             Yaku::RedDragons => return self.find_yakuhai(variant, 34),
-            Yaku::DoubleRiichi => {} // TODO
+            Yaku::DoubleRiichi => {
+                return table.get_my_hand().is_closed() && table.did_i_double_riichi()
+            }
             Yaku::Chanta => {
                 // a chanta has to have shuntsu, else it's honroutou
                 let mut has_shuntsu = false;
@@ -968,8 +893,6 @@ impl Yaku {
                 }
             }
             Yaku::SanshokuDoukou => {
-                println!("{:#?}", variant);
-
                 let mut combos: HashMap<String, [bool; 3]> = HashMap::new();
 
                 for shape in variant.iter() {
@@ -1670,6 +1593,37 @@ impl Yaku {
                 }
                 // TODO
             }
+            Yaku::Renhou => {
+                // ron before my first draw, nobody called anything
+                return table.get_my_hand().is_closed()
+                    && !table.did_i_tsumo()
+                    && !table.am_i_oya()
+                    && table.get_my_discards().is_empty()
+                    && !table.has_calls();
+            }
+            Yaku::Daisharin => {
+                let array_34 = table.get_my_hand().get_34_array(false);
+
+                // a pair of each 2p - 8p
+                return table.get_my_hand().is_closed() && (10..=16).all(|i| array_34[i] == 2);
+            }
+            Yaku::OpenRiichi => {
+                return table.get_my_hand().is_closed() && table.did_i_open_riichi();
+            }
+            Yaku::IshiueSannen => {
+                return table.did_i_double_riichi() && table.get_tiles_remaining() == Some(0);
+            }
+            Yaku::TsubameGaeshi => {
+                return !table.did_i_tsumo()
+                    && matches!(table.get_riichi_declaring_player(), Some(player) if player != 0);
+            }
+            Yaku::Kanburi => {
+                return !table.did_i_tsumo()
+                    && matches!(table.get_kan_declaring_player(), Some(player) if player != 0);
+            }
+            Yaku::Shiisanpuutaa => return self.is_shiisanpuutaa(table),
+            Yaku::Paarenchan => return table.am_i_oya() && table.get_renchan() >= 7,
+            Yaku::Sanrenkou => return self.find_sanrenkou(variant),
         }
 
         false
@@ -1690,6 +1644,11 @@ impl Yaku {
                 | Yaku::Suukantsu
                 | Yaku::Tenhou
                 | Yaku::Chiihou
+                | Yaku::Renhou
+                | Yaku::Daisharin
+                | Yaku::IshiueSannen
+                | Yaku::Shiisanpuutaa
+                | Yaku::Paarenchan
         ) {
             return true;
         }
@@ -1697,6 +1656,111 @@ impl Yaku {
         false
     }
 
+    /// Local yaku that need to be turned on in Rules
+    pub fn is_local(&self) -> bool {
+        matches!(
+            self,
+            Yaku::Renhou
+                | Yaku::Daisharin
+                | Yaku::OpenRiichi
+                | Yaku::IshiueSannen
+                | Yaku::TsubameGaeshi
+                | Yaku::Kanburi
+                | Yaku::Shiisanpuutaa
+                | Yaku::Paarenchan
+                | Yaku::Sanrenkou
+        )
+    }
+
+    /// Standard yaku are always enabled, local yaku depend on the table's rules
+    pub fn is_enabled(&self, table: &Table) -> bool {
+        let rules = table.rules_or_default();
+
+        match self {
+            Yaku::Renhou => rules.renhou,
+            Yaku::Daisharin => rules.daisharin,
+            Yaku::OpenRiichi => rules.open_riichi,
+            Yaku::IshiueSannen => rules.ishiue_sannen,
+            Yaku::TsubameGaeshi => rules.tsubame_gaeshi,
+            Yaku::Kanburi => rules.kanburi,
+            Yaku::Shiisanpuutaa => rules.shiisanpuutaa,
+            Yaku::Paarenchan => rules.paarenchan,
+            Yaku::Sanrenkou => rules.sanrenkou,
+            _ => true,
+        }
+    }
+
+    /// 13 tiles that don't form any shape + one pair, on the first draw
+    fn is_shiisanpuutaa(&self, table: &Table) -> bool {
+        let hand = table.get_my_hand();
+        if !hand.is_closed()
+            || hand.count_tiles() != 14
+            || !table.did_i_tsumo()
+            || !table.get_my_discards().is_empty()
+            || table.has_calls()
+        {
+            return false;
+        }
+
+        let array_34 = hand.get_34_array(false);
+        if array_34.iter().any(|count| *count > 2)
+            || array_34.iter().filter(|count| **count == 2).count() != 1
+        {
+            return false;
+        }
+
+        // no number tiles close enough for a ryanmen, kanchan or penchan
+        for i in 0..27 {
+            if array_34[i] == 0 {
+                continue;
+            }
+
+            let suit_end = (i / 9 + 1) * 9;
+            if array_34[(i + 1)..suit_end.min(i + 3)]
+                .iter()
+                .any(|count| *count > 0)
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Three triplets (or kans) of consecutive numbers in one suit
+    fn find_sanrenkou(&self, variant: &[Shape]) -> bool {
+        let mut ids = vec![];
+
+        for shape in variant.iter() {
+            match shape.get_shape_type() {
+                ShapeType::Complete(cs) => match cs {
+                    CompleteShape::Closed(closed) => match closed {
+                        ClosedShape::Koutsu(tiles) => ids.push(tiles[0].to_id()),
+                        ClosedShape::Kantsu(tiles) => ids.push(tiles[0].to_id()),
+                        _ => (),
+                    },
+                    CompleteShape::Open(open) => match open {
+                        OpenShape::Chi(_) => {}
+                        OpenShape::Pon(tiles) => ids.push(tiles[0].to_id()),
+                        OpenShape::Kan(open_kan) => {
+                            let tiles = match open_kan {
+                                OpenKan::Daiminkan(tls) | OpenKan::Shouminkan(tls) => tls,
+                            };
+
+                            ids.push(tiles[0].to_id());
+                        }
+                    },
+                },
+                ShapeType::Incomplete(..) => return false,
+            }
+        }
+
+        // number tiles only, 1 - 7 can start the run
+        ids.iter().any(|id| {
+            *id <= 27 && (*id - 1) % 9 <= 6 && ids.contains(&(id + 1)) && ids.contains(&(id + 2))
+        })
+    }
+
     fn find_yakuhai(&self, variant: &[Shape], tile_id: u8) -> bool {
         for shape in variant.iter() {
             match shape.get_shape_type() {
@@ -1855,6 +1919,8 @@ impl Yaku {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::riichi::hand::Hand;
+    use crate::riichi::rules::Rules;
     use serde_json::{Map, Value};
 
     #[test]
@@ -2262,4 +2328,194 @@ mod tests {
             _ => false,
         });
     }
+
+    #[test]
+    fn kita_counts_as_dora() {
+        let mut map = Map::new();
+        map.insert("my_tsumo".to_string(), Value::from(true));
+
+        let mut hand = Hand::from_text_sanma("111m234s456789p14z", false).unwrap();
+        hand.nuki_kita().unwrap();
+        let mut drawn = Tile::from_text("1z").unwrap();
+        drawn.is_draw = true;
+        hand.add_tile(drawn);
+
+        let mut table = Table::from_map(&map).unwrap();
+        table.set_my_hand(hand);
+        let (_yakus, score) = table.yaku().unwrap();
+
+        // menzen tsumo + kita
+        assert_eq!(score.han, 2);
+    }
+
+    #[test]
+    fn dora_and_red_fives() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("123m406p789s11155z"));
+        map.insert("my_tsumo".to_string(), Value::from(true));
+        map.insert("my_riichi".to_string(), Value::from(true));
+        map.insert("dora_indicators".to_string(), Value::from("4z"));
+
+        let mut table = Table::from_map(&map).unwrap();
+        let (_yakus, score) = table.yaku().unwrap();
+
+        // menzen tsumo + riichi + 3 dora + red 5p
+        assert_eq!(score.han, 6);
+
+        // all 4 tiles of a kan count, dora without a yaku don't
+        table.set_my_hand(Hand::from_text("123m456p78s55z9s(k1z)", false).unwrap());
+        table.set_my_riichi(false);
+        table.set_dora_indicators(vec![Tile::from_text("4z").unwrap()]);
+        assert_eq!(table.count_my_dora(), 4);
+        assert_eq!(table.yaku().unwrap().1.han, 5);
+
+        table.set_my_tsumo(false);
+        assert_eq!(table.yaku().unwrap().1.han, 0);
+    }
+
+    fn local_yaku_table(hand: &str, tsumo: bool, rules: Rules) -> Table {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from(hand));
+        map.insert("my_tsumo".to_string(), Value::from(tsumo));
+        map.insert("my_seat_wind".to_string(), Value::from(2));
+
+        let mut table = Table::from_map(&map).unwrap();
+        table.set_rules(rules);
+
+        table
+    }
+
+    fn all_local_yaku() -> Rules {
+        Rules {
+            renhou: true,
+            daisharin: true,
+            open_riichi: true,
+            ishiue_sannen: true,
+            tsubame_gaeshi: true,
+            kanburi: true,
+            shiisanpuutaa: true,
+            paarenchan: true,
+            sanrenkou: true,
+            ..Rules::default()
+        }
+    }
+
+    #[test]
+    fn local_yaku_are_off_by_default() {
+        let mut table = local_yaku_table("22334455667788p", false, Rules::default());
+        let (yakus, _score) = table.yaku().unwrap();
+
+        assert!(yakus.iter().all(|y| !y.is_local()));
+    }
+
+    #[test]
+    fn find_daisharin() {
+        let mut table = local_yaku_table("22334455667788p", false, all_local_yaku());
+        table
+            .add_tile_to_discards(0, Tile::from_text("1z").unwrap())
+            .unwrap();
+        let (yakus, score) = table.yaku().unwrap();
+
+        assert!(matches!(yakus[..], [Yaku::Daisharin]));
+        assert_eq!(score.han, 13);
+    }
+
+    #[test]
+    fn find_sanrenkou() {
+        let mut table = local_yaku_table("456p11z(p1m1)(p2m2)(p3m3)", false, all_local_yaku());
+        let (yakus, score) = table.yaku().unwrap();
+
+        assert!(matches!(yakus[..], [Yaku::Sanrenkou]));
+        assert_eq!(score.han, 2);
+    }
+
+    #[test]
+    fn multiple_yakuman_add_up() {
+        let hand = "11122z(p5z1)(p6z2)(p7z3)";
+        let mut table = local_yaku_table(hand, false, Rules::default());
+        let (yakus, score) = table.yaku().unwrap();
+
+        assert!(matches!(yakus[..], [Yaku::Daisangen, Yaku::Tsuuiisou]));
+        assert_eq!(score.get_yakuman_count(), 2);
+        assert_eq!(score.total_points(), 64000);
+
+        let mut table = local_yaku_table(hand, false, Rules::ema());
+        let (_yakus, score) = table.yaku().unwrap();
+        assert_eq!(score.total_points(), 32000);
+    }
+
+    #[test]
+    fn find_renhou() {
+        let mut table = local_yaku_table("123m456p789s11155z", false, all_local_yaku());
+        let (yakus, _score) = table.yaku().unwrap();
+        assert!(matches!(yakus[..], [Yaku::Renhou]));
+
+        // not after my first discard
+        table
+            .add_tile_to_discards(0, Tile::from_text("1z").unwrap())
+            .unwrap();
+        assert!(table.yaku().unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn find_tsubame_gaeshi_and_kanburi() {
+        let mut table = local_yaku_table("123m456p789s11155z", false, all_local_yaku());
+        table
+            .add_tile_to_discards(0, Tile::from_text("1z").unwrap())
+            .unwrap();
+        table.set_riichi_declaring_player(1);
+        table.set_kan_declaring_player(1);
+        let (yakus, score) = table.yaku().unwrap();
+
+        assert!(matches!(yakus[..], [Yaku::TsubameGaeshi, Yaku::Kanburi]));
+        assert_eq!(score.han, 2);
+    }
+
+    #[test]
+    fn find_shiisanpuutaa() {
+        let mut table = local_yaku_table("14m258p369s123455z", true, all_local_yaku());
+        let (yakus, _score) = table.yaku().unwrap();
+        assert!(matches!(yakus[..], [Yaku::Shiisanpuutaa]));
+
+        let mut table = local_yaku_table("14m258p369s123455z", true, Rules::default());
+        assert!(table.yaku().is_none());
+    }
+
+    #[test]
+    fn find_paarenchan() {
+        let mut table = local_yaku_table("123m456p789s11z(p5m2)", false, all_local_yaku());
+        table.set_my_seat_wind(1);
+        table.set_renchan(7);
+        let (yakus, _score) = table.yaku().unwrap();
+
+        assert!(matches!(yakus[..], [Yaku::Paarenchan]));
+    }
+
+    #[test]
+    fn find_open_riichi() {
+        let mut table = local_yaku_table("123m456p789s11155z", true, all_local_yaku());
+        table
+            .add_tile_to_discards(0, Tile::from_text("1z").unwrap())
+            .unwrap();
+        table.set_my_open_riichi(true);
+        let (yakus, score) = table.yaku().unwrap();
+
+        assert!(matches!(
+            yakus[..],
+            [Yaku::MenzenTsumo, Yaku::Riichi, Yaku::OpenRiichi]
+        ));
+        assert_eq!(score.han, 3);
+    }
+
+    #[test]
+    fn serde_yaku_list() {
+        let mut table = local_yaku_table("123m456p789s11155z", true, Rules::default());
+        let (yakus, _score) = table.yaku().unwrap();
+
+        let json = serde_json::to_string(&yakus).unwrap();
+        assert_eq!(json, "[\"MenzenTsumo\"]");
+
+        let back: Vec<Yaku> = serde_json::from_str(&json).unwrap();
+        assert!(matches!(back[..], [Yaku::MenzenTsumo]));
+    }
 }
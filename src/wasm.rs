@@ -0,0 +1,152 @@
+//! Functions exported to JavaScript with wasm-bindgen, enabled by the `wasm` feature.
+//! Hands are in the mpsz notation, tables are JSON objects with the keys of Table::from_map.
+//! Results are JSON strings, errors are thrown as JSON strings with code, message, offset and token.
+
+use crate::riichi::analysis;
+use crate::riichi::hand::Hand;
+use crate::riichi::riichi_error::{RiichiError, ValidationError};
+use crate::riichi::table::Table;
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+/// Shanten of a hand, -1 is a complete hand
+#[wasm_bindgen]
+pub fn shanten(hand: &str) -> Result<i8, JsValue> {
+    shanten_value(hand).map_err(js_error)
+}
+
+/// Tiles that lower the shanten, for every useful discard of a 14 tile hand:
+/// [{"discard": "1z", "tiles": [{"tile": "3m", "count": 4}], "total": 4}]
+#[wasm_bindgen]
+pub fn ukeire(hand: &str) -> Result<String, JsValue> {
+    ukeire_value(hand)
+        .map(|value| value.to_string())
+        .map_err(js_error)
+}
+
+/// Yaku and score of the winning hand on this table, null when there is no yaku
+#[wasm_bindgen]
+pub fn yaku(table: &str) -> Result<String, JsValue> {
+    yaku_value(table)
+        .map(|value| value.to_string())
+        .map_err(js_error)
+}
+
+/// Waits of a 13 tile tenpai hand with the number of tiles left, empty when not tenpai
+#[wasm_bindgen]
+pub fn waits(hand: &str) -> Result<String, JsValue> {
+    waits_value(hand)
+        .map(|value| value.to_string())
+        .map_err(js_error)
+}
+
+/// Safety of every tile in my hand against the opponents on this table, 0.0 - 1.0.
+/// Every opponent adds their tenpai probability / 3 when the tile is safe against them,
+/// so genbutsu against one player in riichi is 1/3 and only a tile safe against three tenpai players is 1.0.
+#[wasm_bindgen(js_name = tableSafety)]
+pub fn table_safety(table: &str) -> Result<String, JsValue> {
+    table_safety_value(table)
+        .map(|value| value.to_string())
+        .map_err(js_error)
+}
+
+fn shanten_value(hand: &str) -> Result<i8, RiichiError> {
+    Ok(Hand::from_text(hand, false)?.shanten())
+}
+
+fn ukeire_value(hand: &str) -> Result<Value, RiichiError> {
+    let mut hand = Hand::from_text(hand, false)?;
+
+    Ok(json!(analysis::ukeire(&mut hand)))
+}
+
+fn yaku_value(table: &str) -> Result<Value, RiichiError> {
+    let mut table = Table::from_json(table)?;
+    if table.get_my_hand_option().is_none() {
+        return Err(ValidationError::UnknownHand(0).into());
+    }
+
+    Ok(json!(analysis::score(&mut table)))
+}
+
+fn waits_value(hand: &str) -> Result<Value, RiichiError> {
+    let mut hand = Hand::from_text(hand, false)?;
+
+    Ok(json!(analysis::waits(&mut hand)))
+}
+
+fn table_safety_value(table: &str) -> Result<Value, RiichiError> {
+    let table = Table::from_json(table)?;
+    let hand = match table.get_my_hand_option() {
+        None => return Err(ValidationError::UnknownHand(0).into()),
+        Some(hand) => hand,
+    };
+
+    let mut tiles = hand.get_closed_tiles();
+    tiles.sort();
+    tiles.dedup();
+
+    Ok(tiles
+        .iter()
+        .map(|tile| json!({"tile": tile.to_string(), "safety": table.tile_safety(tile)}))
+        .collect())
+}
+
+fn js_error(error: RiichiError) -> JsValue {
+    JsValue::from_str(&json!(error).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ukeire_of_14_tiles() {
+        let options = ukeire_value("123m456p789s23m115z").unwrap();
+
+        assert_eq!(options.as_array().unwrap().len(), 1);
+        assert_eq!(options[0]["discard"], "5z");
+        assert_eq!(options[0]["total"], 7);
+        assert_eq!(options[0]["tiles"][0], json!({"tile": "1m", "count": 3}));
+    }
+
+    #[test]
+    fn yaku_and_score() {
+        let table = r#"{"my_hand": "123m456p789s11155z", "my_tsumo": true, "my_riichi": true}"#;
+        let result = yaku_value(table).unwrap();
+
+        assert_eq!(result["yaku"], json!(["MenzenTsumo", "Riichi"]));
+        assert_eq!(result["han"], 2);
+    }
+
+    #[test]
+    fn waits_of_tenpai_hand() {
+        assert_eq!(
+            waits_value("123m456p789s1155z").unwrap(),
+            json!([{"tile": "1z", "count": 2}, {"tile": "5z", "count": 2}])
+        );
+        assert_eq!(waits_value("123m456p789s1357z").unwrap(), json!([]));
+    }
+
+    #[test]
+    fn safety_against_riichi() {
+        let table =
+            r#"{"my_hand": "23666m234p456s44z6m", "p1_riichi": true, "p1_discards": "19m2m"}"#;
+        let result = table_safety_value(table).unwrap();
+
+        assert_eq!(result[0]["tile"], "2m");
+        assert!((result[0]["safety"].as_f64().unwrap() - 1.0 / 3.0).abs() < 0.0001);
+        assert_eq!(result[1]["safety"], 0.0);
+    }
+
+    #[test]
+    fn errors_as_json() {
+        let error = shanten_value("123m456p789s1z(p1z4)").unwrap_err();
+
+        assert_eq!(
+            json!(error),
+            json!({"code": 131, "message": "Wrong meld index 4, must be 1 - 3", "offset": 14, "token": "(p1z4)"})
+        );
+        assert_eq!(yaku_value("{").unwrap_err().code(), 135);
+    }
+}
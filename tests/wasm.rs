@@ -0,0 +1,45 @@
+//! JavaScript API tests, run in Node with `wasm-pack test --node -- --features wasm`
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use riichi_tools_rs::wasm;
+use serde_json::Value;
+use wasm_bindgen_test::*;
+
+fn parse(json: &str) -> Value {
+    serde_json::from_str(json).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn shanten_and_waits() {
+    assert_eq!(wasm::shanten("123m456p789s1155z").unwrap(), 0);
+
+    let waits = parse(&wasm::waits("123m456p789s1155z").unwrap());
+    assert_eq!(waits[0]["tile"], "1z");
+    assert_eq!(waits[1]["tile"], "5z");
+}
+
+#[wasm_bindgen_test]
+fn ukeire_and_yaku() {
+    let ukeire = parse(&wasm::ukeire("123m456p789s23m115z").unwrap());
+    assert_eq!(ukeire[0]["discard"], "5z");
+
+    let result =
+        parse(&wasm::yaku(r#"{"my_hand": "123m456p789s11155z", "my_tsumo": true}"#).unwrap());
+    assert_eq!(result["yaku"][0], "MenzenTsumo");
+}
+
+#[wasm_bindgen_test]
+fn table_safety() {
+    let table = r#"{"my_hand": "23666m234p456s44z6m", "p1_riichi": true, "p1_discards": "2m"}"#;
+    let safeties = parse(&wasm::table_safety(table).unwrap());
+    assert_eq!(safeties[0]["tile"], "2m");
+}
+
+#[wasm_bindgen_test]
+fn errors_are_thrown_as_json() {
+    let error = wasm::shanten("123m456p789s1z(p1z4)").unwrap_err();
+    let error = parse(&error.as_string().unwrap());
+
+    assert_eq!(error["code"], 131);
+    assert_eq!(error["offset"], 14);
+}